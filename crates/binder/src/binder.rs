@@ -1,14 +1,16 @@
 use crate::typeenv::Symbol;
 use itertools::{EitherOrBoth, Itertools};
-use typua_parser::annotation::AnnotationTag;
-use typua_parser::ast::{Stmt, TypeAst};
+use typua_parser::annotation::{AnnotationInfo, AnnotationTag};
+use typua_parser::ast::{Block, FunctionDeclaration, Stmt, TypeAst, Variable};
 use typua_ty::TypeKind;
 
+use crate::registry::{ClassDecl, FieldDecl, TypeRegistry};
 use crate::typeenv::TypeEnv;
 
 #[derive(Debug, Clone, Default)]
 pub struct Binder {
     pub type_env: TypeEnv,
+    pub registry: TypeRegistry,
     // flowgraph: FlowGraph,
 }
 
@@ -16,6 +18,7 @@ impl Binder {
     pub fn new() -> Self {
         Self {
             type_env: TypeEnv::new(),
+            registry: TypeRegistry::new(),
             // flowgraph: FlowGraph::new(),
         }
     }
@@ -23,14 +26,20 @@ impl Binder {
         self.type_env.clone()
     }
     pub fn bind(&mut self, ast: &TypeAst) {
-        for stmt in ast.block.stmts.iter() {
+        self.bind_block(&ast.block);
+    }
+    /// bind locals declared directly in the block, nested function bodies are not visited
+    pub fn bind_block(&mut self, block: &Block) {
+        for stmt in block.stmts.iter() {
             match stmt {
                 Stmt::LocalAssign(local_assign) => {
-                    for pair in local_assign
-                        .vars
+                    self.register_annotations(&local_assign.annotates);
+                    // documentation annotations don't bind types
+                    let type_annotates = local_assign
+                        .annotates
                         .iter()
-                        .zip_longest(local_assign.annotates.iter())
-                    {
+                        .filter(|ann| matches!(ann.tag, AnnotationTag::Type(_)));
+                    for pair in local_assign.vars.iter().zip_longest(type_annotates) {
                         match pair {
                             EitherOrBoth::Both(var, ann) => {
                                 let _ = match &ann.tag {
@@ -48,9 +57,183 @@ impl Binder {
                             EitherOrBoth::Right(_ann) => (),
                         }
                     }
+                    // ---@class Name above `local Name = {}` makes the local the class
+                    if let (Some(var), Some(class)) = (
+                        local_assign.vars.first(),
+                        class_name(&local_assign.annotates),
+                    ) {
+                        let _ = self
+                            .type_env
+                            .insert(&Symbol::new(var.name.clone()), &TypeKind::Custom(class));
+                    }
+                    // ---@type T name overrides the positional one
+                    for ann in local_assign.annotates.iter() {
+                        if let AnnotationTag::NamedType { ty, name } = &ann.tag
+                            && local_assign.vars.iter().any(|var| var.name == *name)
+                        {
+                            let _ = self.type_env.insert(&Symbol::new(name.clone()), ty);
+                        }
+                    }
+                }
+                Stmt::LocalFunction(local_function) => {
+                    self.register_annotations(&local_function.annotates);
+                    let ty = self.function_type(&local_function.params, &local_function.annotates);
+                    let _ = self
+                        .type_env
+                        .insert(&Symbol::new(local_function.name.name.clone()), &ty);
+                }
+                // declares a field or a global, not a local
+                Stmt::FunctionDeclaration(function) => {
+                    self.register_annotations(&function.annotates);
+                    let ty = self.function_type(&function.params, &function.annotates);
+                    if function.fields.is_empty() && function.method.is_none() {
+                        let _ = self
+                            .type_env
+                            .insert(&Symbol::new(function.name.name.clone()), &ty);
+                    } else {
+                        self.register_method(function, ty);
+                    }
                 }
-                _ => unimplemented!(),
+                // locals in branches are bound when the branch is checked
+                Stmt::Assign(_)
+                | Stmt::FunctionCall(_)
+                | Stmt::Goto(_)
+                | Stmt::Label(_)
+                | Stmt::Return(_)
+                | Stmt::If(_)
+                | Stmt::While(_)
+                | Stmt::Repeat(_)
+                | Stmt::Break(_) => (),
+            }
+        }
+    }
+    /// type of a function from its ---@param and ---@return annotations
+    ///   ---@param x T binds the param named x, ---@param x any binds it as any
+    ///   unannotated params are unknown, which is not checked either but tells them apart
+    ///   the annotated types are registered as references to validate
+    pub fn function_type(&mut self, params: &[Variable], annotates: &[AnnotationInfo]) -> TypeKind {
+        let params_names = params.iter().map(|param| param.name.clone()).collect();
+        let params: Vec<TypeKind> = params
+            .iter()
+            .map(|param| {
+                annotates
+                    .iter()
+                    .find_map(|ann| match &ann.tag {
+                        AnnotationTag::Param { name, ty, .. } if *name == param.name => {
+                            Some(ty.clone())
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or(TypeKind::Unknown)
+            })
+            .collect();
+        for ann in annotates.iter() {
+            if let AnnotationTag::Param { ty, .. } | AnnotationTag::Return { ty, .. } = &ann.tag {
+                self.registry.add_reference(ty, &ann.span);
             }
         }
+        let (returns, return_names): (Vec<TypeKind>, Vec<Option<String>>) = annotates
+            .iter()
+            .filter_map(|ann| match &ann.tag {
+                AnnotationTag::Return { ty, name } => Some((ty.clone(), name.clone())),
+                _ => None,
+            })
+            .unzip();
+        TypeKind::Function {
+            generics: Vec::new(),
+            param_names: params_names,
+            params,
+            returns,
+            return_names: if return_names.iter().any(Option::is_some) {
+                return_names
+            } else {
+                Vec::new()
+            },
+            vararg: None,
+        }
     }
+    /// `function Class:m() end` or `function Class.m() end` declares the field m of the class
+    ///   a method takes the instance as its first param, self
+    fn register_method(&mut self, function: &FunctionDeclaration, ty: TypeKind) {
+        let Some(TypeKind::Custom(class)) =
+            self.type_env.get(&Symbol::new(function.name.name.clone()))
+        else {
+            return;
+        };
+        let (name, is_method) = match (function.fields.as_slice(), &function.method) {
+            ([], Some(method)) => (method, true),
+            ([field], None) => (field, false),
+            _ => return,
+        };
+        let ty = match ty {
+            TypeKind::Function {
+                generics,
+                mut param_names,
+                mut params,
+                returns,
+                return_names,
+                vararg,
+            } if is_method => {
+                param_names.insert(0, "self".to_string());
+                params.insert(0, TypeKind::Custom(class.clone()));
+                TypeKind::Function {
+                    generics,
+                    param_names,
+                    params,
+                    returns,
+                    return_names,
+                    vararg,
+                }
+            }
+            ty => ty,
+        };
+        self.registry.add_field(
+            &class,
+            FieldDecl {
+                name: name.name.clone(),
+                ty,
+                span: function.span.clone(),
+            },
+        );
+    }
+    /// ---@class followed by its ---@field, and ---@alias
+    fn register_annotations(&mut self, annotates: &[AnnotationInfo]) {
+        let mut class: Option<ClassDecl> = None;
+        for ann in annotates.iter() {
+            match &ann.tag {
+                AnnotationTag::Class { name, exact } => {
+                    if let Some(class) = class.take() {
+                        self.registry.register_class(class);
+                    }
+                    class = Some(ClassDecl {
+                        name: name.clone(),
+                        exact: *exact,
+                        fields: Vec::new(),
+                    });
+                }
+                AnnotationTag::Field { name, ty, .. } => match class.as_mut() {
+                    Some(class) => class.fields.push(FieldDecl {
+                        name: name.clone(),
+                        ty: ty.clone(),
+                        span: ann.span.clone(),
+                    }),
+                    // field without class is still validated
+                    None => self.registry.add_reference(ty, &ann.span),
+                },
+                AnnotationTag::Alias { name, ty } => self.registry.register_alias(name, ty),
+                _ => (),
+            }
+        }
+        if let Some(class) = class {
+            self.registry.register_class(class);
+        }
+    }
+}
+
+/// name of the last ---@class in the annotations
+fn class_name(annotates: &[AnnotationInfo]) -> Option<String> {
+    annotates.iter().rev().find_map(|ann| match &ann.tag {
+        AnnotationTag::Class { name, .. } => Some(name.clone()),
+        _ => None,
+    })
 }
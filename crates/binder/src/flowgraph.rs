@@ -1,11 +1,10 @@
-pub struct FlowGraph {
-
-}
+// not wired into Binder yet
+#[allow(dead_code)]
+pub struct FlowGraph {}
 
+#[allow(dead_code)]
 impl FlowGraph {
     pub fn new() -> Self {
-        Self {
-
-        }
+        Self {}
     }
 }
@@ -1,6 +1,8 @@
 mod binder;
-mod typeenv;
 mod flowgraph;
+mod registry;
+mod typeenv;
 
-pub use typeenv::{TypeEnv, Symbol};
 pub use binder::Binder;
+pub use registry::{ClassDecl, FieldDecl, TypeRegistry};
+pub use typeenv::{Symbol, TypeEnv};
@@ -0,0 +1,403 @@
+use std::path::{Path, PathBuf};
+
+use im::HashMap;
+use typua_span::Span;
+use typua_ty::TypeKind;
+use typua_ty::diagnostic::Diagnostic;
+use typua_ty::message::Message;
+
+/// ---@class Name
+/// ---@field name type
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDecl {
+    pub name: String,
+    /// ---@class (exact), fields not declared can't be read
+    pub exact: bool,
+    pub fields: Vec<FieldDecl>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDecl {
+    pub name: String,
+    pub ty: TypeKind,
+    pub span: Span,
+}
+
+/// type written in field, param or return annotation
+#[derive(Debug, Clone, PartialEq)]
+struct TypeReference {
+    ty: TypeKind,
+    span: Span,
+    file: Option<PathBuf>,
+}
+
+/// declaration with the file it was registered from
+#[derive(Debug, Clone, PartialEq)]
+struct Declared<T> {
+    decl: T,
+    file: Option<PathBuf>,
+}
+
+/// named types declared by annotations
+///   classes and aliases are registered while binding,
+///   references to them are validated after all of them are registered
+///   every entry remembers its source file, so a single file can be replaced
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+    classes: HashMap<String, Declared<ClassDecl>>,
+    aliases: HashMap<String, Declared<TypeKind>>,
+    references: Vec<TypeReference>,
+    /// file of the entries registered from now on
+    file: Option<PathBuf>,
+    /// number is accepted where integer is expected, `runtime.integer_strict = false`
+    integer_lax: bool,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// registry whose entries are registered from `path`
+    pub fn for_file(path: &Path) -> Self {
+        Self {
+            file: Some(path.to_path_buf()),
+            ..Self::default()
+        }
+    }
+    /// whether a number may have a fraction where an integer is expected, true by default
+    pub fn set_integer_strict(&mut self, strict: bool) {
+        self.integer_lax = !strict;
+    }
+    /// `TypeKind::subtype` under the policy of `set_integer_strict`
+    ///   strict => number is not integer
+    ///   lax    => number and integer are interchangeable
+    pub fn subtype(&self, sub_ty: &TypeKind, sup_ty: &TypeKind) -> bool {
+        let widen = |ty: &TypeKind| {
+            ty.replace(&|part| (*part == TypeKind::Integer).then_some(TypeKind::Number))
+        };
+        TypeKind::subtype(sub_ty, sup_ty)
+            || (self.integer_lax && TypeKind::subtype(&widen(sub_ty), &widen(sup_ty)))
+    }
+    pub fn register_class(&mut self, class: ClassDecl) {
+        for field in class.fields.iter() {
+            self.add_reference(&field.ty, &field.span);
+        }
+        self.classes.insert(
+            class.name.clone(),
+            Declared {
+                decl: class,
+                file: self.file.clone(),
+            },
+        );
+    }
+    pub fn register_alias(&mut self, name: &str, ty: &TypeKind) {
+        self.aliases.insert(
+            name.to_string(),
+            Declared {
+                decl: ty.clone(),
+                file: self.file.clone(),
+            },
+        );
+    }
+    /// field declared by code rather than ---@field, `function Class:m() end`
+    ///   ignored if the class is unknown or already has the field
+    pub fn add_field(&mut self, class: &str, field: FieldDecl) {
+        if let Some(class) = self.classes.get_mut(class)
+            && !class.decl.fields.iter().any(|f| f.name == field.name)
+        {
+            class.decl.fields.push(field);
+        }
+    }
+    pub fn add_reference(&mut self, ty: &TypeKind, span: &Span) {
+        self.references.push(TypeReference {
+            ty: ty.clone(),
+            span: span.clone(),
+            file: self.file.clone(),
+        });
+    }
+    /// merge the entries of `other`, keeping their source files
+    ///   a name declared in both takes the declaration of `other`
+    pub fn extend(&mut self, other: TypeRegistry) {
+        self.classes.extend(other.classes);
+        self.aliases.extend(other.aliases);
+        self.references.extend(other.references);
+    }
+    /// replace the entries of `path` with those of `registry`
+    pub fn add_file(&mut self, path: &Path, mut registry: TypeRegistry) {
+        self.remove_file(path);
+        let file = Some(path.to_path_buf());
+        for (_, class) in registry.classes.iter_mut() {
+            class.file = file.clone();
+        }
+        for (_, alias) in registry.aliases.iter_mut() {
+            alias.file = file.clone();
+        }
+        for reference in registry.references.iter_mut() {
+            reference.file = file.clone();
+        }
+        self.extend(registry);
+    }
+    /// drop every class, alias and reference registered from `path`
+    pub fn remove_file(&mut self, path: &Path) {
+        let from_path = |file: &Option<PathBuf>| file.as_deref() == Some(path);
+        self.classes.retain(|_, class| !from_path(&class.file));
+        self.aliases.retain(|_, alias| !from_path(&alias.file));
+        self.references
+            .retain(|reference| !from_path(&reference.file));
+    }
+    pub fn get_class(&self, name: &str) -> Option<&ClassDecl> {
+        self.classes.get(name).map(|class| &class.decl)
+    }
+    pub fn get_alias(&self, name: &str) -> Option<&TypeKind> {
+        self.aliases.get(name).map(|alias| &alias.decl)
+    }
+    /// the type with alias names replaced by their types, class names are kept
+    ///   ---@alias Handler fun(e: string): boolean => Handler is fun(e: string): boolean
+    ///   an alias referring to itself is left as a name where it recurs
+    pub fn resolve(&self, ty: &TypeKind) -> TypeKind {
+        self.resolve_within(ty, &[])
+    }
+    fn resolve_within(&self, ty: &TypeKind, resolving: &[&str]) -> TypeKind {
+        ty.replace(&|part| match part {
+            TypeKind::Custom(name) if !resolving.contains(&name.as_str()) => {
+                let alias = self.get_alias(name)?;
+                let mut resolving = resolving.to_vec();
+                resolving.push(name);
+                Some(self.resolve_within(alias, &resolving))
+            }
+            _ => None,
+        })
+    }
+    /// names of the classes and aliases declared differently in `self` and `other`,
+    /// including those declared in only one of them
+    pub fn changed_names(&self, other: &TypeRegistry) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .classes
+            .keys()
+            .chain(self.aliases.keys())
+            .chain(other.classes.keys())
+            .chain(other.aliases.keys())
+            .filter(|name| {
+                self.classes.get(*name) != other.classes.get(*name)
+                    || self.aliases.get(*name) != other.aliases.get(*name)
+            })
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+    pub fn is_known(&self, name: &str) -> bool {
+        self.classes.contains_key(name) || self.aliases.contains_key(name)
+    }
+    /// every named type in references must be a declared class or alias
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.validate_references(|_| true)
+    }
+    /// `validate` for the references registered from `path`
+    pub fn validate_file(&self, path: &Path) -> Vec<Diagnostic> {
+        self.validate_references(|reference| reference.file.as_deref() == Some(path))
+    }
+    fn validate_references(&self, filter: impl Fn(&TypeReference) -> bool) -> Vec<Diagnostic> {
+        self.references
+            .iter()
+            .filter(|reference| filter(reference))
+            .flat_map(|reference| {
+                custom_names(&reference.ty)
+                    .into_iter()
+                    .filter(|name| !self.is_known(name))
+                    .map(|name| {
+                        Diagnostic::new(
+                            Message::UnknownType {
+                                name: name.to_string(),
+                            },
+                            reference.span.clone(),
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+/// names of classes and aliases used in the type
+fn custom_names(ty: &TypeKind) -> Vec<&str> {
+    match ty {
+        TypeKind::Custom(name) => vec![name.as_str()],
+        TypeKind::Union(tys) => tys.iter().flat_map(custom_names).collect(),
+        TypeKind::Array(elem) => custom_names(elem),
+        TypeKind::Dict { key, val } | TypeKind::KVTable { key, val } => {
+            let mut names = custom_names(key);
+            names.extend(custom_names(val));
+            names
+        }
+        TypeKind::Function {
+            params,
+            returns,
+            vararg,
+            ..
+        } => params
+            .iter()
+            .chain(returns.iter())
+            .chain(vararg.as_deref())
+            .flat_map(custom_names)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypeRegistry;
+    use crate::Binder;
+    use pretty_assertions::assert_eq;
+    use std::path::Path;
+    use typua_config::LuaVersion;
+    use typua_parser::parse;
+    use typua_span::{Position, Span};
+    use typua_ty::TypeKind;
+    use typua_ty::diagnostic::{Diagnostic, DiagnosticKind};
+    use unindent::unindent;
+    fn bind(code: &str) -> TypeRegistry {
+        let (ast, _) = parse(&unindent(code), LuaVersion::Lua51);
+        let mut binder = Binder::new();
+        binder.bind(&ast);
+        binder.registry
+    }
+    fn validate(code: &str) -> Vec<Diagnostic> {
+        bind(code).validate()
+    }
+    #[test]
+    fn unknown_field_type() {
+        let diagnostics = validate(
+            r#"
+            ---@class Person
+            local Person = {}
+            ---@class Pet
+            ---@field owner Persn
+            local Pet = {}
+            "#,
+        );
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                message: "unknown type `Persn`".to_string(),
+                kind: DiagnosticKind::UnknownType,
+                span: Span::new(Position::new(4, 1), Position::new(4, 22)),
+            }]
+        );
+    }
+    #[test]
+    fn known_types() {
+        let diagnostics = validate(
+            r#"
+            ---@alias Id number|string
+            local _ = nil
+            ---@class Pet
+            ---@field owner Person
+            ---@field id Id
+            local Pet = {}
+            ---@class Person
+            local Person = {}
+            ---@param p Person
+            ---@return Pet[]
+            local function pets(p)
+            end
+            "#,
+        );
+        assert_eq!(diagnostics, Vec::new());
+    }
+    #[test]
+    fn remove_file() {
+        let person = Path::new("person.lua");
+        let pet = Path::new("pet.lua");
+        let mut registry = TypeRegistry::new();
+        registry.add_file(
+            person,
+            bind(
+                r#"
+                ---@class Person
+                local Person = {}
+                "#,
+            ),
+        );
+        registry.add_file(
+            pet,
+            bind(
+                r#"
+                ---@class Pet
+                ---@field owner Person
+                local Pet = {}
+                "#,
+            ),
+        );
+        assert_eq!(registry.validate(), Vec::new());
+
+        registry.remove_file(person);
+        assert!(registry.get_class("Person").is_none());
+        assert!(registry.get_class("Pet").is_some());
+        assert_eq!(
+            registry.validate(),
+            vec![Diagnostic {
+                message: "unknown type `Person`".to_string(),
+                kind: DiagnosticKind::UnknownType,
+                span: Span::new(Position::new(2, 1), Position::new(2, 23)),
+            }]
+        );
+
+        // removing the file again doesn't touch the others
+        registry.remove_file(person);
+        assert!(registry.get_class("Pet").is_some());
+    }
+    #[test]
+    fn add_file_replaces_previous_contents() {
+        let path = Path::new("types.lua");
+        let mut registry = TypeRegistry::new();
+        registry.add_file(
+            path,
+            bind(
+                r#"
+                ---@alias Id number
+                local _ = nil
+                "#,
+            ),
+        );
+        registry.add_file(
+            path,
+            bind(
+                r#"
+                ---@alias Key string
+                local _ = nil
+                "#,
+            ),
+        );
+        assert!(!registry.is_known("Id"));
+        assert!(registry.is_known("Key"));
+    }
+    #[test]
+    fn resolve_function_alias() {
+        let registry = bind(
+            r#"
+            ---@alias Handler fun(e: Event): boolean
+            ---@alias Event string|number
+            ---@alias List List[]
+            local _ = nil
+            "#,
+        );
+        assert_eq!(
+            registry.resolve(&TypeKind::Custom("Handler".to_string())),
+            TypeKind::Function {
+                generics: Vec::new(),
+                param_names: vec!["e".to_string()],
+                params: vec![TypeKind::Union(vec![TypeKind::String, TypeKind::Number])],
+                returns: vec![TypeKind::Boolean],
+                return_names: Vec::new(),
+                vararg: None,
+            }
+        );
+        // recursion stops at the alias being resolved
+        assert_eq!(
+            registry.resolve(&TypeKind::Custom("List".to_string())),
+            TypeKind::Array(Box::new(TypeKind::Custom("List".to_string())))
+        );
+    }
+}
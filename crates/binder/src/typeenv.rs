@@ -3,18 +3,24 @@ use im::HashMap;
 use typua_ty::TypeKind;
 use typua_ty::{BindError, TypuaError};
 
+/// types of the variables in scope
+///   `insert` declares a variable, `narrow` changes its type until it is assigned again
 #[derive(Debug, Clone)]
 pub struct TypeEnv {
     vars: HashMap<Symbol, TypeKind>,
+    /// types given at the declaration, without narrowing
+    declared: HashMap<Symbol, TypeKind>,
 }
 
 impl TypeEnv {
     pub fn new() -> Self {
         Self {
             vars: HashMap::new(),
+            declared: HashMap::new(),
         }
     }
     pub fn insert(&mut self, symbol: &Symbol, ty: &TypeKind) -> Result<(), TypuaError> {
+        let _ = self.declared.insert(symbol.clone(), ty.clone());
         match self.vars.insert(symbol.clone(), ty.clone()) {
             Some(_) => Ok(()),
             None => Err(TypuaError::Bind(BindError::InsertionFailed(format!(
@@ -23,9 +29,36 @@ impl TypeEnv {
             )))),
         }
     }
+    /// type of a declared variable for the following code, undeclared ones are ignored
+    pub fn narrow(&mut self, symbol: &Symbol, ty: &TypeKind) {
+        if let Some(var) = self.vars.get_mut(symbol) {
+            *var = ty.clone();
+        }
+    }
+    /// the variable gets back its declared type, as after an assignment
+    pub fn reset(&mut self, symbol: &Symbol) {
+        if let Some(ty) = self.declared.get(symbol) {
+            let _ = self.vars.insert(symbol.clone(), ty.clone());
+        }
+    }
+    /// every variable gets back its declared type
+    pub fn reset_all(&mut self) {
+        self.vars = self.declared.clone();
+    }
+    /// the variable is no longer in scope
+    pub fn remove(&mut self, symbol: &Symbol) {
+        self.vars.remove(symbol);
+        self.declared.remove(symbol);
+    }
     pub fn get(&self, symbol: &Symbol) -> Option<TypeKind> {
         self.vars.get(symbol).cloned()
     }
+    pub fn declared(&self, symbol: &Symbol) -> Option<TypeKind> {
+        self.declared.get(symbol).cloned()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Symbol, &TypeKind)> {
+        self.vars.iter()
+    }
 }
 
 impl Default for TypeEnv {
@@ -41,9 +74,7 @@ pub struct Symbol {
 
 impl Symbol {
     pub fn new(val: String) -> Self {
-        Self {
-            val
-        }
+        Self { val }
     }
 }
 
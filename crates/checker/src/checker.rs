@@ -1,298 +1,4177 @@
-use crate::result::{CheckResult, EvalErr, EvalType};
-use typua_binder::{Symbol, TypeEnv};
-use typua_parser::ast::{BinOp, Block, Expression, Stmt, TypeAst};
-use typua_span::Span;
-use typua_ty::{
-    diagnostic::{Diagnostic, DiagnosticKind},
-    kind::TypeKind,
+use std::collections::{HashMap, HashSet};
+
+use crate::narrowing::{analyze_condition, analyze_negated_condition};
+use crate::result::{CheckResult, EvalErr, EvalType, InferredLocal, ScopeSnapshot};
+use typua_binder::{Binder, Symbol, TypeEnv, TypeRegistry};
+use typua_parser::annotation::{AnnotationInfo, AnnotationTag, CastOp};
+use typua_parser::ast::{
+    Assign, BinOp, Block, Expression, FunctionCall, FunctionDeclaration, Return, ReturnValue, Stmt,
+    TableField, TypeAst, UnOp, Variable,
 };
+use typua_span::Span;
+use typua_ty::{diagnostic::Diagnostic, kind::TypeKind, message::Message};
 
 /// entry point typechcking
-pub fn typecheck(ast: &TypeAst, env: &TypeEnv) -> CheckResult {
-    typecheck_block(&ast.block, env)
+pub fn typecheck(ast: &TypeAst, binder: &Binder) -> CheckResult {
+    let mut result = typecheck_block(&ast.block, None, &binder.type_env, &binder.registry, None);
+    result.diagnostics.extend(check_dangling_annotations(ast));
+    result.diagnostics.extend(check_module_return(ast));
+    result.diagnostics.extend(check_breaks(&ast.block, false));
+    result.diagnostics.extend(check_yields(&ast.block, false));
+    result
+        .diagnostics
+        .extend(check_malformed_annotations(&ast.trailing_annotates));
+    result
 }
 
-fn typecheck_block(block: &Block, env: &TypeEnv) -> CheckResult {
-    let mut result = CheckResult::new();
-    for stmt in block.stmts.iter() {
-        result = CheckResult::merge(&result, &typecheck_stmt(stmt, env));
+/// globals of the standard library, assigning them declares nothing new
+const KNOWN_GLOBALS: &[&str] = &[
+    "_G",
+    "_ENV",
+    "_VERSION",
+    "arg",
+    "assert",
+    "bit",
+    "bit32",
+    "collectgarbage",
+    "coroutine",
+    "debug",
+    "dofile",
+    "error",
+    "getfenv",
+    "getmetatable",
+    "io",
+    "ipairs",
+    "jit",
+    "load",
+    "loadfile",
+    "loadstring",
+    "math",
+    "module",
+    "next",
+    "os",
+    "package",
+    "pairs",
+    "pcall",
+    "print",
+    "rawequal",
+    "rawget",
+    "rawlen",
+    "rawset",
+    "require",
+    "select",
+    "setfenv",
+    "setmetatable",
+    "string",
+    "table",
+    "tonumber",
+    "tostring",
+    "type",
+    "unpack",
+    "utf8",
+    "xpcall",
+];
+
+/// `name = value` at the top level of the file where name is neither a local declared before
+/// nor a known global, checked only with `runtime.strict_globals`
+pub fn check_implicit_globals(ast: &TypeAst) -> Vec<Diagnostic> {
+    let mut locals: HashSet<&str> = HashSet::new();
+    let mut diagnostics = Vec::new();
+    for stmt in ast.block.stmts.iter() {
+        match stmt {
+            Stmt::LocalAssign(local_assign) => {
+                locals.extend(local_assign.vars.iter().map(|var| var.name.as_str()))
+            }
+            Stmt::LocalFunction(local_function) => {
+                let _ = locals.insert(local_function.name.name.as_str());
+            }
+            Stmt::Assign(assign) => {
+                for var in assign.vars.iter() {
+                    if let Expression::Var { span, symbol } = var
+                        && !locals.contains(symbol.as_str())
+                        && !KNOWN_GLOBALS.contains(&symbol.as_str())
+                    {
+                        diagnostics.push(Diagnostic::new(
+                            Message::ImplicitGlobal {
+                                name: symbol.clone(),
+                            },
+                            span.clone(),
+                        ));
+                    }
+                }
+            }
+            _ => (),
+        }
     }
-    result
+    diagnostics
 }
 
-fn typecheck_stmt(stmt: &Stmt, env: &TypeEnv) -> CheckResult {
-    match stmt {
-        Stmt::LocalAssign(local_assign) => {
-            let mut diags: Vec<Diagnostic> = Vec::new();
-            for (var, expr) in local_assign.vars.iter().zip(local_assign.exprs.iter()) {
-                match eval_expr(expr, env) {
-                    Ok(eval_ty) => {
-                        let maybe_ann_ty = env.get(&Symbol::from(var.name.clone()));
-                        if let Some(ann_ty) = maybe_ann_ty
-                            && !TypeKind::subtype(&eval_ty.ty, &ann_ty)
-                        {
-                            diags.push(Diagnostic {
-                                message: format!("cannot assign `{}` to `{}`", eval_ty.ty, ann_ty),
-                                kind: DiagnosticKind::TypeMismatch,
-                                span: eval_ty.span,
-                            })
-                        }
-                    }
-                    Err(eval_err) => {
-                        diags.push(eval_err.diagnostic);
-                    }
+/// `name = value` and `function name() end` at the top level of the file, with their types
+///   declared in the other files with `workspace.shared_globals`
+///   a value which can't be evaluated is any
+pub fn top_level_globals(ast: &TypeAst, binder: &Binder) -> Vec<(String, TypeKind)> {
+    let mut globals = Vec::new();
+    for stmt in ast.block.stmts.iter() {
+        match stmt {
+            Stmt::Assign(assign) => {
+                for (i, var) in assign.vars.iter().enumerate() {
+                    let Expression::Var { symbol, .. } = var else {
+                        continue;
+                    };
+                    let ty = assign
+                        .exprs
+                        .get(i)
+                        .and_then(|expr| eval_expr(expr, &binder.type_env, &binder.registry).ok())
+                        .map_or(TypeKind::Any, |value| value.ty.widen_literal());
+                    globals.push((symbol.clone(), ty));
                 }
             }
-            CheckResult { diagnostics: diags }
+            Stmt::FunctionDeclaration(function)
+                if function.fields.is_empty() && function.method.is_none() =>
+            {
+                let ty = binder
+                    .clone()
+                    .function_type(&function.params, &function.annotates);
+                globals.push((function.name.name.clone(), ty));
+            }
+            _ => (),
         }
-        _ => unimplemented!(),
     }
+    globals
 }
 
-fn eval_expr(expr: &Expression, env: &TypeEnv) -> Result<EvalType, EvalErr> {
-    match expr {
-        Expression::Number { span } => Ok(EvalType {
-            span: span.clone(),
-            ty: TypeKind::Number,
-        }),
-        Expression::Boolean { span } => Ok(EvalType {
-            span: span.clone(),
-            ty: TypeKind::Boolean,
-        }),
-        Expression::BinaryOperator { lhs, binop, rhs } => {
-            let lhs_eval = eval_expr(lhs, env);
-            let rhs_eval = eval_expr(rhs, env);
-            match binop {
-                BinOp::Add(_) => match (lhs_eval, rhs_eval) {
-                    (
-                        Ok(EvalType {
-                            span: left_span,
-                            ty: left_ty,
-                        }),
-                        Ok(EvalType {
-                            span: right_span,
-                            ty: right_ty,
-                        }),
-                    ) => match TypeKind::can_add(&left_ty, &right_ty) {
-                        Ok(ty) => Ok(EvalType {
-                            span: Span::new(left_span.start, right_span.end),
-                            ty,
-                        }),
-                        Err(_e) => Err(EvalErr {
-                            span: Span::new(left_span.start.clone(), right_span.end.clone()),
-                            diagnostic: Diagnostic {
-                                message: format!("cannot add `{}` and `{}`", left_ty, right_ty),
-                                kind: DiagnosticKind::TypeMismatch,
-                                span: Span::new(left_span.start, right_span.end),
-                            },
-                        }),
-                    },
-                    (_, _) => unimplemented!(),
+/// locals named like a known global, which is hidden from them on
+///   local table = {} => `table.insert` no longer reaches the library
+///   names starting with `_` are kept quiet, local _print = print
+fn check_shadowed_builtins<'a>(vars: impl IntoIterator<Item = &'a Variable>) -> Vec<Diagnostic> {
+    vars.into_iter()
+        .filter(|var| !var.name.starts_with('_') && KNOWN_GLOBALS.contains(&var.name.as_str()))
+        .map(|var| {
+            Diagnostic::new(
+                Message::ShadowedBuiltin {
+                    name: var.name.clone(),
                 },
-                _ => unimplemented!(),
+                var.span.clone(),
+            )
+        })
+        .collect()
+}
+
+/// `---@type` given twice for the same name, only the first one is applied
+///   ---@type number, string on one line annotates each name in order
+///   ---@type number a and ---@type string b annotate distinct names
+fn check_conflicting_types(annotates: &[AnnotationInfo]) -> Vec<Diagnostic> {
+    let mut first_line = None;
+    let mut named: HashSet<&str> = HashSet::new();
+    annotates
+        .iter()
+        .filter(|ann| match &ann.tag {
+            AnnotationTag::Type(_) => {
+                *first_line.get_or_insert(ann.span.start.line()) != ann.span.start.line()
             }
-        }
-        Expression::Var { span, symbol } => match env.get(&Symbol::new(symbol.clone())) {
-            Some(ty) => Ok(EvalType {
-                span: span.clone(),
-                ty,
-            }),
-            None => Err(EvalErr {
-                span: span.clone(),
-                diagnostic: Diagnostic {
-                    span: span.clone(),
-                    kind: DiagnosticKind::NotDeclaredVariable,
-                    message: format!("'{}' is not declared", *symbol),
+            AnnotationTag::NamedType { name, .. } => !named.insert(name.as_str()),
+            _ => false,
+        })
+        .map(|ann| Diagnostic::new(Message::ConflictingAnnotation, ann.span.clone()))
+        .collect()
+}
+
+/// annotations which can't be parsed
+fn check_malformed_annotations(annotates: &[AnnotationInfo]) -> Vec<Diagnostic> {
+    annotates
+        .iter()
+        .filter_map(|ann| match &ann.tag {
+            AnnotationTag::Malformed(reason) => Some(Diagnostic::new(
+                Message::MalformedAnnotation {
+                    reason: reason.clone(),
                 },
-            }),
+                ann.span.clone(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// annotations at the end of file are attached to no statement
+/// `---@meta` files are exempt since they only declare types
+fn check_dangling_annotations(ast: &TypeAst) -> Vec<Diagnostic> {
+    if ast.is_meta() {
+        return Vec::new();
+    }
+    ast.trailing_annotates
+        .iter()
+        .filter(|ann| {
+            !matches!(
+                ann.tag,
+                AnnotationTag::Doc { .. } | AnnotationTag::Malformed(_)
+            )
+        })
+        .map(|ann| Diagnostic::new(Message::DanglingAnnotation, ann.span.clone()))
+        .collect()
+}
+
+/// `---@module` file whose main chunk doesn't end in `return value`
+///   `return` and `return nil` give nothing to `require` either
+///   `---@meta` files are exempt since they only declare types
+fn check_module_return(ast: &TypeAst) -> Vec<Diagnostic> {
+    let Some((module, ann)) = ast.module() else {
+        return Vec::new();
+    };
+    let returns_value = match ast.block.stmts.last() {
+        Some(Stmt::Return(ret)) => ret
+            .values
+            .first()
+            .is_some_and(|value| !matches!(value.expr, Expression::Nil { .. })),
+        _ => false,
+    };
+    if returns_value || ast.is_meta() {
+        return Vec::new();
+    }
+    vec![Diagnostic::new(
+        Message::MissingModuleReturn {
+            module: module.to_string(),
         },
-        _ => unimplemented!(),
+        ann.span.clone(),
+    )]
+}
+
+/// `break` in no loop, `in_loop` tells whether the block is in a loop of its function
+///   a function body is outside the loops around it
+fn check_breaks(block: &Block, in_loop: bool) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    for stmt in block.stmts.iter() {
+        match stmt {
+            Stmt::Break(break_stmt) if !in_loop => diags.push(Diagnostic::new(
+                Message::BreakOutsideLoop,
+                break_stmt.span.clone(),
+            )),
+            Stmt::If(if_stmt) => {
+                for block in if_stmt
+                    .branches
+                    .iter()
+                    .map(|branch| &branch.block)
+                    .chain(if_stmt.else_block.iter())
+                {
+                    diags.extend(check_breaks(block, in_loop));
+                }
+            }
+            Stmt::While(while_stmt) => diags.extend(check_breaks(&while_stmt.block, true)),
+            Stmt::Repeat(repeat) => diags.extend(check_breaks(&repeat.block, true)),
+            Stmt::LocalFunction(local_function) => {
+                diags.extend(check_breaks(&local_function.body, false))
+            }
+            Stmt::FunctionDeclaration(function) => {
+                diags.extend(check_breaks(&function.body, false))
+            }
+            _ => (),
+        }
     }
+    diags
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use pretty_assertions::assert_eq;
-    use typua_span::{Position, Span};
-    #[test]
-    fn eval_expr_literal() {
-        let env = TypeEnv::new();
-        let expr = Expression::Number {
-            span: Span {
-                start: Position::new(0, 0),
-                end: Position::new(0, 0),
-            },
-        };
-        let ret = eval_expr(&expr, &env);
-        assert_eq!(ret.is_ok(), true);
-        assert_eq!(
-            ret.unwrap(),
-            EvalType {
-                span: Span::new(Position::new(0, 0), Position::new(0, 0)),
-                ty: TypeKind::Number
+/// `coroutine.yield` in a function not annotated with `---@async`
+///   `is_async` tells whether the function of the block is, the main chunk is not
+fn check_yields(block: &Block, is_async: bool) -> Vec<Diagnostic> {
+    let is_async_function = |annotates: &[AnnotationInfo]| {
+        annotates
+            .iter()
+            .any(|ann| matches!(ann.tag, AnnotationTag::Async))
+    };
+    let mut diags = Vec::new();
+    for stmt in block.stmts.iter() {
+        match stmt {
+            Stmt::If(if_stmt) => {
+                for block in if_stmt
+                    .branches
+                    .iter()
+                    .map(|branch| &branch.block)
+                    .chain(if_stmt.else_block.iter())
+                {
+                    diags.extend(check_yields(block, is_async));
+                }
             }
-        );
+            Stmt::While(while_stmt) => diags.extend(check_yields(&while_stmt.block, is_async)),
+            Stmt::Repeat(repeat) => diags.extend(check_yields(&repeat.block, is_async)),
+            Stmt::LocalFunction(local_function) => diags.extend(check_yields(
+                &local_function.body,
+                is_async_function(&local_function.annotates),
+            )),
+            Stmt::FunctionDeclaration(function) => diags.extend(check_yields(
+                &function.body,
+                is_async_function(&function.annotates),
+            )),
+            _ => (),
+        }
+        if !is_async {
+            let mut calls = Vec::new();
+            if let Stmt::FunctionCall(call) = stmt
+                && is_yield(call)
+            {
+                calls.push(call);
+            }
+            for expr in stmt_exprs(stmt) {
+                visit_exprs(expr, &mut |expr| {
+                    if let Expression::FunctionCall(call) = expr
+                        && is_yield(call)
+                    {
+                        calls.push(call);
+                    }
+                });
+            }
+            diags.extend(
+                calls
+                    .into_iter()
+                    .map(|call| Diagnostic::new(Message::YieldOutsideAsync, call.span.clone())),
+            );
+        }
     }
-    #[test]
-    fn eval_expr_binop() {
-        // normal test: number + number
-        let env = TypeEnv::new();
-        let expr = Expression::BinaryOperator {
-            lhs: Box::new(Expression::Number {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 0),
-                },
-            }),
-            rhs: Box::new(Expression::Number {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 10),
-                },
-            }),
-            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
-        };
-        let ret = eval_expr(&expr, &env);
-        assert_eq!(ret.is_ok(), true);
-        assert_eq!(
-            ret.unwrap(),
-            EvalType {
-                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                ty: TypeKind::Number,
+    diags
+}
+
+/// `coroutine.yield(...)`, the global library function
+fn is_yield(call: &FunctionCall) -> bool {
+    call.method.is_none()
+        && matches!(
+            call.callee.as_ref(),
+            Expression::Field { expr, key }
+                if key.name == "yield"
+                    && matches!(expr.as_ref(), Expression::Var { symbol, .. } if symbol == "coroutine")
+        )
+}
+
+/// expressions written in the statement itself, those of its nested blocks are not included
+///   a call statement gives its callee and arguments
+fn stmt_exprs(stmt: &Stmt) -> Vec<&Expression> {
+    match stmt {
+        Stmt::LocalAssign(local_assign) => local_assign.exprs.iter().collect(),
+        Stmt::Assign(assign) => assign.vars.iter().chain(assign.exprs.iter()).collect(),
+        Stmt::FunctionCall(call) => std::iter::once(call.callee.as_ref())
+            .chain(call.args.iter())
+            .collect(),
+        Stmt::Return(ret) => ret.values.iter().map(|value| &value.expr).collect(),
+        Stmt::If(if_stmt) => if_stmt.branches.iter().map(|branch| &branch.cond).collect(),
+        Stmt::While(while_stmt) => vec![&while_stmt.cond],
+        Stmt::Repeat(repeat) => vec![&repeat.until],
+        _ => Vec::new(),
+    }
+}
+
+/// `f` on the expression and every expression in it, outer ones first
+///   arguments, operands and table fields are visited, function bodies are not
+fn visit_exprs<'a>(expr: &'a Expression, f: &mut impl FnMut(&'a Expression)) {
+    f(expr);
+    match expr {
+        Expression::FunctionCall(call) => {
+            visit_exprs(&call.callee, f);
+            for arg in call.args.iter() {
+                visit_exprs(arg, f);
             }
-        );
+        }
+        Expression::BinaryOperator { lhs, rhs, .. } => {
+            visit_exprs(lhs, f);
+            visit_exprs(rhs, f);
+        }
+        Expression::UnaryOperator { expr, .. }
+        | Expression::Parentheses { expr, .. }
+        | Expression::Field { expr, .. } => visit_exprs(expr, f),
+        Expression::Index { expr, key, .. } => {
+            visit_exprs(expr, f);
+            visit_exprs(key, f);
+        }
+        Expression::Table { fields, .. } => {
+            for field in fields.iter() {
+                if let TableField::ExpressionKey { key, .. } = field {
+                    visit_exprs(key, f);
+                }
+                visit_exprs(field.value(), f);
+            }
+        }
+        _ => (),
+    }
+}
 
-        // TypeMismatch: number + bool
-        let env = TypeEnv::new();
-        let expr = Expression::BinaryOperator {
-            lhs: Box::new(Expression::Boolean {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 0),
-                },
-            }),
-            rhs: Box::new(Expression::Number {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 10),
-                },
-            }),
-            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
+/// `==` or `~=` in the statement between types which have no value in common
+///   legal in lua, but the result never depends on the values
+///   sides which may be nil and narrowed locals are skipped, the env has one type per name
+///   and it may be of another declaration of the same name
+fn check_comparisons(stmt: &Stmt, env: &TypeEnv, registry: &TypeRegistry) -> Vec<Diagnostic> {
+    let mut comparisons = Vec::new();
+    for expr in stmt_exprs(stmt) {
+        visit_exprs(expr, &mut |expr| {
+            if let Expression::BinaryOperator {
+                lhs,
+                binop: BinOp::Equal(_) | BinOp::NotEqual(_),
+                rhs,
+            } = expr
+            {
+                comparisons.push((lhs.as_ref(), rhs.as_ref()));
+            }
+        });
+    }
+    let is_narrowed = |expr: &Expression| {
+        let Expression::Var { symbol, .. } = expr else {
+            return false;
         };
-        let ret = eval_expr(&expr, &env);
-        assert_eq!(ret.is_err(), true);
-        assert_eq!(
-            ret.unwrap_err(),
-            EvalErr {
-                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                diagnostic: Diagnostic {
-                    message: "cannot add `boolean` and `number`".to_string(),
-                    kind: DiagnosticKind::TypeMismatch,
-                    span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                }
+        let symbol = Symbol::new(symbol.clone());
+        env.get(&symbol) != env.declared(&symbol)
+    };
+    let may_be_nil = |ty: &TypeKind| TypeKind::subtype(&TypeKind::Nil, ty);
+    comparisons
+        .into_iter()
+        .filter(|(lhs, rhs)| !is_narrowed(lhs) && !is_narrowed(rhs))
+        .filter_map(|(lhs, rhs)| {
+            let lhs = eval_expr(lhs, env, registry).ok()?;
+            let rhs = eval_expr(rhs, env, registry).ok()?;
+            if may_be_nil(&lhs.ty) || may_be_nil(&rhs.ty) || lhs.ty.overlaps(&rhs.ty) {
+                return None;
             }
-        );
-        // normal test: binop vars
-        let mut env = TypeEnv::new();
-        let _ = env.insert(&Symbol::new("x".to_string()), &TypeKind::Number);
-        let _ = env.insert(&Symbol::new("y".to_string()), &TypeKind::Number);
-        let expr = Expression::BinaryOperator {
-            lhs: Box::new(Expression::Var {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 0),
-                },
-                symbol: "x".to_string(),
-            }),
-            rhs: Box::new(Expression::Var {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 10),
+            Some(Diagnostic::new(
+                Message::NeverEqual {
+                    lhs: lhs.ty,
+                    rhs: rhs.ty,
                 },
-                symbol: "y".to_string(),
-            }),
-            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
+                Span::new(lhs.span.start, rhs.span.end),
+            ))
+        })
+        .collect()
+}
+
+/// fields of table literals in the statement whose inline annotation `v --[[@type T]]`
+/// doesn't accept the value, each reported at its value
+///   values which can't be evaluated are reported by evaluating the statement
+fn check_field_annotations(stmt: &Stmt, env: &TypeEnv, registry: &TypeRegistry) -> Vec<Diagnostic> {
+    let mut fields = Vec::new();
+    for expr in stmt_exprs(stmt) {
+        visit_exprs(expr, &mut |expr| {
+            if let Expression::Table { fields: table, .. } = expr {
+                fields.extend(table.iter());
+            }
+        });
+    }
+    let mut diags = Vec::new();
+    for field in fields {
+        let Ok(value) = eval_expr(field.value(), env, registry) else {
+            continue;
         };
-        let ret = eval_expr(&expr, &env);
-        assert_eq!(ret.is_ok(), true);
-        assert_eq!(
-            ret.unwrap(),
-            EvalType {
-                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                ty: TypeKind::Number,
+        for ann in field.annotates() {
+            if let AnnotationTag::Type(ann_ty) = &ann.tag
+                && !registry.subtype(&value.ty, ann_ty)
+            {
+                diags.push(Diagnostic::new(
+                    Message::AssignMismatch {
+                        value: value.ty.clone(),
+                        expected: ann_ty.clone(),
+                    },
+                    value.span.clone(),
+                ));
             }
-        );
-        // abnormal test: binop vars
-        let mut env = TypeEnv::new();
-        let _ = env.insert(&Symbol::new("x".to_string()), &TypeKind::Number);
-        let _ = env.insert(&Symbol::new("y".to_string()), &TypeKind::Boolean);
-        let expr = Expression::BinaryOperator {
-            lhs: Box::new(Expression::Var {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 0),
-                },
-                symbol: "x".to_string(),
-            }),
-            rhs: Box::new(Expression::Var {
-                span: Span {
-                    start: Position::new(0, 0),
-                    end: Position::new(0, 10),
-                },
-                symbol: "y".to_string(),
-            }),
-            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
+        }
+    }
+    diags
+}
+
+/// `span` is the range where the block's variables are visible, None for the main chunk
+/// `returns` are the `---@return` types of the enclosing function, None if not annotated
+fn typecheck_block(
+    block: &Block,
+    span: Option<Span>,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+    returns: Option<&[TypeKind]>,
+) -> CheckResult {
+    typecheck_block_env(block, span, env, registry, returns).0
+}
+
+/// `typecheck_block` with the environment at the end of the block
+fn typecheck_block_env(
+    block: &Block,
+    span: Option<Span>,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+    returns: Option<&[TypeKind]>,
+) -> (CheckResult, TypeEnv) {
+    let mut result = CheckResult::new();
+    let locals = block
+        .stmts
+        .iter()
+        .flat_map(|stmt| match stmt {
+            Stmt::LocalAssign(local_assign) => {
+                let end = local_assign.span().end;
+                local_assign
+                    .vars
+                    .iter()
+                    .map(|var| (var.name.clone(), end.clone()))
+                    .collect()
+            }
+            Stmt::LocalFunction(local_function) => vec![(
+                local_function.name.name.clone(),
+                local_function.name.span.start.clone(),
+            )],
+            _ => Vec::new(),
+        })
+        .collect();
+    result.scopes.push(ScopeSnapshot {
+        span,
+        env: env.clone(),
+        locals,
+    });
+    // narrowing applied by statements is visible for the rest of the block
+    let mut env = env.clone();
+    // names assigned in the block, found when the first function is checked
+    let mut assigned: Option<HashSet<String>> = None;
+    for (i, stmt) in block.stmts.iter().enumerate() {
+        result
+            .diagnostics
+            .extend(check_malformed_annotations(stmt.annotates()));
+        result.diagnostics.extend(apply_casts(stmt, &mut env));
+        result
+            .diagnostics
+            .extend(check_comparisons(stmt, &env, registry));
+        result
+            .diagnostics
+            .extend(check_field_annotations(stmt, &env, registry));
+        let stmt_result = match stmt {
+            // the body may run after a captured local is reassigned, narrowing doesn't reach it
+            Stmt::LocalFunction(_) | Stmt::FunctionDeclaration(_) => {
+                let mut closure_env = env.clone();
+                for name in assigned.get_or_insert_with(|| assigned_names(block)).iter() {
+                    closure_env.reset(&Symbol::new(name.clone()));
+                }
+                typecheck_stmt(stmt, &mut closure_env, registry, returns)
+            }
+            _ => typecheck_stmt(stmt, &mut env, registry, returns),
         };
-        let ret = eval_expr(&expr, &env);
-        assert_eq!(ret.is_err(), true);
-        assert_eq!(
-            ret.unwrap_err(),
-            EvalErr {
-                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                diagnostic: Diagnostic {
-                    message: "cannot add `number` and `boolean`".to_string(),
-                    kind: DiagnosticKind::TypeMismatch,
-                    span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+        result = CheckResult::merge(&result, &stmt_result);
+        match stmt {
+            // a backward goto may reach the label with any of the declared types
+            Stmt::Label(_) => env.reset_all(),
+            // the assigned value is not tracked, the variable gets back its declared type
+            //   narrowing by an enclosing block is dropped too
+            Stmt::Assign(assign) => {
+                for name in assign.names() {
+                    env.reset(&Symbol::new(name.to_string()));
                 }
             }
-        );
+            _ => (),
+        }
+        if is_terminator(stmt)
+            && let (Some(first), Some(last)) = (block.stmts.get(i + 1), block.stmts.last())
+        {
+            result.diagnostics.push(Diagnostic::new(
+                Message::UnreachableCode,
+                Span::new(first.span().start, last.span().end),
+            ));
+            break;
+        }
     }
-    #[test]
-    fn eval_expr_var() {
-        let mut env = TypeEnv::new();
+    (result, env)
+}
+
+/// `---@cast` annotations of the statement applied to the variables in `env`
+///   ---@cast x -nil    => number|nil becomes number
+///   ---@cast x +string => number becomes number|string
+fn apply_casts(stmt: &Stmt, env: &mut TypeEnv) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    for ann in stmt.annotates() {
+        let AnnotationTag::Cast { name, op, ty } = &ann.tag else {
+            continue;
+        };
+        let symbol = Symbol::new(name.clone());
+        let Some(current) = env.get(&symbol) else {
+            diags.push(Diagnostic::new(
+                Message::UnknownCastTarget { name: name.clone() },
+                ann.span.clone(),
+            ));
+            continue;
+        };
+        let casted = match op {
+            CastOp::Set => ty.clone(),
+            CastOp::Add => current.union(ty),
+            CastOp::Remove => current.exclude(ty),
+        };
+        env.narrow(&symbol, &casted);
+    }
+    diags
+}
+
+/// names assigned anywhere in the block, nested blocks and function bodies included
+fn assigned_names(block: &Block) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for stmt in block.stmts.iter() {
+        match stmt {
+            Stmt::Assign(assign) => names.extend(assign.names().map(str::to_string)),
+            Stmt::If(if_stmt) => {
+                for block in if_stmt
+                    .branches
+                    .iter()
+                    .map(|branch| &branch.block)
+                    .chain(if_stmt.else_block.iter())
+                {
+                    names.extend(assigned_names(block));
+                }
+            }
+            Stmt::While(while_stmt) => names.extend(assigned_names(&while_stmt.block)),
+            Stmt::Repeat(repeat) => names.extend(assigned_names(&repeat.block)),
+            Stmt::LocalFunction(local_function) => {
+                names.extend(assigned_names(&local_function.body))
+            }
+            Stmt::FunctionDeclaration(function) => names.extend(assigned_names(&function.body)),
+            _ => (),
+        }
+    }
+    names
+}
+
+/// block never reaches its end, `return`, `break` or `error(...)` is the last statement
+fn block_exits(block: &Block) -> bool {
+    match block.stmts.last() {
+        Some(Stmt::Return(_) | Stmt::Break(_)) => true,
+        Some(stmt) => is_terminator(stmt),
+        None => false,
+    }
+}
+
+/// environment after the paths of `envs` join
+///   a symbol keeps its narrowed type only if every path agrees, otherwise the type in `env`
+fn join_paths(env: &TypeEnv, envs: &[TypeEnv]) -> TypeEnv {
+    let mut joined = env.clone();
+    let Some((first, rest)) = envs.split_first() else {
+        return joined;
+    };
+    for (symbol, ty) in first.iter() {
+        if rest
+            .iter()
+            .all(|other| other.get(symbol).as_ref() == Some(ty))
+        {
+            joined.narrow(symbol, ty);
+        }
+    }
+    joined
+}
+
+/// statement never returns control to the following statement
+///   error(...)
+fn is_terminator(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::FunctionCall(call) => is_builtin_call(call, "error"),
+        _ => false,
+    }
+}
+
+fn is_builtin_call(call: &FunctionCall, name: &str) -> bool {
+    call.method.is_none()
+        && matches!(call.callee.as_ref(), Expression::Var { symbol, .. } if symbol == name)
+}
+
+/// table literal assigned to an array must not have keyed fields
+///   { 1, 2, foo = 3 }
+fn check_mixed_table_literal(expr: &Expression) -> Option<Diagnostic> {
+    let Expression::Table { fields, .. } = expr else {
+        return None;
+    };
+    if !fields.iter().any(|f| matches!(f, TableField::NoKey { .. })) {
+        return None;
+    }
+    fields
+        .iter()
+        .find(|f| !matches!(f, TableField::NoKey { .. }))
+        .map(|keyed| Diagnostic::new(Message::MixedTableLiteral, keyed.span()))
+}
+
+/// keys and values of a table literal assigned to `table<K, V>` must be K and V
+///   { a = 1, b = "x" } as table<string, number> => `"x"` is not number
+///   `a = v` has a string key, `v` alone has a number key
+fn check_table_entries(
+    expr: &Expression,
+    key_ty: &TypeKind,
+    val_ty: &TypeKind,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+) -> Vec<Diagnostic> {
+    let Expression::Table { fields, .. } = expr else {
+        return Vec::new();
+    };
+    let mut diags = Vec::new();
+    for field in fields.iter() {
+        let key = match field {
+            TableField::NameKey { key, .. } => Some((TypeKind::String, key.span.clone())),
+            TableField::NoKey { .. } => None,
+            TableField::ExpressionKey { key, .. } => eval_expr(key, env, registry)
+                .ok()
+                .map(|key| (key.ty, key.span)),
+        };
+        let (key, span) = key.unwrap_or((TypeKind::Number, field.span()));
+        if !registry.subtype(&key, key_ty) {
+            diags.push(Diagnostic::new(
+                Message::KeyMismatch {
+                    key,
+                    expected: key_ty.clone(),
+                },
+                span,
+            ));
+        }
+        if let Ok(value) = eval_expr(field.value(), env, registry)
+            && !registry.subtype(&value.ty, val_ty)
+        {
+            diags.push(Diagnostic::new(
+                Message::AssignMismatch {
+                    value: value.ty,
+                    expected: val_ty.clone(),
+                },
+                value.span,
+            ));
+        }
+    }
+    diags
+}
+
+/// kind of the literal which can never match the annotation
+///   `{}` as string => Some("table")
+///   `{ 1, "a" }` as number[] => None, the literal kind itself is acceptable
+fn literal_conflict(
+    expr: &Expression,
+    ann_ty: &TypeKind,
+    registry: &TypeRegistry,
+) -> Option<&'static str> {
+    let (literal, ty) = match expr {
+        Expression::Nil { .. } => ("nil", TypeKind::Nil),
+        Expression::Number { integer: true, .. } => ("number", TypeKind::Integer),
+        Expression::Number { .. } => ("number", TypeKind::Number),
+        Expression::String { value, .. } => ("string", TypeKind::StringLiteral(value.clone())),
+        Expression::Boolean { value, .. } => ("boolean", TypeKind::BooleanLiteral(*value)),
+        Expression::Table { .. } => ("table", TypeKind::Table),
+        _ => return None,
+    };
+    (!registry.subtype(&ty, ann_ty)).then_some(literal)
+}
+
+/// type of `name` of an instance of `---@class`
+///   None => not a class instance or the class has no such field
+fn field_type(ty: &TypeKind, name: &str, registry: &TypeRegistry) -> Option<TypeKind> {
+    let TypeKind::Custom(class) = ty else {
+        return None;
+    };
+    registry
+        .get_class(class)?
+        .fields
+        .iter()
+        .find(|field| field.name == name)
+        .map(|field| field.ty.clone())
+}
+
+/// `obj.f = value` where obj is a class instance
+///   the value must be of the type of ---@field f, any member of a union
+///   a class which is exact has no fields other than the declared ones
+fn check_field_assignment(
+    var: &Expression,
+    value: &EvalType,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+) -> Option<Diagnostic> {
+    let Expression::Field { expr, key } = var else {
+        return None;
+    };
+    let object = eval_expr(expr, env, registry).ok()?;
+    let TypeKind::Custom(class) = &object.ty else {
+        return None;
+    };
+    let exact = registry.get_class(class)?.exact;
+    match field_type(&object.ty, &key.name, registry) {
+        Some(ty) if !registry.subtype(&value.ty, &registry.resolve(&ty)) => Some(Diagnostic::new(
+            Message::AssignMismatch {
+                value: value.ty.clone(),
+                expected: ty,
+            },
+            value.span.clone(),
+        )),
+        None if exact => Some(Diagnostic::new(
+            Message::UndefinedField {
+                ty: object.ty.clone(),
+                field: key.name.clone(),
+            },
+            key.span.clone(),
+        )),
+        _ => None,
+    }
+}
+
+/// type hinted for the i-th value of `a.b, c = v1, v2`
+///   v1 --[[@as T]] after the value, else the i-th ---@type above the statement
+fn assign_hint(assign: &Assign, i: usize) -> Option<&TypeKind> {
+    let inline = assign.value_annotates.get(i).and_then(|annotates| {
+        annotates.iter().find_map(|ann| match &ann.tag {
+            AnnotationTag::As(ty) | AnnotationTag::Type(ty) => Some(ty),
+            _ => None,
+        })
+    });
+    inline.or_else(|| {
+        assign
+            .annotates
+            .iter()
+            .filter_map(|ann| match &ann.tag {
+                AnnotationTag::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .nth(i)
+    })
+}
+
+/// `{ name = value }` taken as an instance of `---@class`
+///   each value must be of the type of its ---@field, any member of a union
+///   a class which is exact has no fields other than the declared ones
+///   values which can't be evaluated are reported by evaluating the table
+fn check_class_literal(
+    expr: &Expression,
+    ty: &TypeKind,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+) -> Vec<Diagnostic> {
+    let (Expression::Table { fields, .. }, TypeKind::Custom(class)) = (expr, registry.resolve(ty))
+    else {
+        return Vec::new();
+    };
+    let Some(decl) = registry.get_class(&class) else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter_map(|field| {
+            let TableField::NameKey { key, value, .. } = field else {
+                return None;
+            };
+            let value = eval_expr(value, env, registry).ok()?;
+            match decl.fields.iter().find(|field| field.name == key.name) {
+                Some(field) if !registry.subtype(&value.ty, &registry.resolve(&field.ty)) => {
+                    Some(Diagnostic::new(
+                        Message::AssignMismatch {
+                            value: value.ty,
+                            expected: field.ty.clone(),
+                        },
+                        value.span,
+                    ))
+                }
+                None if decl.exact => Some(Diagnostic::new(
+                    Message::UndefinedField {
+                        ty: TypeKind::Custom(class.clone()),
+                        field: key.name.clone(),
+                    },
+                    key.span.clone(),
+                )),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// function type of the callee
+///   obj:m(...) calls the field m of obj's class, its first param is the implicit self
+///   generic functions are instantiated with the types of the arguments
+///   None => callee is not known to be a function
+fn callee_type(call: &FunctionCall, env: &TypeEnv, registry: &TypeRegistry) -> Option<TypeKind> {
+    let callee = eval_expr(&call.callee, env, registry).ok()?.ty;
+    let ty = match &call.method {
+        Some(method) => field_type(&callee, &method.name, registry)?,
+        None => callee,
+    };
+    let TypeKind::Function { generics, .. } = &ty else {
+        return None;
+    };
+    if generics.is_empty() {
+        return Some(ty);
+    }
+    let receiver = call.method.as_ref().map(|_| call.callee.as_ref());
+    let args: Vec<TypeKind> = receiver
+        .into_iter()
+        .chain(call.args.iter())
+        .map(|arg| eval_expr(arg, env, registry).map_or(TypeKind::Any, |value| value.ty))
+        .collect();
+    Some(instantiate(&ty, &args))
+}
+
+/// generic function with its type parameters replaced by the types inferred from `args`
+///   fun<T>(x: T): T[] called with number => fun(number): number[]
+///   type parameters which can't be inferred are left generic
+fn instantiate(func: &TypeKind, args: &[TypeKind]) -> TypeKind {
+    let TypeKind::Function { params, .. } = func else {
+        return func.clone();
+    };
+    let mut bindings = HashMap::new();
+    for (param, arg) in params.iter().zip(args.iter()) {
+        unify(param, arg, &mut bindings);
+    }
+    match func.replace(&|ty| match ty {
+        TypeKind::Generic(name) => bindings.get(name).cloned(),
+        _ => None,
+    }) {
+        TypeKind::Function {
+            param_names,
+            params,
+            returns,
+            return_names,
+            vararg,
+            ..
+        } => TypeKind::Function {
+            generics: Vec::new(),
+            param_names,
+            params,
+            returns,
+            return_names,
+            vararg,
+        },
+        ty => ty,
+    }
+}
+
+/// bind the type parameters in `param` to the parts of `arg` at the same place
+///   fun(x: T): U with fun(number): string => T is number, U is string
+///   the first binding of a type parameter wins
+fn unify(param: &TypeKind, arg: &TypeKind, bindings: &mut HashMap<String, TypeKind>) {
+    match (param, arg) {
+        (TypeKind::Generic(name), _) => {
+            bindings
+                .entry(name.clone())
+                .or_insert_with(|| arg.widen_literal());
+        }
+        (TypeKind::Array(param_elem), TypeKind::Array(arg_elem)) => {
+            unify(param_elem, arg_elem, bindings)
+        }
+        (
+            TypeKind::Dict { key, val } | TypeKind::KVTable { key, val },
+            TypeKind::Dict {
+                key: arg_key,
+                val: arg_val,
+            }
+            | TypeKind::KVTable {
+                key: arg_key,
+                val: arg_val,
+            },
+        ) => {
+            unify(key, arg_key, bindings);
+            unify(val, arg_val, bindings);
+        }
+        (
+            TypeKind::Function {
+                params, returns, ..
+            },
+            TypeKind::Function {
+                params: arg_params,
+                returns: arg_returns,
+                ..
+            },
+        ) => {
+            for (param, arg) in params
+                .iter()
+                .zip(arg_params.iter())
+                .chain(returns.iter().zip(arg_returns.iter()))
+            {
+                unify(param, arg, bindings);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// return types of a call to a function with `---@return` annotations, and its trailing `...T`
+///   None => callee is not a function or returns are not annotated
+fn call_returns(
+    call: &FunctionCall,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+) -> Option<(Vec<TypeKind>, Option<TypeKind>)> {
+    match callee_type(call, env, registry) {
+        Some(TypeKind::Function {
+            returns, vararg, ..
+        }) if !returns.is_empty() || vararg.is_some() => Some((returns, vararg.map(|ty| *ty))),
+        _ => None,
+    }
+}
+
+/// arguments must be accepted by the params of the callee
+///   obj:m(a) passes obj as the first argument
+///   trailing params which accept nil may be omitted, `...` takes the rest
+fn check_call_args(call: &FunctionCall, env: &TypeEnv, registry: &TypeRegistry) -> Vec<Diagnostic> {
+    let receiver = call.method.as_ref().map(|_| call.callee.as_ref());
+    let mut args = Vec::new();
+    let mut diags = Vec::new();
+    for arg in receiver.into_iter().chain(call.args.iter()) {
+        match eval_expr(arg, env, registry) {
+            Ok(value) => args.push(value),
+            Err(eval_err) => diags.push(eval_err.diagnostic),
+        }
+    }
+    if !diags.is_empty() {
+        return diags;
+    }
+    // methods of a class which is not exact may be added anywhere, a hint is reported for them
+    if let (Some(method), Some(TypeKind::Custom(class))) =
+        (&call.method, args.first().map(|receiver| &receiver.ty))
+        && let Some(declared) = registry.get_class(class)
+        && field_type(&args[0].ty, &method.name, registry).is_none()
+    {
+        diags.push(Diagnostic::new(
+            Message::UndefinedMethod {
+                class: class.clone(),
+                method: method.name.clone(),
+                exact: declared.exact,
+            },
+            method.span.clone(),
+        ));
+        return diags;
+    }
+    let Some(TypeKind::Function {
+        params,
+        param_names,
+        ..
+    }) = callee_type(call, env, registry)
+    else {
+        return diags;
+    };
+    // `...` takes any number of arguments
+    let variadic = param_names.last().is_some_and(|name| name == "...");
+    for (arg, param) in args.iter().zip(params.iter()) {
+        if !registry.subtype(&arg.ty, param) {
+            diags.push(Diagnostic::new(
+                Message::ArgumentMismatch {
+                    arg: arg.ty.clone(),
+                    param: param.clone(),
+                },
+                arg.span.clone(),
+            ));
+        }
+    }
+    // a call at the end passes unknown number of values
+    if matches!(call.args.last(), Some(Expression::FunctionCall(_))) {
+        return diags;
+    }
+    let required = params
+        .iter()
+        .rposition(|param| !TypeKind::subtype(&TypeKind::Nil, param))
+        .map_or(0, |i| i + 1);
+    if args.len() < required || (params.len() < args.len() && !variadic) {
+        let implicit = receiver.map_or(0, |_| 1);
+        diags.push(Diagnostic::new(
+            Message::ArgumentCount {
+                expected: params.len() - implicit.min(params.len()),
+                found: args.len() - implicit,
+            },
+            call.span.clone(),
+        ));
+    }
+    diags
+}
+
+/// returned values must match the `---@return` types
+///   `v --[[@type T]]` casts the value to T when either of them is a subtype of the other
+///   missing values are nil unless the last value is a call
+fn check_return(
+    ret: &Return,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+    returns: Option<&[TypeKind]>,
+) -> Vec<Diagnostic> {
+    let mut diags = Vec::new();
+    for (i, value) in ret.values.iter().enumerate() {
+        let mut eval_ty = match eval_expr(&value.expr, env, registry) {
+            Ok(eval_ty) => eval_ty,
+            Err(eval_err) => {
+                diags.push(eval_err.diagnostic);
+                continue;
+            }
+        };
+        for ann in value.annotates.iter() {
+            let AnnotationTag::Type(cast_ty) = &ann.tag else {
+                continue;
+            };
+            if TypeKind::subtype(&eval_ty.ty, cast_ty) || TypeKind::subtype(cast_ty, &eval_ty.ty) {
+                eval_ty.ty = cast_ty.clone();
+            } else {
+                diags.push(Diagnostic::new(
+                    Message::CastMismatch {
+                        value: eval_ty.ty.clone(),
+                        target: cast_ty.clone(),
+                    },
+                    eval_ty.span.clone(),
+                ));
+            }
+        }
+        if let Some(expected) = returns.and_then(|returns| returns.get(i))
+            && !registry.subtype(&eval_ty.ty, &registry.resolve(expected))
+        {
+            diags.push(Diagnostic::new(
+                Message::ReturnMismatch {
+                    value: eval_ty.ty,
+                    expected: expected.clone(),
+                },
+                eval_ty.span,
+            ));
+        }
+    }
+    if matches!(
+        ret.values.last(),
+        Some(ReturnValue {
+            expr: Expression::FunctionCall(_),
+            ..
+        })
+    ) {
+        return diags;
+    }
+    for expected in returns.unwrap_or_default().iter().skip(ret.values.len()) {
+        if !TypeKind::subtype(&TypeKind::Nil, expected) {
+            diags.push(Diagnostic::new(
+                Message::ReturnMismatch {
+                    value: TypeKind::Nil,
+                    expected: expected.clone(),
+                },
+                ret.span.clone(),
+            ));
+        }
+    }
+    diags
+}
+
+/// values of an expression list
+///   a bare call at the end spreads all of its returns, otherwise each expression is one value
+///   a `...T` return of the spread call fills the values up to `width`
+///   None => the number of values is not known
+fn eval_exprs<'a>(
+    exprs: &'a [Expression],
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+    width: usize,
+) -> Option<Vec<(&'a Expression, Result<EvalType, EvalErr>)>> {
+    let mut values = Vec::new();
+    for (i, expr) in exprs.iter().enumerate() {
+        match expr {
+            Expression::FunctionCall(call) if i + 1 == exprs.len() => {
+                if let Err(eval_err) = eval_expr(expr, env, registry) {
+                    values.push((expr, Err(eval_err)));
+                    return Some(values);
+                }
+                let (returns, vararg) = call_returns(call, env, registry)?;
+                let rest = width.saturating_sub(values.len() + returns.len());
+                let varargs = vararg
+                    .into_iter()
+                    .flat_map(|ty| std::iter::repeat_n(ty, rest));
+                values.extend(returns.into_iter().chain(varargs).map(|ty| {
+                    (
+                        expr,
+                        Ok(EvalType {
+                            span: call.span.clone(),
+                            ty,
+                        }),
+                    )
+                }));
+            }
+            _ => values.push((expr, eval_expr(expr, env, registry))),
+        }
+    }
+    Some(values)
+}
+
+fn typecheck_stmt(
+    stmt: &Stmt,
+    env: &mut TypeEnv,
+    registry: &TypeRegistry,
+    returns: Option<&[TypeKind]>,
+) -> CheckResult {
+    match stmt {
+        Stmt::LocalAssign(local_assign) => {
+            let mut diags = check_conflicting_types(&local_assign.annotates);
+            diags.extend(check_shadowed_builtins(&local_assign.vars));
+            let mut named = Vec::new();
+            for ann in local_assign.annotates.iter() {
+                let AnnotationTag::NamedType { name, .. } = &ann.tag else {
+                    continue;
+                };
+                if local_assign.vars.iter().any(|var| var.name == *name) {
+                    named.push(name);
+                } else {
+                    diags.push(Diagnostic::new(
+                        Message::UnknownNamedTarget { name: name.clone() },
+                        ann.span.clone(),
+                    ));
+                }
+            }
+            let Some(values) =
+                eval_exprs(&local_assign.exprs, env, registry, local_assign.vars.len())
+            else {
+                // the last call returns unknown number of values
+                return CheckResult {
+                    diagnostics: diags,
+                    ..CheckResult::new()
+                };
+            };
+            // `local a, b = 1` leaves b nil, `local a, b` is an intentional declaration
+            if !values.is_empty() {
+                for var in local_assign.vars.iter().skip(values.len()) {
+                    diags.push(Diagnostic::new(
+                        Message::UnbalancedAssignment {
+                            name: var.name.clone(),
+                        },
+                        var.span.clone(),
+                    ));
+                }
+            }
+            // names after the annotated ones take the type of their value
+            // ---@class annotates the first name with the class
+            let is_class = local_assign
+                .annotates
+                .iter()
+                .any(|ann| matches!(ann.tag, AnnotationTag::Class { .. }));
+            let annotated = local_assign
+                .annotates
+                .iter()
+                .filter(|ann| matches!(ann.tag, AnnotationTag::Type(_)))
+                .count()
+                .max(usize::from(is_class));
+            let mut inferred = Vec::new();
+            for (i, (var, (expr, value))) in local_assign.vars.iter().zip(values).enumerate() {
+                match value {
+                    Ok(eval_ty) if i >= annotated && !named.contains(&&var.name) => {
+                        let ty = eval_ty.ty.widen_literal();
+                        let _ = env.insert(&Symbol::from(var.name.clone()), &ty);
+                        inferred.push(InferredLocal {
+                            span: var.span.clone(),
+                            ty,
+                        });
+                    }
+                    Ok(eval_ty) => {
+                        let maybe_ann_ty = env
+                            .get(&Symbol::from(var.name.clone()))
+                            .map(|ty| registry.resolve(&ty));
+                        if let Some(TypeKind::Array(_)) = maybe_ann_ty
+                            && let Some(diag) = check_mixed_table_literal(expr)
+                        {
+                            diags.push(diag);
+                        } else if let Some(
+                            TypeKind::Dict { key, val } | TypeKind::KVTable { key, val },
+                        ) = &maybe_ann_ty
+                            && matches!(expr, Expression::Table { .. })
+                        {
+                            diags.extend(check_table_entries(expr, key, val, env, registry));
+                        } else if let Some(ann_ty) = maybe_ann_ty
+                            && !registry.subtype(&eval_ty.ty, &ann_ty)
+                        {
+                            diags.push(match literal_conflict(expr, &ann_ty, registry) {
+                                Some(literal) => Diagnostic::new(
+                                    Message::LiteralMismatch {
+                                        literal: literal.to_string(),
+                                        expected: ann_ty,
+                                    },
+                                    expr.span(),
+                                ),
+                                None => Diagnostic::new(
+                                    Message::AssignMismatch {
+                                        value: eval_ty.ty,
+                                        expected: ann_ty,
+                                    },
+                                    eval_ty.span,
+                                ),
+                            })
+                        }
+                    }
+                    Err(eval_err) => {
+                        diags.push(eval_err.diagnostic);
+                    }
+                }
+            }
+            CheckResult {
+                diagnostics: diags,
+                inferred,
+                ..CheckResult::new()
+            }
+        }
+        // narrowing is reset in typecheck_block
+        Stmt::Goto(_) | Stmt::Label(_) => CheckResult::new(),
+        // placement is checked by check_breaks
+        Stmt::Break(_) => CheckResult::new(),
+        // values are evaluated for their errors, only class fields are checked against a type
+        // a value with a hint is taken as the hinted type, a table literal is checked against it
+        Stmt::Assign(assign) => {
+            let mut diags = check_conflicting_types(&assign.annotates);
+            for (i, expr) in assign.exprs.iter().enumerate() {
+                match eval_expr(expr, env, registry) {
+                    Ok(mut value) => {
+                        if let Some(hint) = assign_hint(assign, i) {
+                            if TypeKind::subtype(&value.ty, hint)
+                                || TypeKind::subtype(hint, &value.ty)
+                            {
+                                diags.extend(check_class_literal(expr, hint, env, registry));
+                                value.ty = hint.clone();
+                            } else {
+                                diags.push(Diagnostic::new(
+                                    Message::CastMismatch {
+                                        value: value.ty.clone(),
+                                        target: hint.clone(),
+                                    },
+                                    value.span.clone(),
+                                ));
+                            }
+                        }
+                        if let Some(var) = assign.vars.get(i) {
+                            diags.extend(check_field_assignment(var, &value, env, registry));
+                        }
+                    }
+                    Err(eval_err) => diags.push(eval_err.diagnostic),
+                }
+            }
+            CheckResult {
+                diagnostics: diags,
+                ..CheckResult::new()
+            }
+        }
+        // each branch sees its condition true and the previous ones false
+        // branches which exit don't reach the following statements, so don't narrow them
+        // variables assigned in a reaching branch lose their narrowing after the statement
+        Stmt::If(if_stmt) => {
+            let mut result = CheckResult::new();
+            let mut reaching = Vec::new();
+            let mut reassigned = HashSet::new();
+            let mut rest_env = env.clone();
+            let branches = if_stmt
+                .branches
+                .iter()
+                .map(|branch| (Some(&branch.cond), &branch.block))
+                .chain(if_stmt.else_block.iter().map(|block| (None, block)));
+            for (cond, block) in branches {
+                let branch_env = match cond {
+                    Some(cond) => {
+                        match eval_expr(cond, &rest_env, registry) {
+                            Ok(value) => result.diagnostics.extend(constant_condition(&value)),
+                            Err(eval_err) => result.diagnostics.push(eval_err.diagnostic),
+                        }
+                        let branch_env = analyze_condition(cond, &rest_env);
+                        rest_env = analyze_negated_condition(cond, &rest_env);
+                        branch_env
+                    }
+                    None => rest_env.clone(),
+                };
+                let mut binder = Binder {
+                    type_env: branch_env.clone(),
+                    registry: registry.clone(),
+                };
+                binder.bind_block(block);
+                let branch_result = typecheck_block(
+                    block,
+                    Some(if_stmt.span.clone()),
+                    &binder.type_env,
+                    &binder.registry,
+                    returns,
+                );
+                result = CheckResult::merge(&result, &branch_result);
+                if !block_exits(block) {
+                    reaching.push(branch_env);
+                    reassigned.extend(assigned_names(block));
+                }
+            }
+            // without else, the statement is reached when every condition is false
+            if if_stmt.else_block.is_none() {
+                reaching.push(rest_env);
+            }
+            *env = join_paths(env, &reaching);
+            for name in reassigned {
+                env.reset(&Symbol::new(name));
+            }
+            result
+        }
+        // the body sees the condition true, `while true do` is not a constant condition
+        // variables assigned in the body lose their narrowing after the loop
+        Stmt::While(while_stmt) => {
+            let mut result = CheckResult::new();
+            match eval_expr(&while_stmt.cond, env, registry) {
+                Ok(_) if matches!(while_stmt.cond, Expression::Boolean { value: true, .. }) => (),
+                Ok(value) => result.diagnostics.extend(constant_condition(&value)),
+                Err(eval_err) => result.diagnostics.push(eval_err.diagnostic),
+            }
+            let mut binder = Binder {
+                type_env: analyze_condition(&while_stmt.cond, env),
+                registry: registry.clone(),
+            };
+            binder.bind_block(&while_stmt.block);
+            let body_result = typecheck_block(
+                &while_stmt.block,
+                Some(while_stmt.span.clone()),
+                &binder.type_env,
+                &binder.registry,
+                returns,
+            );
+            for name in assigned_names(&while_stmt.block) {
+                env.reset(&Symbol::new(name));
+            }
+            CheckResult::merge(&result, &body_result)
+        }
+        // the condition sees the locals of the body, as they are at its end
+        Stmt::Repeat(repeat) => {
+            let mut binder = Binder {
+                type_env: env.clone(),
+                registry: registry.clone(),
+            };
+            binder.bind_block(&repeat.block);
+            let (mut result, body_env) = typecheck_block_env(
+                &repeat.block,
+                Some(repeat.span.clone()),
+                &binder.type_env,
+                &binder.registry,
+                returns,
+            );
+            if let Err(eval_err) = eval_expr(&repeat.until, &body_env, registry) {
+                result.diagnostics.push(eval_err.diagnostic);
+            }
+            for name in assigned_names(&repeat.block) {
+                env.reset(&Symbol::new(name));
+            }
+            result
+        }
+        Stmt::Return(ret) => CheckResult {
+            diagnostics: check_return(ret, env, registry, returns),
+            ..CheckResult::new()
+        },
+        Stmt::FunctionCall(call) => {
+            let diags = check_call_args(call, env, registry);
+            // assert(cond) narrows the rest of the block as if cond is truthy
+            if is_builtin_call(call, "assert")
+                && let Some(cond) = call.args.first()
+            {
+                *env = analyze_condition(cond, env);
+            }
+            CheckResult {
+                diagnostics: diags,
+                ..CheckResult::new()
+            }
+        }
+        Stmt::LocalFunction(local_function) => {
+            // the body sees the params and its own locals
+            let mut body_env = env.clone();
+            let mut body_returns = None;
+            if let Some(TypeKind::Function {
+                params, returns, ..
+            }) = env.get(&Symbol::new(local_function.name.name.clone()))
+            {
+                for (param, ty) in local_function.params.iter().zip(params.iter()) {
+                    let _ = body_env.insert(&Symbol::new(param.name.clone()), ty);
+                }
+                body_returns = (!returns.is_empty()).then_some(returns);
+            }
+            let mut binder = Binder {
+                type_env: body_env,
+                registry: registry.clone(),
+            };
+            binder.bind_block(&local_function.body);
+            let mut result = typecheck_block(
+                &local_function.body,
+                Some(local_function.span.clone()),
+                &binder.type_env,
+                &binder.registry,
+                body_returns.as_deref(),
+            );
+            result
+                .diagnostics
+                .extend(check_shadowed_builtins([&local_function.name]));
+            result
+        }
+        // the body sees the params, self for a method, and its own locals
+        Stmt::FunctionDeclaration(function) => {
+            let mut binder = Binder {
+                type_env: env.clone(),
+                registry: registry.clone(),
+            };
+            let TypeKind::Function {
+                params, returns, ..
+            } = binder.function_type(&function.params, &function.annotates)
+            else {
+                unreachable!()
+            };
+            for (param, ty) in function.params.iter().zip(params.iter()) {
+                let _ = binder.type_env.insert(&Symbol::new(param.name.clone()), ty);
+            }
+            // function a.b:m() is a method of the type of a.b
+            if function.method.is_some() {
+                let mut owner = env.get(&Symbol::new(function.name.name.clone()));
+                for field in function.fields.iter() {
+                    owner = owner.and_then(|ty| field_type(&ty, &field.name, registry));
+                }
+                let _ = binder.type_env.insert(
+                    &Symbol::new("self".to_string()),
+                    &owner.unwrap_or(TypeKind::Any),
+                );
+            }
+            binder.bind_block(&function.body);
+            let mut result = typecheck_block(
+                &function.body,
+                Some(function.span.clone()),
+                &binder.type_env,
+                &binder.registry,
+                (!returns.is_empty()).then_some(returns.as_slice()),
+            );
+            if let Some(diagnostic) = check_function_owner(function, env, registry) {
+                result.diagnostics.insert(0, diagnostic);
+            }
+            result
+        }
+    }
+}
+
+/// the table a function is declared into must exist
+///   function a.b.c() needs a and a.b, function a.b:m() needs a and a.b too
+///   function f() declares a global, nothing is needed
+fn check_function_owner(
+    function: &FunctionDeclaration,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+) -> Option<Diagnostic> {
+    let owner_fields = match function.method {
+        Some(_) => function.fields.as_slice(),
+        None => function.fields.split_last()?.1,
+    };
+    let owner = owner_fields.iter().fold(
+        Expression::Var {
+            span: function.name.span.clone(),
+            symbol: function.name.name.clone(),
+        },
+        |expr, field| Expression::Field {
+            expr: Box::new(expr),
+            key: field.clone(),
+        },
+    );
+    let owner = match eval_expr(&owner, env, registry) {
+        Ok(owner) => owner,
+        Err(eval_err) => return Some(eval_err.diagnostic),
+    };
+    (!TypeKind::subtype(&owner.ty, &TypeKind::Table)).then(|| {
+        Diagnostic::new(
+            Message::FunctionOwnerMismatch { owner: owner.ty },
+            owner.span,
+        )
+    })
+}
+
+/// number from an operator taking numbers, `operation` names it in the error
+///   the result is an integer if both operands are, as lua 5.3 computes it
+fn eval_numeric(lhs: EvalType, rhs: EvalType, operation: &str) -> Result<EvalType, EvalErr> {
+    let span = Span::new(lhs.span.start.clone(), rhs.span.end.clone());
+    match [&lhs, &rhs]
+        .into_iter()
+        .find_map(|operand| operand_mismatch(operand, &TypeKind::Number, operation))
+    {
+        Some(diagnostic) => Err(EvalErr { span, diagnostic }),
+        None => Ok(EvalType {
+            span,
+            ty: if lhs.ty == TypeKind::Integer && rhs.ty == TypeKind::Integer {
+                TypeKind::Integer
+            } else {
+                TypeKind::Number
+            },
+        }),
+    }
+}
+
+/// error for an operand of `operation` which is not of `expected`
+///   number|nil where a number is expected is reported as a missing nil check
+fn operand_mismatch(
+    operand: &EvalType,
+    expected: &TypeKind,
+    operation: &str,
+) -> Option<Diagnostic> {
+    if TypeKind::subtype(&operand.ty, expected) {
+        return None;
+    }
+    let non_nil = operand.ty.non_nil_part();
+    let message = if operand.ty.is_optional()
+        && non_nil != TypeKind::Never
+        && TypeKind::subtype(&non_nil, expected)
+    {
+        Message::NilArithmetic {
+            operand: operand.ty.clone(),
+        }
+    } else {
+        Message::NumericOperand {
+            operand: operand.ty.clone(),
+            operation: operation.to_string(),
+        }
+    };
+    Some(Diagnostic::new(message, operand.span.clone()))
+}
+
+/// `a and b` is b if a is truthy, a otherwise
+/// `a or b` is a if a is truthy, b otherwise
+///   b is evaluated where a is known to be truthy (and) or falsy (or)
+fn eval_logical(
+    lhs: &Expression,
+    binop: &BinOp,
+    rhs: &Expression,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+) -> Result<EvalType, EvalErr> {
+    let lhs_eval = eval_expr(lhs, env, registry)?;
+    let is_and = matches!(binop, BinOp::And(_));
+    let narrowed = if is_and {
+        analyze_condition(lhs, env)
+    } else {
+        analyze_negated_condition(lhs, env)
+    };
+    let rhs_eval = eval_expr(rhs, &narrowed, registry)?;
+    // the part of a which is the result, never if a is always b
+    let kept = if is_and {
+        lhs_eval.ty.falsy_part()
+    } else {
+        lhs_eval.ty.truthy_part()
+    };
+    let ty = if matches!(lhs_eval.ty, TypeKind::Any | TypeKind::Unknown) {
+        TypeKind::Any
+    } else if kept == lhs_eval.ty {
+        kept
+    } else {
+        kept.union(&rhs_eval.ty)
+    };
+    Ok(EvalType {
+        span: Span::new(lhs_eval.span.start, rhs_eval.span.end),
+        ty,
+    })
+}
+
+/// hint for a condition whose type decides the branch without running it
+fn constant_condition(cond: &EvalType) -> Option<Diagnostic> {
+    let always = if cond.ty.is_truthy() {
+        true
+    } else if cond.ty.is_falsy() {
+        false
+    } else {
+        return None;
+    };
+    Some(Diagnostic::new(
+        Message::ConstantCondition {
+            condition: cond.ty.clone(),
+            always,
+        },
+        cond.span.clone(),
+    ))
+}
+
+fn eval_expr(
+    expr: &Expression,
+    env: &TypeEnv,
+    registry: &TypeRegistry,
+) -> Result<EvalType, EvalErr> {
+    match expr {
+        Expression::Nil { span } => Ok(EvalType {
+            span: span.clone(),
+            ty: TypeKind::Nil,
+        }),
+        Expression::Number { span, integer } => Ok(EvalType {
+            span: span.clone(),
+            ty: if *integer {
+                TypeKind::Integer
+            } else {
+                TypeKind::Number
+            },
+        }),
+        Expression::String { span, value } => Ok(EvalType {
+            span: span.clone(),
+            ty: TypeKind::StringLiteral(value.clone()),
+        }),
+        Expression::Boolean { span, value } => Ok(EvalType {
+            span: span.clone(),
+            ty: TypeKind::BooleanLiteral(*value),
+        }),
+        Expression::BinaryOperator {
+            lhs,
+            binop: binop @ (BinOp::And(_) | BinOp::Or(_)),
+            rhs,
+        } => eval_logical(lhs, binop, rhs, env, registry),
+        Expression::BinaryOperator { lhs, binop, rhs } => {
+            let lhs_eval = eval_expr(lhs, env, registry);
+            let rhs_eval = eval_expr(rhs, env, registry);
+            match binop {
+                BinOp::Add(_) => match (lhs_eval, rhs_eval) {
+                    (
+                        Ok(EvalType {
+                            span: left_span,
+                            ty: left_ty,
+                        }),
+                        Ok(EvalType {
+                            span: right_span,
+                            ty: right_ty,
+                        }),
+                    ) => match TypeKind::can_add(
+                        &registry.resolve(&left_ty),
+                        &registry.resolve(&right_ty),
+                    ) {
+                        Ok(ty) => Ok(EvalType {
+                            span: Span::new(left_span.start, right_span.end),
+                            ty,
+                        }),
+                        Err(_e) => {
+                            let span = Span::new(left_span.start.clone(), right_span.end.clone());
+                            // point at the operand which is not a number
+                            let (operand_span, operand_ty) =
+                                if TypeKind::subtype(&left_ty, &TypeKind::Number) {
+                                    (right_span, &right_ty)
+                                } else {
+                                    (left_span, &left_ty)
+                                };
+                            // number|nil used without a nil check
+                            let message = if TypeKind::subtype(&TypeKind::Nil, operand_ty) {
+                                Message::NilArithmetic {
+                                    operand: operand_ty.clone(),
+                                }
+                            } else {
+                                Message::AddMismatch {
+                                    lhs: left_ty.clone(),
+                                    rhs: right_ty.clone(),
+                                }
+                            };
+                            let diagnostic = Diagnostic::new(message, operand_span);
+                            Err(EvalErr { span, diagnostic })
+                        }
+                    },
+                    (Err(err), _) | (_, Err(err)) => Err(err),
+                },
+                BinOp::BitAnd(_)
+                | BinOp::BitOr(_)
+                | BinOp::BitXor(_)
+                | BinOp::ShiftLeft(_)
+                // operands are converted to integers, `1.0 & 3` is 1
+                | BinOp::ShiftRight(_) => eval_numeric(lhs_eval?, rhs_eval?, "bitwise operation")
+                    .map(|value| EvalType {
+                        ty: TypeKind::Integer,
+                        ..value
+                    }),
+                BinOp::Sub(_) => eval_numeric(lhs_eval?, rhs_eval?, "subtraction"),
+                BinOp::Mul(_) => eval_numeric(lhs_eval?, rhs_eval?, "multiplication"),
+                BinOp::Mod(_) => eval_numeric(lhs_eval?, rhs_eval?, "modulo"),
+                BinOp::FloorDiv(_) => eval_numeric(lhs_eval?, rhs_eval?, "floor division"),
+                // `^` is a float power even on integers
+                BinOp::Pow(_) => eval_numeric(lhs_eval?, rhs_eval?, "exponentiation").map(
+                    |value| EvalType {
+                        ty: TypeKind::Number,
+                        ..value
+                    },
+                ),
+                // numbers are converted to strings
+                BinOp::Concat(_) => {
+                    let (lhs, rhs) = (lhs_eval?, rhs_eval?);
+                    let span = Span::new(lhs.span.start.clone(), rhs.span.end.clone());
+                    let expected = TypeKind::Union(vec![TypeKind::String, TypeKind::Number]);
+                    match [&lhs, &rhs]
+                        .into_iter()
+                        .find_map(|operand| operand_mismatch(operand, &expected, "concatenation"))
+                    {
+                        Some(diagnostic) => Err(EvalErr { span, diagnostic }),
+                        None => Ok(EvalType {
+                            span,
+                            ty: TypeKind::String,
+                        }),
+                    }
+                }
+                // both numbers or both strings
+                BinOp::LessThan(_)
+                | BinOp::LessThanEqual(_)
+                | BinOp::GreaterThan(_)
+                | BinOp::GreaterThanEqual(_) => {
+                    let (lhs, rhs) = (lhs_eval?, rhs_eval?);
+                    let span = Span::new(lhs.span.start.clone(), rhs.span.end.clone());
+                    let comparable = |lhs: &TypeKind, rhs: &TypeKind| {
+                        [TypeKind::Number, TypeKind::String].iter().any(|ty| {
+                            TypeKind::subtype(lhs, ty) && TypeKind::subtype(rhs, ty)
+                        })
+                    };
+                    if comparable(&lhs.ty, &rhs.ty) {
+                        return Ok(EvalType {
+                            span,
+                            ty: TypeKind::Boolean,
+                        });
+                    }
+                    // number|nil compared without a nil check
+                    let optional = [&lhs, &rhs]
+                        .into_iter()
+                        .find(|operand| operand.ty.is_optional());
+                    let diagnostic = match optional {
+                        Some(operand)
+                            if comparable(&lhs.ty.non_nil_part(), &rhs.ty.non_nil_part()) =>
+                        {
+                            Diagnostic::new(
+                                Message::NilArithmetic {
+                                    operand: operand.ty.clone(),
+                                },
+                                operand.span.clone(),
+                            )
+                        }
+                        _ => Diagnostic::new(
+                            Message::CompareMismatch {
+                                lhs: lhs.ty.clone(),
+                                rhs: rhs.ty.clone(),
+                            },
+                            span.clone(),
+                        ),
+                    };
+                    Err(EvalErr { span, diagnostic })
+                }
+                // `/` is a float division even on integers
+                BinOp::Div(_) => {
+                    eval_numeric(lhs_eval?, rhs_eval?, "division").map(|value| EvalType {
+                        ty: TypeKind::Number,
+                        ..value
+                    })
+                }
+                BinOp::Equal(_) | BinOp::NotEqual(_) => {
+                    let lhs_ty = lhs_eval?;
+                    let rhs_ty = rhs_eval?;
+                    // operands of disjoint types are reported by check_comparisons
+                    Ok(EvalType {
+                        span: Span::new(lhs_ty.span.start, rhs_ty.span.end),
+                        ty: TypeKind::Boolean,
+                    })
+                }
+                BinOp::And(_) | BinOp::Or(_) => unreachable!("evaluated by eval_logical"),
+            }
+        }
+        Expression::UnaryOperator { unop, expr, span } => {
+            let operand = eval_expr(expr, env, registry)?;
+            let (expected, operation) = match unop {
+                UnOp::Not => {
+                    return Ok(EvalType {
+                        span: span.clone(),
+                        ty: TypeKind::Boolean,
+                    });
+                }
+                UnOp::Minus => (TypeKind::Number, "negation"),
+                UnOp::Tilde => (TypeKind::Number, "bitwise operation"),
+                UnOp::Hash => (
+                    TypeKind::Union(vec![TypeKind::String, TypeKind::Table]),
+                    "length operation",
+                ),
+            };
+            if let Some(diagnostic) = operand_mismatch(&operand, &expected, operation) {
+                return Err(EvalErr {
+                    span: span.clone(),
+                    diagnostic,
+                });
+            }
+            // `-` keeps integers, `~` and `#` are integers
+            let ty = match unop {
+                UnOp::Minus if operand.ty != TypeKind::Integer => TypeKind::Number,
+                _ => TypeKind::Integer,
+            };
+            Ok(EvalType {
+                span: span.clone(),
+                ty,
+            })
+        }
+        Expression::Table { fields, span } => {
+            let mut elem_tys: Vec<TypeKind> = Vec::new();
+            let mut record_fields: Vec<(String, TypeKind)> = Vec::new();
+            for field in fields.iter() {
+                let value = eval_expr(field.value(), env, registry)?;
+                let elem_ty = value.ty.widen_literal();
+                if !elem_tys.contains(&elem_ty) {
+                    elem_tys.push(elem_ty);
+                }
+                if let TableField::NameKey { key, .. } = field {
+                    record_fields.push((key.name.clone(), value.ty.widen_literal()));
+                }
+            }
+            // { v1, v2, ... } is array of the element types
+            // { k1 = v1, k2 = v2, ... } is record of the field types
+            let is_array =
+                !fields.is_empty() && fields.iter().all(|f| matches!(f, TableField::NoKey { .. }));
+            let is_record = !fields.is_empty() && record_fields.len() == fields.len();
+            let ty = if is_array {
+                let elem_ty = if elem_tys.len() == 1 {
+                    elem_tys.remove(0)
+                } else {
+                    TypeKind::Union(elem_tys)
+                };
+                TypeKind::Array(Box::new(elem_ty))
+            } else if is_record {
+                TypeKind::Record(record_fields)
+            } else {
+                TypeKind::Table
+            };
+            Ok(EvalType {
+                span: span.clone(),
+                ty,
+            })
+        }
+        Expression::FunctionCall(call) => {
+            if let Some(diagnostic) = check_call_args(call, env, registry).into_iter().next() {
+                return Err(EvalErr {
+                    span: call.span.clone(),
+                    diagnostic,
+                });
+            }
+            // a single value is the first return, unannotated returns are not inferred yet
+            let ty = match call_returns(call, env, registry) {
+                Some((returns, vararg)) => returns
+                    .into_iter()
+                    .next()
+                    .or(vararg)
+                    .unwrap_or(TypeKind::Any),
+                None => TypeKind::Any,
+            };
+            Ok(EvalType {
+                span: call.span.clone(),
+                ty,
+            })
+        }
+        Expression::Parentheses { expr, span } => {
+            let value = eval_expr(expr, env, registry)?;
+            Ok(EvalType {
+                span: span.clone(),
+                ty: value.ty,
+            })
+        }
+        Expression::Field { expr, key } => {
+            let object = eval_expr(expr, env, registry)?;
+            let span = Span::new(object.span.start.clone(), key.span.end.clone());
+            // fields of tables other than class instances and records are not tracked
+            //   None => the table is known to have no such field
+            let ty = match &object.ty {
+                TypeKind::Record(fields) => fields
+                    .iter()
+                    .find(|(name, _)| *name == key.name)
+                    .map(|(_, ty)| ty.clone()),
+                TypeKind::Custom(class)
+                    if registry.get_class(class).is_some_and(|class| class.exact) =>
+                {
+                    field_type(&object.ty, &key.name, registry)
+                }
+                ty => Some(field_type(ty, &key.name, registry).unwrap_or(TypeKind::Any)),
+            };
+            let Some(ty) = ty else {
+                return Err(EvalErr {
+                    span,
+                    diagnostic: Diagnostic::new(
+                        Message::UndefinedField {
+                            ty: object.ty,
+                            field: key.name.clone(),
+                        },
+                        key.span.clone(),
+                    ),
+                });
+            };
+            Ok(EvalType { span, ty })
+        }
+        // elements of arrays and values of tables<K, V>, others are not tracked
+        Expression::Index { expr, key, span } => {
+            let object = eval_expr(expr, env, registry)?;
+            let key = eval_expr(key, env, registry)?;
+            let (expected, ty) = match registry.resolve(&object.ty) {
+                TypeKind::Array(elem) => (TypeKind::Number, *elem),
+                TypeKind::Dict { key, val } | TypeKind::KVTable { key, val } => (*key, *val),
+                _ => (TypeKind::Any, TypeKind::Any),
+            };
+            if !TypeKind::subtype(&key.ty, &expected) {
+                return Err(EvalErr {
+                    span: span.clone(),
+                    diagnostic: Diagnostic::new(
+                        Message::KeyMismatch {
+                            key: key.ty,
+                            expected,
+                        },
+                        key.span,
+                    ),
+                });
+            }
+            Ok(EvalType {
+                span: span.clone(),
+                ty,
+            })
+        }
+        Expression::Var { span, symbol } => match env.get(&Symbol::new(symbol.clone())) {
+            Some(ty) => Ok(EvalType {
+                span: span.clone(),
+                ty: registry.resolve(&ty),
+            }),
+            None => Err(EvalErr {
+                span: span.clone(),
+                diagnostic: Diagnostic::new(
+                    Message::NotDeclared {
+                        name: symbol.clone(),
+                    },
+                    span.clone(),
+                ),
+            }),
+        },
+        // bodies of function expressions and the values of `...` are not tracked yet
+        Expression::Function { span, .. } | Expression::Vararg { span } => Ok(EvalType {
+            span: span.clone(),
+            ty: TypeKind::Any,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use typua_binder::Binder;
+    use typua_config::LuaVersion;
+    use typua_parser::parse;
+    use typua_span::{Position, Span};
+    use typua_ty::diagnostic::{DiagnosticKind, Severity};
+    use unindent::unindent;
+    fn check(code: &str) -> CheckResult {
+        let (ast, _) = parse(&unindent(code), LuaVersion::Lua51);
+        let mut binder = Binder::new();
+        binder.bind(&ast);
+        typecheck(&ast, &binder)
+    }
+    #[test]
+    fn eval_expr_literal() {
+        let env = TypeEnv::new();
+        let expr = Expression::Number {
+            span: Span {
+                start: Position::new(0, 0),
+                end: Position::new(0, 0),
+            },
+            integer: false,
+        };
+        let ret = eval_expr(&expr, &env, &TypeRegistry::new());
+        assert_eq!(ret.is_ok(), true);
+        assert_eq!(
+            ret.unwrap(),
+            EvalType {
+                span: Span::new(Position::new(0, 0), Position::new(0, 0)),
+                ty: TypeKind::Number
+            }
+        );
+    }
+    #[test]
+    fn eval_expr_binop() {
+        // normal test: number + number
+        let env = TypeEnv::new();
+        let expr = Expression::BinaryOperator {
+            lhs: Box::new(Expression::Number {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 0),
+                },
+                integer: false,
+            }),
+            rhs: Box::new(Expression::Number {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 10),
+                },
+                integer: false,
+            }),
+            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
+        };
+        let ret = eval_expr(&expr, &env, &TypeRegistry::new());
+        assert_eq!(ret.is_ok(), true);
+        assert_eq!(
+            ret.unwrap(),
+            EvalType {
+                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                ty: TypeKind::Number,
+            }
+        );
+
+        // TypeMismatch: number + bool
+        let env = TypeEnv::new();
+        let expr = Expression::BinaryOperator {
+            lhs: Box::new(Expression::Boolean {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 0),
+                },
+                value: true,
+            }),
+            rhs: Box::new(Expression::Number {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 10),
+                },
+                integer: false,
+            }),
+            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
+        };
+        let ret = eval_expr(&expr, &env, &TypeRegistry::new());
+        assert_eq!(ret.is_err(), true);
+        assert_eq!(
+            ret.unwrap_err(),
+            EvalErr {
+                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                diagnostic: Diagnostic {
+                    message: "cannot add `true` and `number`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(0, 0), Position::new(0, 0)),
+                }
+            }
+        );
+        // normal test: binop vars
+        let mut env = TypeEnv::new();
+        let _ = env.insert(&Symbol::new("x".to_string()), &TypeKind::Number);
+        let _ = env.insert(&Symbol::new("y".to_string()), &TypeKind::Number);
+        let expr = Expression::BinaryOperator {
+            lhs: Box::new(Expression::Var {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 0),
+                },
+                symbol: "x".to_string(),
+            }),
+            rhs: Box::new(Expression::Var {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 10),
+                },
+                symbol: "y".to_string(),
+            }),
+            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
+        };
+        let ret = eval_expr(&expr, &env, &TypeRegistry::new());
+        assert_eq!(ret.is_ok(), true);
+        assert_eq!(
+            ret.unwrap(),
+            EvalType {
+                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                ty: TypeKind::Number,
+            }
+        );
+        // abnormal test: binop vars
+        let mut env = TypeEnv::new();
+        let _ = env.insert(&Symbol::new("x".to_string()), &TypeKind::Number);
+        let _ = env.insert(&Symbol::new("y".to_string()), &TypeKind::Boolean);
+        let expr = Expression::BinaryOperator {
+            lhs: Box::new(Expression::Var {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 0),
+                },
+                symbol: "x".to_string(),
+            }),
+            rhs: Box::new(Expression::Var {
+                span: Span {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 10),
+                },
+                symbol: "y".to_string(),
+            }),
+            binop: BinOp::Add(Span::new(Position::new(0, 0), Position::new(0, 0))),
+        };
+        let ret = eval_expr(&expr, &env, &TypeRegistry::new());
+        assert_eq!(ret.is_err(), true);
+        assert_eq!(
+            ret.unwrap_err(),
+            EvalErr {
+                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                diagnostic: Diagnostic {
+                    message: "cannot add `number` and `boolean`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                }
+            }
+        );
+    }
+    #[test]
+    fn eval_expr_var() {
+        let mut env = TypeEnv::new();
         let _ = env.insert(&Symbol::new("x".to_string()), &TypeKind::Number);
         // normal test: number
         let expr = Expression::Var {
             span: Span::new(Position::new(0, 0), Position::new(0, 10)),
             symbol: "x".to_string(),
         };
-        let ret = eval_expr(&expr, &env);
-        assert_eq!(ret.is_ok(), true);
+        let ret = eval_expr(&expr, &env, &TypeRegistry::new());
+        assert_eq!(ret.is_ok(), true);
+        assert_eq!(
+            ret.unwrap(),
+            EvalType {
+                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                ty: TypeKind::Number,
+            }
+        );
+        // abnormal test: number
+        let expr = Expression::Var {
+            span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+            symbol: "y".to_string(),
+        };
+        let ret = eval_expr(&expr, &env, &TypeRegistry::new());
+        assert_eq!(ret.is_err(), true);
+        assert_eq!(
+            ret.unwrap_err(),
+            EvalErr {
+                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                diagnostic: Diagnostic {
+                    span: Span::new(Position::new(0, 0), Position::new(0, 10)),
+                    kind: DiagnosticKind::NotDeclaredVariable,
+                    message: "'y' is not declared".to_string()
+                }
+            }
+        );
+    }
+    #[test]
+    fn assert_narrowing() {
+        // without assert, x may be nil
+        let result = check(
+            r#"
+            ---@type number?
+            local x = nil
+            ---@type number
+            local y = x
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `number|nil` to `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(4, 11), Position::new(4, 12)),
+            }]
+        );
+        // after assert(x ~= nil), x is number
+        let result = check(
+            r#"
+            ---@type number?
+            local x = nil
+            assert(x ~= nil)
+            ---@type number
+            local y = x
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn error_terminates_block() {
+        let result = check(
+            r#"
+            local x = 1
+            error("x")
+            local y = 2
+            local z = 3
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "unreachable code".to_string(),
+                kind: DiagnosticKind::UnreachableCode,
+                span: Span::new(Position::new(3, 7), Position::new(4, 12)),
+            }]
+        );
+        // nothing follows error
+        let result = check(
+            r#"
+            local x = 1
+            error("x")
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn binop_diagnostic_points_at_operand() {
+        let result = check(
+            r#"
+            local x = "a" + 1
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot add `\"a\"` and `integer`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(1, 11), Position::new(1, 14)),
+            }]
+        );
+        let result = check(
+            r#"
+            local x = 1 + "a"
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot add `integer` and `\"a\"`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(1, 15), Position::new(1, 18)),
+            }]
+        );
+    }
+    #[test]
+    fn table_field_inline_annotation() {
+        let result = check(
+            r#"
+            local t = { count = 0 --[[@type number]], name = "a" --[[@type string]] }
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            local t = { count = "zero" --[[@type number]] }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `\"zero\"` to `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(1, 21), Position::new(1, 27)),
+            }]
+        );
+        // every mismatching field is reported, nested tables and call arguments included
+        let result = check(
+            r#"
+            local t = {
+                count = "zero" --[[@type number]],
+                size = 1 --[[@type number]],
+                inner = { name = 2 --[[@type string]] },
+            }
+            print({ ok = 1 --[[@type boolean]] })
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.message.as_str(), d.span.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "cannot assign `\"zero\"` to `number`",
+                    Span::new(Position::new(2, 13), Position::new(2, 19))
+                ),
+                (
+                    "cannot assign `integer` to `string`",
+                    Span::new(Position::new(4, 22), Position::new(4, 23))
+                ),
+                (
+                    "cannot assign `integer` to `boolean`",
+                    Span::new(Position::new(6, 14), Position::new(6, 15))
+                ),
+            ]
+        );
+    }
+    #[test]
+    fn table_expression_key_annotation() {
+        let result = check(
+            r#"
+            local KEY = "k"
+            local t = {
+                ---@type number
+                [KEY] = 1,
+                [2] = "two" --[[@type string]],
+            }
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            local t = {
+                ---@type number
+                ["count"] = "zero",
+            }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `\"zero\"` to `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(3, 17), Position::new(3, 23)),
+            }]
+        );
+        // the annotation above a name key applies to its value as well
+        let result = check(
+            r#"
+            local t = {
+                ---@type number
+                count = 0,
+                ---@type number
+                name = "zero",
+            }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `\"zero\"` to `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(5, 12), Position::new(5, 18)),
+            }]
+        );
+        let result = check(
+            r#"
+            ---@type table<string, number>
+            local t = { ["a"] = 1, [2] = 2 }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot use `integer` as `string` key".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(2, 25), Position::new(2, 26)),
+            }]
+        );
+    }
+    #[test]
+    fn mixed_table_literal() {
+        let result = check(
+            r#"
+            ---@type number[]
+            local xs = { 1, 2, foo = 3 }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "keyed field in array table literal".to_string(),
+                kind: DiagnosticKind::MixedTableLiteral,
+                span: Span::new(Position::new(2, 20), Position::new(2, 27)),
+            }]
+        );
+        // pure array and pure record are clean
+        let result = check(
+            r#"
+            ---@type number[]
+            local xs = { 1, 2, 3 }
+            ---@type number[]
+            local ys = { foo = 3 }
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        // element type is still checked
+        let result = check(
+            r#"
+            ---@type number[]
+            local xs = { 1, "a" }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `(number|string)[]` to `number[]`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(2, 12), Position::new(2, 22)),
+            }]
+        );
+    }
+    #[test]
+    fn doc_annotation_does_not_affect_types() {
+        let result = check(
+            r#"
+            ---@see other
+            ---@since 0.2
+            ---@type number
+            local x = "a"
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot annotate string literal as `number`".to_string(),
+                kind: DiagnosticKind::AssignTypeMismatch,
+                span: Span::new(Position::new(4, 11), Position::new(4, 14)),
+            }]
+        );
+    }
+    #[test]
+    fn parenthesized_call_truncates_returns() {
+        let result = check(
+            r#"
+            ---@return number
+            ---@return string
+            local function f()
+            end
+            local a, b = (f())
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "no value is assigned to `b`".to_string(),
+                kind: DiagnosticKind::UnbalancedAssignment,
+                span: Span::new(Position::new(5, 10), Position::new(5, 11)),
+            }]
+        );
+        let result = check(
+            r#"
+            ---@return number
+            ---@return string
+            local function f()
+            end
+            ---@type number, string
+            local a, b = f()
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn assert_type_narrowing() {
+        let result = check(
+            r#"
+            ---@type number|string
+            local x = 1
+            assert(type(x) == "number")
+            ---@type number
+            local y = x
+            ---@type string
+            local z = x
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `number` to `string`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(7, 11), Position::new(7, 12)),
+            }]
+        );
+    }
+    #[test]
+    fn malformed_annotation() {
+        let result = check(
+            r#"
+            ---@type number|
+            local x = 1
+            ---@type number||string
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "malformed annotation, empty member in union type".to_string(),
+                    kind: DiagnosticKind::MalformedAnnotation,
+                    span: Span::new(Position::new(1, 1), Position::new(1, 17)),
+                },
+                Diagnostic {
+                    message: "malformed annotation, empty member in union type".to_string(),
+                    kind: DiagnosticKind::MalformedAnnotation,
+                    span: Span::new(Position::new(3, 1), Position::new(3, 24)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn dangling_annotation() {
+        let result = check(
+            r#"
+            local x = 1
+            ---@type number
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "annotation is not attached to any statement".to_string(),
+                kind: DiagnosticKind::DanglingAnnotation,
+                span: Span::new(Position::new(2, 10), Position::new(2, 16)),
+            }]
+        );
+        // definition-only file
+        let result = check(
+            r#"
+            ---@meta
+            ---@class Person
+            ---@field name string
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn module_without_return() {
+        let result = check(
+            r#"
+            ---@module "point"
+            local Point = {}
+            function Point.new() end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "module `point` returns no value, `require` gets `true`".to_string(),
+                kind: DiagnosticKind::MissingModuleReturn,
+                span: Span::new(Position::new(1, 1), Position::new(1, 19)),
+            }]
+        );
+        let result = check(
+            r#"
+            ---@module "point"
+            local Point = {}
+            function Point.new() end
+            return Point
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn shadowed_builtin() {
+        let result = check(
+            r#"
+            local table = 1
+            local tbl, _string = 1, "s"
+            local function type() end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "local `table` shadows the builtin global of the same name"
+                        .to_string(),
+                    kind: DiagnosticKind::ShadowedBuiltin,
+                    span: Span::new(Position::new(1, 7), Position::new(1, 12)),
+                },
+                Diagnostic {
+                    message: "local `type` shadows the builtin global of the same name".to_string(),
+                    kind: DiagnosticKind::ShadowedBuiltin,
+                    span: Span::new(Position::new(3, 16), Position::new(3, 20)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn annotated_field_assignment() {
+        let result = check(
+            r#"
+            ---@class Config
+            ---@field name string
+            local Config = {}
+            ---@class (exact) Opts
+            ---@field verbose boolean
+            local Opts = {}
+            ---@class App
+            ---@field config Config
+            local App = {}
+            ---@type App
+            local app = { config = { name = "a" } }
+            app.config = { name = 1 } --[[@as Config]]
+            ---@type Config
+            app.config = { name = "b" }
+            app.config = { verbose = true, quiet = false } --[[@as Opts]]
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot assign `integer` to `string`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(12, 23), Position::new(12, 24)),
+                },
+                Diagnostic {
+                    message: "`Opts` has no field `quiet`".to_string(),
+                    kind: DiagnosticKind::UndefinedField,
+                    span: Span::new(Position::new(15, 32), Position::new(15, 37)),
+                },
+                Diagnostic {
+                    message: "cannot assign `Opts` to `Config`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(15, 14), Position::new(15, 47)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn yield_outside_async() {
+        let result = check(
+            r#"
+            ---@async
+            local function producer()
+                coroutine.yield(1)
+                local x = coroutine.yield()
+            end
+            local function consumer(ready)
+                if ready then
+                    coroutine.yield(1)
+                end
+                return coroutine.yield()
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "`coroutine.yield` in a function not marked `---@async`".to_string(),
+                    kind: DiagnosticKind::YieldOutsideAsync,
+                    span: Span::new(Position::new(8, 9), Position::new(8, 27)),
+                },
+                Diagnostic {
+                    message: "`coroutine.yield` in a function not marked `---@async`".to_string(),
+                    kind: DiagnosticKind::YieldOutsideAsync,
+                    span: Span::new(Position::new(10, 12), Position::new(10, 29)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn break_outside_loop() {
+        let result = check(
+            r#"
+            local n = 1
+            while true do
+                if n == 5 then
+                    break
+                end
+                n = n + 1
+            end
+            repeat
+                break
+            until true
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            while true do
+                local function f()
+                    break
+                end
+            end
+            break
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "`break` outside a loop".to_string(),
+                    kind: DiagnosticKind::BreakOutsideLoop,
+                    span: Span::new(Position::new(3, 9), Position::new(3, 14)),
+                },
+                Diagnostic {
+                    message: "`break` outside a loop".to_string(),
+                    kind: DiagnosticKind::BreakOutsideLoop,
+                    span: Span::new(Position::new(6, 1), Position::new(6, 6)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn param_annotation_multiple_names() {
+        let result = check(
+            r#"
+            ---@param a, b number
+            local function f(a, b)
+                ---@type string
+                local x = b
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `number` to `string`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(4, 15), Position::new(4, 16)),
+            }]
+        );
+    }
+    #[test]
+    fn single_valued_calls() {
+        let result = check(
+            r#"
+            ---@return string
+            local function getName()
+            end
+            ---@return number
+            local function getAge()
+            end
+            local x, y = getName(), getAge()
+            ---@type string
+            local name = x
+            ---@type number
+            local age = y
+            ---@type number
+            local wrong = x
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `string` to `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(13, 15), Position::new(13, 16)),
+            }]
+        );
+    }
+    #[test]
+    fn function_subtyping() {
+        // covariant return
+        let result = check(
+            r#"
+            ---@param x number
+            ---@return number
+            local function f(x)
+            end
+            ---@type fun(x: number): number|nil
+            local g = f
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        // incompatible return
+        let result = check(
+            r#"
+            ---@param x number
+            ---@return string
+            local function f(x)
+            end
+            ---@type fun(x: number): number
+            local g = f
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `fun(x: number): string` to `fun(x: number): number`"
+                    .to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(6, 11), Position::new(6, 12)),
+            }]
+        );
+        // more required params than the annotation passes
+        let result = check(
+            r#"
+            ---@param x number
+            ---@param y number
+            ---@return number
+            local function f(x, y)
+            end
+            ---@type fun(x: number): number
+            local g = f
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `fun(x: number, y: number): number` to \
+                          `fun(x: number): number`"
+                    .to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(7, 11), Position::new(7, 12)),
+            }]
+        );
+    }
+    #[test]
+    fn vararg_returns() {
+        let result = check(
+            r#"
+            local function f()
+            end
+            ---@type fun(): number, ...string
+            local g = f
+            local a, b, c = g()
+            ---@type string
+            local first = g()
+            ---@type number
+            local third = c
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot assign `number` to `string`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(7, 15), Position::new(7, 18)),
+                },
+                Diagnostic {
+                    message: "cannot assign `string` to `number`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(9, 15), Position::new(9, 16)),
+                }
+            ]
+        );
+    }
+    #[test]
+    fn generic_method_call() {
+        let result = check(
+            r#"
+            ---@class List
+            ---@field map fun<T, U>(self, fn: fun(x: T): U): U[]
+            local List = {}
+            ---@param x number
+            ---@return string
+            local function show(x)
+                return "n"
+            end
+            ---@param list List
+            local function run(list)
+                local names = list:map(show)
+            end
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        assert_eq!(
+            result
+                .inferred
+                .iter()
+                .find(|local| local.span.start.line() == 11)
+                .map(|local| local.ty.clone()),
+            Some(TypeKind::Array(Box::new(TypeKind::String)))
+        );
+        // arguments are checked against the instantiated params
+        let result = check(
+            r#"
+            ---@class Box
+            ---@field set fun<T>(self, x: T, y: T)
+            local Box = {}
+            ---@param b Box
+            local function run(b)
+                b:set(1, "a")
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot pass `\"a\"` to parameter of type `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(6, 14), Position::new(6, 17)),
+            }]
+        );
+    }
+    #[test]
+    fn method_declaration_self() {
+        let result = check(
+            r#"
+            ---@class (exact) Player
+            ---@field hp number
+            local Player = {}
+            ---@param damage number
+            function Player:hit(damage)
+                local hp = self.hp
+                local xp = self.xp
+            end
+            "#,
+        );
+        assert_eq!(
+            result
+                .inferred
+                .iter()
+                .find(|local| local.span.start.line() == 6)
+                .map(|local| local.ty.clone()),
+            Some(TypeKind::Number)
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "`Player` has no field `xp`".to_string(),
+                kind: DiagnosticKind::UndefinedField,
+                span: Span::new(Position::new(7, 21), Position::new(7, 23)),
+            }]
+        );
+        // fields of a class which is not exact are not known to be absent
+        let result = check(
+            r#"
+            ---@class Player
+            ---@field hp number
+            local Player = {}
+            function Player:hit()
+                local xp = self.xp
+            end
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn global_function_declaration() {
+        let result = check(
+            r#"
+            ---@param x number
+            function f(x) end
+            f("a")
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot pass `\"a\"` to parameter of type `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(3, 3), Position::new(3, 6)),
+            }]
+        );
+    }
+    #[test]
+    fn undefined_method() {
+        let result = check(
+            r#"
+            ---@class (exact) Player
+            ---@field move fun(self: Player, dx: number)
+            local Player = {}
+            function Player:jump() end
+            ---@param player Player
+            local function step(player)
+                player:move(1)
+                player:jump()
+                player:fly()
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "`Player` has no method `fly`".to_string(),
+                kind: DiagnosticKind::UndefinedMethod,
+                span: Span::new(Position::new(9, 12), Position::new(9, 15)),
+            }]
+        );
+        // methods of a class which is not exact are not known to be absent, a hint is reported
+        let result = check(
+            r#"
+            ---@class Player
+            local Player = {}
+            function Player:jump() end
+            ---@param player Player
+            local function step(player)
+                player:jump()
+                player:fly()
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "`Player` declares no method `fly`, it may be added elsewhere".to_string(),
+                kind: DiagnosticKind::PossiblyUndefinedMethod,
+                span: Span::new(Position::new(7, 12), Position::new(7, 15)),
+            }]
+        );
+        assert_eq!(
+            DiagnosticKind::PossiblyUndefinedMethod.severity(),
+            Severity::Hint
+        );
+    }
+    #[test]
+    fn function_declaration_owner() {
+        let result = check(
+            r#"
+            ---@class (exact) Server
+            ---@field options table
+            local Server = {}
+            function Server.options.reload() end
+            function Server:start() end
+            function config.server.start() end
+            function Server.routes.add() end
+            local port = 80
+            function port.open() end
+            function main() end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "'config' is not declared".to_string(),
+                    kind: DiagnosticKind::NotDeclaredVariable,
+                    span: Span::new(Position::new(6, 10), Position::new(6, 16)),
+                },
+                Diagnostic {
+                    message: "`Server` has no field `routes`".to_string(),
+                    kind: DiagnosticKind::UndefinedField,
+                    span: Span::new(Position::new(7, 17), Position::new(7, 23)),
+                },
+                Diagnostic {
+                    message: "cannot declare a function in `number`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(9, 10), Position::new(9, 14)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn method_field_with_self() {
+        // self is implicit in method call, explicit in field call
+        let result = check(
+            r#"
+            ---@class Player
+            ---@field move fun(self: Player, dx: number)
+            local Player = {}
+            ---@param player Player
+            local function step(player)
+                player:move(1)
+                player.move(player, 1)
+            end
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            ---@class Player
+            ---@field move fun(self: Player, dx: number)
+            local Player = {}
+            ---@param player Player
+            local function step(player)
+                player:move("1")
+                player:move(player, 1)
+                player.move(1)
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot pass `\"1\"` to parameter of type `number`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(6, 17), Position::new(6, 20)),
+                },
+                Diagnostic {
+                    message: "cannot pass `Player` to parameter of type `number`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(7, 17), Position::new(7, 23)),
+                },
+                Diagnostic {
+                    message: "expected 1 argument, found 2".to_string(),
+                    kind: DiagnosticKind::ArgumentCountMismatch,
+                    span: Span::new(Position::new(7, 5), Position::new(7, 27)),
+                },
+                Diagnostic {
+                    message: "cannot pass `integer` to parameter of type `Player`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(8, 17), Position::new(8, 18)),
+                },
+                Diagnostic {
+                    message: "expected 2 arguments, found 1".to_string(),
+                    kind: DiagnosticKind::ArgumentCountMismatch,
+                    span: Span::new(Position::new(8, 5), Position::new(8, 19)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn narrowing_reset_at_label() {
+        let code = unindent(
+            r#"
+            ---@type number|nil
+            local x = nil
+            assert(x)
+            ---@type number
+            local before = x
+            ::continue::
+            ---@type number
+            local after = x
+            goto continue
+            "#,
+        );
+        let (ast, _) = parse(&code, LuaVersion::Lua52);
+        let mut binder = Binder::new();
+        binder.bind(&ast);
+        let result = typecheck(&ast, &binder);
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `number|nil` to `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(8, 15), Position::new(8, 16)),
+            }]
+        );
+    }
+    #[test]
+    fn inline_cast_return() {
+        let result = check(
+            r#"
+            ---@class Foo
+            local Foo = {}
+            ---@return Foo
+            local function new()
+                local value = {}
+                return value --[[@type Foo]]
+            end
+            ---@class Bar
+            local Bar = {}
+            ---@return Bar
+            local function wrong()
+                local value = {}
+                return value --[[@type Foo]]
+            end
+            ---@return Foo
+            local function uncast()
+                return 1
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot return `Foo` as `Bar`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(13, 12), Position::new(13, 17)),
+                },
+                Diagnostic {
+                    message: "cannot return `integer` as `Foo`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(17, 12), Position::new(17, 13)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn boolean_literal_type() {
+        let result = check(
+            r#"
+            ---@type true
+            local yes = true
+            ---@type true
+            local no = false
+            ---@type boolean
+            local flag = yes
+            ---@type true|false
+            local both = flag
+            ---@type false
+            local wrong = flag
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot annotate boolean literal as `true`".to_string(),
+                    kind: DiagnosticKind::AssignTypeMismatch,
+                    span: Span::new(Position::new(4, 12), Position::new(4, 17)),
+                },
+                Diagnostic {
+                    message: "cannot assign `boolean` to `false`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(10, 15), Position::new(10, 19)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn string_literal_union() {
+        let result = check(
+            r#"
+            ---@type "debug"|"info"|"warn"
+            local level = "info"
+            if level == "debug" then
+                ---@type "debug"
+                local dbg = level
+            else
+                ---@type "info"|"warn"
+                local rest = level
+            end
+            ---@type "debug"|"info"|"warn"
+            local wrong = "trace"
+            local never = level == "trace"
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot annotate string literal as `\"debug\"|\"info\"|\"warn\"`"
+                        .to_string(),
+                    kind: DiagnosticKind::AssignTypeMismatch,
+                    span: Span::new(Position::new(11, 15), Position::new(11, 22)),
+                },
+                Diagnostic {
+                    message: "`\"debug\"|\"info\"|\"warn\"` and `\"trace\"` are never equal"
+                        .to_string(),
+                    kind: DiagnosticKind::SuspiciousComparison,
+                    span: Span::new(Position::new(12, 15), Position::new(12, 31)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn boolean_literal_narrowing() {
+        let result = check(
+            r#"
+            ---@type boolean|nil
+            local flag = nil
+            if flag == true then
+                ---@type true
+                local t = flag
+            else
+                ---@type false|nil
+                local rest = flag
+            end
+            if flag ~= false then
+                ---@type true|nil
+                local maybe = flag
+            else
+                ---@type true
+                local wrong = flag
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `false` to `true`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(15, 19), Position::new(15, 23)),
+            }]
+        );
+    }
+    #[test]
+    fn negated_or_narrowing() {
+        let result = check(
+            r#"
+            ---@type number|nil
+            local x = nil
+            ---@type string|nil
+            local y = nil
+            if not (x == nil or y == nil) then
+                ---@type number
+                local n = x
+                ---@type string
+                local s = y
+            else
+                ---@type number
+                local wrong = x
+            end
+            if x == nil or y == nil then
+                ---@type string
+                local maybe = y
+            else
+                ---@type string
+                local sure = y
+            end
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.message.as_str(), d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("cannot assign `number|nil` to `number`", 12),
+                ("cannot assign `string|nil` to `string`", 16),
+            ]
+        );
+    }
+    #[test]
+    fn named_type_annotation() {
+        let result = check(
+            r#"
+            ---@type string y
+            local x, y = 1, 2
+            ---@type number z
+            local a, b = 1, 2
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot annotate number literal as `string`".to_string(),
+                    kind: DiagnosticKind::AssignTypeMismatch,
+                    span: Span::new(Position::new(2, 17), Position::new(2, 18)),
+                },
+                Diagnostic {
+                    message: "`z` is not declared by this statement".to_string(),
+                    kind: DiagnosticKind::UnknownAnnotationTarget,
+                    span: Span::new(Position::new(3, 10), Position::new(3, 18)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn conflicting_type_annotations() {
+        let result = check(
+            r#"
+            ---@type number
+            ---@type string
+            local x = 1
+            ---@type number a
+            ---@type string b
+            local a, b = 1, "s"
+            ---@type number, string
+            local c, d = 1, "s"
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "`---@type` is already given, this one is ignored".to_string(),
+                kind: DiagnosticKind::ConflictingAnnotation,
+                span: Span::new(Position::new(2, 10), Position::new(2, 16)),
+            }]
+        );
+    }
+    #[test]
+    fn index_annotated_field() {
+        let result = check(
+            r#"
+            ---@class Bag
+            ---@field items number[]
+            ---@field names table<string, string>
+            local Bag = {}
+            function Bag:fill()
+                self.items = {}
+                local first = self.items[1]
+                ---@type string
+                local wrong = self.items[2]
+                local name = self.names["a"]
+                local missing = self.names[1]
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot assign `number` to `string`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(9, 19), Position::new(9, 32)),
+                },
+                Diagnostic {
+                    message: "cannot use `integer` as `string` key".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(11, 32), Position::new(11, 33)),
+                },
+            ]
+        );
+        let inferred: Vec<&TypeKind> = result.inferred.iter().map(|local| &local.ty).collect();
+        assert_eq!(inferred, vec![&TypeKind::Number, &TypeKind::String]);
+    }
+    #[test]
+    fn union_field_assignment() {
+        let result = check(
+            r#"
+            ---@class (exact) Box
+            ---@field value number|string
+            ---@field label string?
+            local Box = {}
+            function Box:set()
+                self.value = 1
+                self.value = "one"
+                self.value = true
+                self.label = nil
+                self.label = "x"
+                self.size = 1
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot assign `true` to `number|string`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(8, 18), Position::new(8, 22)),
+                },
+                Diagnostic {
+                    message: "`Box` has no field `size`".to_string(),
+                    kind: DiagnosticKind::UndefinedField,
+                    span: Span::new(Position::new(11, 10), Position::new(11, 14)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn cast_annotation() {
+        let result = check(
+            r#"
+            ---@type number|nil
+            local x = nil
+            ---@cast x -nil
+            local narrowed = x
+            ---@cast x +string
+            local widened = x
+            ---@cast y -nil
+            local z = 1
+            "#,
+        );
+        let inferred = |line: u32| {
+            result
+                .inferred
+                .iter()
+                .find(|local| local.span.start.line() == line)
+                .map(|local| local.ty.clone())
+        };
+        assert_eq!(inferred(4), Some(TypeKind::Number));
         assert_eq!(
-            ret.unwrap(),
-            EvalType {
-                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                ty: TypeKind::Number,
-            }
+            inferred(6),
+            Some(TypeKind::Union(vec![TypeKind::Number, TypeKind::String]))
         );
-        // abnormal test: number
-        let expr = Expression::Var {
-            span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-            symbol: "y".to_string(),
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "`y` is not declared".to_string(),
+                kind: DiagnosticKind::UnknownAnnotationTarget,
+                span: Span::new(Position::new(7, 1), Position::new(7, 16)),
+            }]
+        );
+    }
+    #[test]
+    fn cast_before_call_and_return() {
+        let result = check(
+            r#"
+            ---@param n number
+            local function double(n)
+            end
+            ---@type number|nil
+            local x = nil
+            ---@cast x -nil
+            double(x)
+            ---@return string
+            local function name()
+                ---@type string|nil
+                local y = nil
+                ---@cast y string
+                return y
+            end
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn vararg_and_function_expressions() {
+        let result = check(
+            r#"
+            ---@param first number
+            local function sum(first, ...)
+                local rest = { ... }
+                return first
+            end
+            sum(1, 2, 3)
+            local square = function(x)
+                return x * x
+            end
+            local upper = ("x"):upper()
+            sum{}
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| d.message.as_str())
+                .collect::<Vec<_>>(),
+            vec!["cannot pass `table` to parameter of type `number`"]
+        );
+    }
+    #[test]
+    fn record_field_read() {
+        let result = check(
+            r#"
+            local t = { x = 1, ok = true }
+            local x = t.x
+            local ok = t.ok
+            local z = t.z
+            "#,
+        );
+        assert_eq!(
+            result.inferred[0].ty,
+            TypeKind::Record(vec![
+                ("x".to_string(), TypeKind::Number),
+                ("ok".to_string(), TypeKind::Boolean),
+            ])
+        );
+        assert_eq!(result.inferred[1].ty, TypeKind::Number);
+        assert_eq!(result.inferred[2].ty, TypeKind::Boolean);
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "`{ x: number, ok: boolean }` has no field `z`".to_string(),
+                kind: DiagnosticKind::UndefinedField,
+                span: Span::new(Position::new(4, 13), Position::new(4, 14)),
+            }]
+        );
+    }
+    #[test]
+    fn early_return_guard() {
+        let result = check(
+            r#"
+            ---@type number|nil
+            local x = nil
+            if x == nil then
+                return
+            end
+            local y = x
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        assert_eq!(result.inferred[0].ty, TypeKind::Number);
+        // error(...) exits too, a branch falling through keeps nil
+        let result = check(
+            r#"
+            ---@type number|nil
+            local x = nil
+            ---@type string|nil
+            local s = nil
+            if x == nil then
+                error("x is required")
+            elseif s == nil then
+                local message = "no s"
+            end
+            local y = x
+            local t = s
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let inferred = |line: u32| {
+            result
+                .inferred
+                .iter()
+                .find(|local| local.span.start.line() == line)
+                .map(|local| local.ty.clone())
         };
-        let ret = eval_expr(&expr, &env);
-        assert_eq!(ret.is_err(), true);
+        assert_eq!(inferred(10), Some(TypeKind::Number));
         assert_eq!(
-            ret.unwrap_err(),
-            EvalErr {
-                span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                diagnostic: Diagnostic {
-                    span: Span::new(Position::new(0, 0), Position::new(0, 10)),
-                    kind: DiagnosticKind::NotDeclaredVariable,
-                    message: "'y' is not declared".to_string()
-                }
-            }
+            inferred(11),
+            Some(TypeKind::Union(vec![TypeKind::String, TypeKind::Nil]))
+        );
+    }
+    #[test]
+    fn narrowing_until_reassigned() {
+        let result = check(
+            r#"
+            ---@param x number?
+            local function f(x)
+                if x == nil then
+                    return
+                end
+                local y = x + 1
+                local z = 2 + x
+            end
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            ---@param x number?
+            ---@param c boolean
+            local function f(x, c)
+                if x == nil then
+                    return
+                end
+                if c then
+                    x = nil
+                end
+                local y = x + 1
+                ---@type number?
+                local w = 1
+                if w ~= nil then
+                    w = nil
+                    local v = w + 1
+                end
+            end
+            "#,
+        );
+        let nil_arithmetic = |line: u32, character: u32| Diagnostic {
+            message: "operand of type `number|nil` may be nil, check it for nil first".to_string(),
+            kind: DiagnosticKind::NilArithmetic,
+            span: Span::new(
+                Position::new(line, character),
+                Position::new(line, character + 1),
+            ),
+        };
+        assert_eq!(
+            result.diagnostics,
+            vec![nil_arithmetic(10, 15), nil_arithmetic(15, 19)]
+        );
+    }
+    #[test]
+    fn closure_captures_reassigned_local() {
+        let code = |reassign: &str| {
+            format!(
+                r#"
+                ---@type number|nil
+                local x = 1
+                if x == nil then
+                    return
+                end
+                local function f()
+                    local y = x + 1
+                end
+                {reassign}
+                "#
+            )
+        };
+        assert_eq!(check(&code("")).diagnostics, Vec::new());
+        assert_eq!(
+            check(&code("x = nil"))
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.kind, d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![(DiagnosticKind::NilArithmetic, 7)]
+        );
+    }
+    #[test]
+    fn function_alias_param() {
+        let result = check(
+            r#"
+            ---@alias Handler fun(e: string): boolean
+            ---@param h Handler
+            ---@return boolean
+            local function run(h)
+                local ok = h("click")
+                local bad = h(1)
+                return ok
+            end
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.kind.clone(), d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![(DiagnosticKind::TypeMismatch, 6)]
+        );
+        assert_eq!(
+            result
+                .inferred
+                .iter()
+                .find(|local| local.span.start.line() == 5)
+                .map(|local| local.ty.clone()),
+            Some(TypeKind::Boolean)
+        );
+    }
+    #[test]
+    fn nil_arithmetic() {
+        let result = check(
+            r#"
+            ---@type number|nil
+            local x = nil
+            local y = x + 1
+            if x ~= nil then
+                local z = x + 1
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "operand of type `number|nil` may be nil, check it for nil first"
+                    .to_string(),
+                kind: DiagnosticKind::NilArithmetic,
+                span: Span::new(Position::new(3, 11), Position::new(3, 12)),
+            }]
+        );
+    }
+    #[test]
+    fn add_non_number_operand() {
+        // tests/scripts/binop.lua, `bool` is not a declared type
+        let result = check(
+            r#"
+            ---@type bool
+            local a = 1
+            local b = 1 + a
+            ---@alias Count integer
+            ---@type Count
+            local c = 2
+            local d = 1 + c
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.message.as_str(), d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("cannot annotate number literal as `bool`", 2),
+                ("cannot add `integer` and `bool`", 3),
+            ]
+        );
+    }
+    #[test]
+    fn bitwise_operators() {
+        let check_version = |code: &str, version: LuaVersion| {
+            let (ast, errors) = parse(&unindent(code), version);
+            let mut binder = Binder::new();
+            binder.bind(&ast);
+            (errors.len(), typecheck(&ast, &binder).diagnostics)
+        };
+        // always an integer, even from floats
+        let (errors, diagnostics) = check_version(
+            r#"
+            ---@type integer
+            local mask = 0xff & 0x0f | 1 << 4
+            ---@type number
+            local ratio = 1.5
+            ---@type integer
+            local flags = ratio ~ 2 >> 1
+            ---@type integer
+            local low = 3.0 & 1
+            "#,
+            LuaVersion::Lua54,
+        );
+        assert_eq!((errors, diagnostics), (0, Vec::new()));
+        let (_, diagnostics) = check_version("local bad = 1 & \"x\"\n", LuaVersion::Lua53);
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                message: "cannot use `\"x\"` in bitwise operation".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(1, 17), Position::new(1, 20)),
+            }]
+        );
+        // not an operator before lua 5.3
+        let (_, errors) = parse("local mask = 1 & 2\n", LuaVersion::Lua51);
+        assert!(!errors.is_empty());
+    }
+    #[test]
+    fn floor_division() {
+        let (ast, errors) = parse(
+            "local half = 7 // 2\nlocal bad = \"7\" // 2\n",
+            LuaVersion::Lua54,
+        );
+        assert!(errors.is_empty());
+        let mut binder = Binder::new();
+        binder.bind(&ast);
+        let result = typecheck(&ast, &binder);
+        assert_eq!(result.inferred[0].ty, TypeKind::Number);
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot use `\"7\"` in floor division".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(2, 13), Position::new(2, 16)),
+            }]
+        );
+        let (_, errors) = parse("local half = 7 // 2\n", LuaVersion::Lua51);
+        assert!(!errors.is_empty());
+    }
+    #[test]
+    fn arithmetic_operators() {
+        let result = check(
+            r#"
+            ---@type integer
+            local a = 2 * 3 - 7 % 2
+            ---@type integer
+            local b = -a
+            ---@type integer
+            local c = 2 ^ 2
+            ---@type number|nil
+            local d = nil
+            local e = d * 2
+            local f = -"x"
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.message.as_str(), d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("cannot assign `number` to `integer`", 6),
+                (
+                    "operand of type `number|nil` may be nil, check it for nil first",
+                    9
+                ),
+                ("cannot use `\"x\"` in negation", 10),
+            ]
+        );
+    }
+    #[test]
+    fn string_and_comparison_operators() {
+        let result = check(
+            r#"
+            ---@type string
+            local s = "a" .. "b" .. 1
+            ---@type integer
+            local n = #s
+            ---@type boolean
+            local lt = 1 < n
+            ---@type boolean
+            local ge = "a" >= s
+            ---@type boolean
+            local no = not s
+            local bad = s .. {}
+            local len = #1
+            local cmp = 1 < "2"
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.message.as_str(), d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("cannot use `table` in concatenation", 11),
+                ("cannot use `integer` in length operation", 12),
+                ("cannot compare `integer` with `\"2\"`", 13),
+            ]
+        );
+    }
+    #[test]
+    fn logical_operators() {
+        let result = check(
+            r#"
+            ---@param name string|nil
+            ---@param fallback string
+            local function greet(name, fallback)
+                ---@type string
+                local a = name or fallback
+                ---@type string
+                local b = name and name .. "!" or fallback
+                ---@type string|nil
+                local c = name and fallback
+                ---@type string
+                local d = name and fallback
+            end
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.message.as_str(), d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![("cannot assign `nil|string` to `string`", 11)]
+        );
+    }
+    #[test]
+    fn integer_arithmetic() {
+        let result = check(
+            r#"
+            ---@type integer
+            local x = 2 + 3
+            ---@type integer
+            local y = 1 / 2
+            ---@type number
+            local z = 1 / 2
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot assign `number` to `integer`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(4, 11), Position::new(4, 16)),
+            }]
+        );
+    }
+    #[test]
+    fn any_param() {
+        let code = r#"
+            ---@param x any
+            local function f(x, y)
+                x = "a"
+                x = 1
+                x = y
+                x = {}
+                local a = x + 1
+                local b = 1 + y
+            end
+            "#;
+        let result = check(code);
+        assert_eq!(result.diagnostics, Vec::new());
+        let (ast, _errors) = parse(&unindent(code), LuaVersion::Lua51);
+        let mut binder = Binder::new();
+        binder.bind(&ast);
+        assert_eq!(
+            binder.type_env.get(&Symbol::new("f".to_string())),
+            Some(TypeKind::Function {
+                generics: Vec::new(),
+                param_names: vec!["x".to_string(), "y".to_string()],
+                params: vec![TypeKind::Any, TypeKind::Unknown],
+                returns: Vec::new(),
+                return_names: Vec::new(),
+                vararg: None,
+            })
+        );
+    }
+    #[test]
+    fn table_literal_entries() {
+        let result = check(
+            r#"
+            ---@type table<string, number>
+            local t = { a = 1, b = 2 }
+            local u = t
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            ---@type table<string, number>
+            local t = { a = 1, b = "x" }
+            ---@type table<string, number>
+            local u = { 1 }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "cannot assign `\"x\"` to `number`".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(2, 24), Position::new(2, 27)),
+                },
+                Diagnostic {
+                    message: "cannot use `number` as `string` key".to_string(),
+                    kind: DiagnosticKind::TypeMismatch,
+                    span: Span::new(Position::new(4, 13), Position::new(4, 14)),
+                },
+            ]
+        );
+    }
+    #[test]
+    fn suspicious_comparison() {
+        let result = check(
+            r#"
+            ---@type number
+            local x = 1
+            ---@type number|nil
+            local y = nil
+            local same = x == 2
+            local optional = y ~= x
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            ---@type number
+            local x = 1
+            local b = x == "str"
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "`number` and `\"str\"` are never equal".to_string(),
+                kind: DiagnosticKind::SuspiciousComparison,
+                span: Span::new(Position::new(3, 11), Position::new(3, 21)),
+            }]
+        );
+    }
+    #[test]
+    fn suspicious_comparison_is_still_checked() {
+        let result = check(
+            r#"
+            ---@param s string
+            local function f(s) end
+            ---@type number
+            local n = 1
+            f(n == "s")
+            local t = { a = n == "s" }
+            f(t)
+            "#,
+        );
+        assert_eq!(
+            result
+                .diagnostics
+                .iter()
+                .map(|d| (d.kind.clone(), d.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![
+                (DiagnosticKind::SuspiciousComparison, 5),
+                (DiagnosticKind::TypeMismatch, 5),
+                (DiagnosticKind::SuspiciousComparison, 6),
+                (DiagnosticKind::TypeMismatch, 7),
+            ]
+        );
+    }
+    #[test]
+    fn comparison_with_nil_or_narrowed_local() {
+        let result = check(
+            r#"
+            ---@type number|string|boolean
+            local v = 1
+            local is_nil = v == nil
+            ---@type string?
+            local s = nil
+            local same = s == 1
+            if type(v) == "string" then
+                local one = v == 1
+            end
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+    }
+    #[test]
+    fn repeat_until_sees_body_locals() {
+        let result = check(
+            r#"
+            local function f() end
+            repeat
+                local done = f()
+            until done
+            repeat
+                local count = 1
+            until count + true
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot add `number` and `true`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(7, 15), Position::new(7, 19)),
+            }]
+        );
+    }
+    #[test]
+    fn constant_condition() {
+        let result = check(
+            r#"
+            local function f(x)
+                if x then
+                    return 1
+                end
+            end
+            "#,
+        );
+        assert_eq!(result.diagnostics, Vec::new());
+        let result = check(
+            r#"
+            if nil then
+                local a = 1
+            elseif "yes" then
+                local b = 2
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![
+                Diagnostic {
+                    message: "condition of type `nil` is always false".to_string(),
+                    kind: DiagnosticKind::ConstantCondition,
+                    span: Span::new(Position::new(1, 4), Position::new(1, 7)),
+                },
+                Diagnostic {
+                    message: "condition of type `\"yes\"` is always true".to_string(),
+                    kind: DiagnosticKind::ConstantCondition,
+                    span: Span::new(Position::new(3, 8), Position::new(3, 13)),
+                },
+            ]
+        );
+        // `while true do` is the usual endless loop, other constant loop conditions are reported
+        let result = check(
+            r#"
+            while true do
+                break
+            end
+            while false do
+                local a = 1
+            end
+            local done = false
+            while not done do
+                done = true
+            end
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "condition of type `false` is always false".to_string(),
+                kind: DiagnosticKind::ConstantCondition,
+                span: Span::new(Position::new(4, 7), Position::new(4, 12)),
+            }]
+        );
+        assert_eq!(DiagnosticKind::ConstantCondition.severity(), Severity::Hint);
+    }
+    #[test]
+    fn literal_annotation_conflict() {
+        let result = check(
+            r#"
+            ---@type string
+            local x = { 1, 2 }
+            "#,
+        );
+        assert_eq!(
+            result.diagnostics,
+            vec![Diagnostic {
+                message: "cannot annotate table literal as `string`".to_string(),
+                kind: DiagnosticKind::AssignTypeMismatch,
+                span: Span::new(Position::new(2, 11), Position::new(2, 19)),
+            }]
         );
     }
 }
@@ -0,0 +1,49 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::panic::AssertUnwindSafe;
+use std::sync::Once;
+
+use typua_span::{Position, Span};
+use typua_ty::diagnostic::Diagnostic;
+use typua_ty::message::Message;
+
+thread_local! {
+    /// the thread is in `catch_panic`, its panics are not printed
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+static QUIET_HOOK: Once = Once::new();
+
+/// `f` with a panic turned into an internal error at the start of the file
+///   parser and checker panic on syntax not supported yet, which is not printed to stderr
+///   panics of other threads are printed by the hook installed before
+pub fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, Diagnostic> {
+    QUIET_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !QUIET.with(Cell::get) {
+                previous(info);
+            }
+        }));
+    });
+    let was_quiet = QUIET.with(|quiet| quiet.replace(true));
+    let result = std::panic::catch_unwind(AssertUnwindSafe(f));
+    QUIET.with(|quiet| quiet.set(was_quiet));
+    result.map_err(|payload| {
+        Diagnostic::new(
+            Message::InternalError {
+                reason: panic_reason(payload.as_ref()),
+            },
+            Span::new(Position::new(1, 1), Position::new(1, 1)),
+        )
+    })
+}
+
+/// message given to `panic!`, `unimplemented!` gives "not implemented"
+fn panic_reason(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|reason| reason.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
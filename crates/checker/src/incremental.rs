@@ -0,0 +1,463 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use typua_binder::{Binder, TypeEnv, TypeRegistry};
+use typua_config::{Config, LuaVersion};
+use typua_parser::annotation::module_name;
+use typua_parser::ast::TypeAst;
+use typua_parser::parse;
+use typua_ty::TypeKind;
+use typua_ty::diagnostic::Diagnostic;
+
+use crate::checker::{top_level_globals, typecheck};
+use crate::guard::catch_panic;
+use crate::run::{configured_diagnostics, declare_globals, nearest_config, syntax_errors};
+
+/// parsed file kept between updates
+#[derive(Debug, Clone)]
+struct CachedFile {
+    /// None => syntax not supported yet, nothing is checked
+    ast: Option<TypeAst>,
+    /// locals bound at the top level of the file
+    type_env: TypeEnv,
+    /// globals assigned at the top level, declared in every file with `workspace.shared_globals`
+    globals: Vec<(String, TypeKind)>,
+    /// classes and aliases the file declares
+    registry: TypeRegistry,
+    /// `---@module` name other files require it by
+    module: Option<String>,
+    /// modules the file requires, the edges of the dependency graph
+    requires: Vec<String>,
+    /// identifiers in the source, comments included, to find the files using a declaration
+    names: HashSet<String>,
+    /// syntax errors, or the internal error of a parse which panicked,
+    /// the file is reported with them only
+    parse_diagnostics: Vec<Diagnostic>,
+    /// nearest config of the file when it was updated
+    config: Config,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// diagnostics of a set of files, recomputed only for what changed
+///   files are checked against the classes and aliases of every file
+///   a file is re-parsed only when its source is updated, others reuse their cached ast
+///   an update rechecks the files requiring the file and those using its changed declarations
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalChecker {
+    /// version of files whose config has no `runtime.version`
+    version: LuaVersion,
+    /// workspace root, files under it use the nearest config above them
+    root: Option<PathBuf>,
+    /// config of the root, and of files outside it
+    config: Config,
+    /// nearest config of each directory, None => the config of the root
+    configs: HashMap<PathBuf, Option<Config>>,
+    files: HashMap<PathBuf, CachedFile>,
+    /// declarations of every file
+    registry: TypeRegistry,
+    parse_count: usize,
+}
+
+impl IncrementalChecker {
+    pub fn new(version: LuaVersion) -> Self {
+        Self {
+            version,
+            ..Self::default()
+        }
+    }
+    /// files are checked with `config`, or the config of a subdirectory of `root` nearer to them
+    pub fn with_config(mut self, root: impl Into<PathBuf>, config: Config) -> Self {
+        self.root = Some(root.into());
+        self.config = config;
+        self.configs.clear();
+        self
+    }
+    /// config of the root, documents which are not files are checked with it
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    /// config `path` is checked with, a config which can't be loaded is skipped
+    pub fn config_of(&mut self, path: &Path) -> Config {
+        let nested = match (&self.root, path.parent()) {
+            (Some(root), Some(dir)) => {
+                nearest_config(dir, root, &mut self.configs).unwrap_or_default()
+            }
+            _ => None,
+        };
+        nested.unwrap_or_else(|| self.config.clone())
+    }
+    /// number of files parsed so far
+    pub fn parse_count(&self) -> usize {
+        self.parse_count
+    }
+    pub fn diagnostics(&self, path: &Path) -> &[Diagnostic] {
+        self.files
+            .get(path)
+            .map_or(&[], |file| file.diagnostics.as_slice())
+    }
+    /// replace the source of `path` and return the files whose diagnostics were recomputed,
+    /// `path` first and then its dependents
+    pub fn update_file(&mut self, path: &Path, source: &str) -> Vec<PathBuf> {
+        self.parse_count += 1;
+        // parser panics on syntax not supported yet, such a file declares nothing
+        let config = self.config_of(path);
+        let version = config.runtime.version.unwrap_or(self.version);
+        let (ast, binder, globals, parse_diagnostics) = match catch_panic(|| {
+            let (ast, errors) = parse(source, version);
+            let mut binder = Binder::new();
+            binder.bind(&ast);
+            let globals = top_level_globals(&ast, &binder);
+            (ast, binder, globals, syntax_errors(&errors))
+        }) {
+            Ok((ast, binder, globals, errors)) => (Some(ast), binder, globals, errors),
+            Err(internal) => (None, Binder::new(), Vec::new(), vec![internal]),
+        };
+        let previous = self.files.get(path);
+        let mut changed = previous
+            .map_or_else(TypeRegistry::new, |file| file.registry.clone())
+            .changed_names(&binder.registry);
+        if self.config.workspace.shared_globals {
+            let before = previous.map_or(&[][..], |file| file.globals.as_slice());
+            changed.extend(
+                before
+                    .iter()
+                    .filter(|global| !globals.contains(global))
+                    .chain(globals.iter().filter(|global| !before.contains(global)))
+                    .map(|(name, _)| name.clone()),
+            );
+        }
+        let module = module_name(source);
+        // files requiring the module by its old name are affected by a rename
+        let modules: Vec<String> = previous
+            .and_then(|file| file.module.clone())
+            .into_iter()
+            .chain(module.clone())
+            .collect();
+        self.registry.add_file(path, binder.registry.clone());
+        self.files.insert(
+            path.to_path_buf(),
+            CachedFile {
+                ast,
+                type_env: binder.type_env,
+                globals,
+                registry: binder.registry,
+                module,
+                requires: required_modules(source),
+                names: identifiers(source),
+                parse_diagnostics,
+                config,
+                diagnostics: Vec::new(),
+            },
+        );
+        let mut affected = vec![path.to_path_buf()];
+        affected.extend(self.dependents(path, &modules, &changed));
+        let globals = self.shared_globals();
+        for path in affected.iter() {
+            let diagnostics = self.check(path, &globals);
+            if let Some(file) = self.files.get_mut(path) {
+                file.diagnostics = diagnostics;
+            }
+        }
+        affected
+    }
+    /// forget `path`, return the files whose diagnostics were recomputed
+    pub fn remove_file(&mut self, path: &Path) -> Vec<PathBuf> {
+        let Some(file) = self.files.remove(path) else {
+            return Vec::new();
+        };
+        self.registry.remove_file(path);
+        let mut changed = file.registry.changed_names(&TypeRegistry::new());
+        if self.config.workspace.shared_globals {
+            changed.extend(file.globals.iter().map(|(name, _)| name.clone()));
+        }
+        let affected = self.dependents(path, file.module.as_slice(), &changed);
+        let globals = self.shared_globals();
+        for path in affected.iter() {
+            let diagnostics = self.check(path, &globals);
+            if let Some(file) = self.files.get_mut(path) {
+                file.diagnostics = diagnostics;
+            }
+        }
+        affected
+    }
+    /// files other than `path` whose diagnostics may change with it, sorted
+    ///   those requiring one of `modules`, directly or through the modules they declare
+    ///   those mentioning one of the `changed` classes or aliases
+    fn dependents(&self, path: &Path, modules: &[String], changed: &[String]) -> Vec<PathBuf> {
+        let mut affected: HashSet<&PathBuf> = self
+            .files
+            .iter()
+            .filter(|(other, file)| {
+                other.as_path() != path && changed.iter().any(|name| file.names.contains(name))
+            })
+            .map(|(other, _)| other)
+            .collect();
+        let mut pending = modules.to_vec();
+        let mut visited = HashSet::new();
+        while let Some(module) = pending.pop() {
+            if !visited.insert(module.clone()) {
+                continue;
+            }
+            for (other, file) in self.files.iter() {
+                if other.as_path() != path && file.requires.contains(&module) {
+                    let _ = affected.insert(other);
+                    pending.extend(file.module.clone());
+                }
+            }
+        }
+        let mut affected: Vec<PathBuf> = affected.into_iter().cloned().collect();
+        affected.sort();
+        affected
+    }
+    /// globals of every file with `workspace.shared_globals`, none without it
+    fn shared_globals(&self) -> TypeEnv {
+        let mut globals = TypeEnv::new();
+        if self.config.workspace.shared_globals {
+            let mut paths: Vec<&PathBuf> = self.files.keys().collect();
+            paths.sort();
+            for path in paths {
+                declare_globals(&mut globals, &self.files[path].globals);
+            }
+        }
+        globals
+    }
+    /// unknown types in annotations, then type errors in code, as `run` reports them
+    ///   `globals` are declared before the locals of the file
+    ///   a file with syntax errors is reported with them only
+    fn check(&self, path: &Path, globals: &TypeEnv) -> Vec<Diagnostic> {
+        let Some(file) = self.files.get(path) else {
+            return Vec::new();
+        };
+        let Some(ast) = file
+            .ast
+            .as_ref()
+            .filter(|_| file.parse_diagnostics.is_empty())
+        else {
+            return file.parse_diagnostics.clone();
+        };
+        let mut type_env = globals.clone();
+        for (symbol, _) in file.type_env.iter() {
+            if let Some(ty) = file.type_env.declared(symbol) {
+                let _ = type_env.insert(symbol, &ty);
+            }
+        }
+        let mut binder = Binder {
+            type_env,
+            registry: self.registry.clone(),
+        };
+        binder
+            .registry
+            .set_integer_strict(file.config.runtime.integer_strict);
+        // checker panics on expressions not supported yet
+        let result = match catch_panic(|| typecheck(ast, &binder)) {
+            Ok(result) => result,
+            Err(internal) => return vec![internal],
+        };
+        let diagnostics = self
+            .registry
+            .validate_file(path)
+            .into_iter()
+            .chain(result.diagnostics)
+            .collect();
+        configured_diagnostics(ast, &file.config, diagnostics)
+    }
+}
+
+/// names in `require("name")` and `require "name"`, comments included
+fn required_modules(source: &str) -> Vec<String> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut modules = Vec::new();
+    for (i, _) in source.match_indices("require") {
+        if source[..i].ends_with(is_ident) {
+            continue;
+        }
+        let rest = source[i + "require".len()..].trim_start();
+        let rest = rest.strip_prefix('(').unwrap_or(rest).trim_start();
+        let Some(quote) = rest.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            continue;
+        };
+        if let Some(end) = rest[1..].find(quote) {
+            modules.push(rest[1..1 + end].to_string());
+        }
+    }
+    modules
+}
+
+/// every identifier in the source, keywords and those in comments included
+fn identifiers(source: &str) -> HashSet<String> {
+    source
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use typua_ty::diagnostic::DiagnosticKind;
+    fn kinds(checker: &IncrementalChecker, path: &str) -> Vec<DiagnosticKind> {
+        checker
+            .diagnostics(Path::new(path))
+            .iter()
+            .map(|diagnostic| diagnostic.kind.clone())
+            .collect()
+    }
+    #[test]
+    fn update_only_changed_file() {
+        let mut checker = IncrementalChecker::new(LuaVersion::Lua51);
+        checker.update_file(Path::new("a.lua"), "local x = 1 + true\n");
+        checker.update_file(Path::new("b.lua"), "local y = z\n");
+        assert_eq!(checker.parse_count(), 2);
+        let affected = checker.update_file(Path::new("a.lua"), "local x = 1\n");
+        assert_eq!(affected, vec![PathBuf::from("a.lua")]);
+        assert_eq!(checker.parse_count(), 3);
+        assert_eq!(kinds(&checker, "a.lua"), Vec::new());
+        assert_eq!(
+            kinds(&checker, "b.lua"),
+            vec![DiagnosticKind::NotDeclaredVariable]
+        );
+    }
+    #[test]
+    fn recheck_requiring_files() {
+        let mut checker = IncrementalChecker::new(LuaVersion::Lua51);
+        checker.update_file(
+            Path::new("a.lua"),
+            "---@module \"a\"\nlocal M = {}\nreturn M\n",
+        );
+        checker.update_file(
+            Path::new("b.lua"),
+            "---@module \"b\"\nlocal a = require(\"a\")\nreturn a\n",
+        );
+        checker.update_file(Path::new("c.lua"), "local c = 1\n");
+        checker.update_file(Path::new("d.lua"), "local b = require 'b'\n");
+        let affected = checker.update_file(
+            Path::new("a.lua"),
+            "---@module \"a\"\nlocal M = { x = 1 }\nreturn M\n",
+        );
+        // d.lua requires a.lua through b.lua, c.lua is unrelated
+        assert_eq!(
+            affected,
+            vec![
+                PathBuf::from("a.lua"),
+                PathBuf::from("b.lua"),
+                PathBuf::from("d.lua")
+            ]
+        );
+        // a new class is used by no other file
+        let affected =
+            checker.update_file(Path::new("c.lua"), "---@class Point\nlocal Point = {}\n");
+        assert_eq!(affected, vec![PathBuf::from("c.lua")]);
+        assert_eq!(
+            required_modules("require('x.y') myrequire 'z'\nrequire \"w\""),
+            vec!["x.y", "w"]
+        );
+    }
+    #[test]
+    fn declarations_recheck_other_files() {
+        let mut checker = IncrementalChecker::new(LuaVersion::Lua51);
+        checker.update_file(
+            Path::new("user.lua"),
+            "---@param p Person\nlocal function greet(p) end\n",
+        );
+        assert_eq!(
+            kinds(&checker, "user.lua"),
+            vec![DiagnosticKind::UnknownType]
+        );
+        let mut affected = checker.update_file(
+            Path::new("person.lua"),
+            "---@class Person\nlocal Person = {}\n",
+        );
+        affected.sort();
+        assert_eq!(
+            affected,
+            vec![PathBuf::from("person.lua"), PathBuf::from("user.lua")]
+        );
+        // user.lua is rechecked with its cached ast
+        assert_eq!(checker.parse_count(), 2);
+        assert_eq!(kinds(&checker, "user.lua"), Vec::new());
+        checker.remove_file(Path::new("person.lua"));
+        assert_eq!(
+            kinds(&checker, "user.lua"),
+            vec![DiagnosticKind::UnknownType]
+        );
+    }
+    #[test]
+    fn syntax_errors_and_unsupported_syntax() {
+        let mut checker = IncrementalChecker::new(LuaVersion::Lua51);
+        // the type error is not reported while the file has syntax errors
+        checker.update_file(Path::new("a.lua"), "local x = 1 + true\nlocal = 2\n");
+        assert_eq!(
+            kinds(&checker, "a.lua"),
+            vec![DiagnosticKind::ParseError, DiagnosticKind::ParseError]
+        );
+        checker.update_file(Path::new("b.lua"), "do\nend\n");
+        assert_eq!(
+            kinds(&checker, "b.lua"),
+            vec![DiagnosticKind::InternalError]
+        );
+    }
+    #[test]
+    fn shared_globals_recheck_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.workspace.shared_globals = true;
+        // files outside the root are checked with its config
+        let mut checker =
+            IncrementalChecker::new(LuaVersion::Lua51).with_config(dir.path(), config);
+        checker.update_file(Path::new("b.lua"), "---@type number\nlocal n = count\n");
+        assert_eq!(
+            kinds(&checker, "b.lua"),
+            vec![DiagnosticKind::NotDeclaredVariable]
+        );
+        let affected = checker.update_file(Path::new("a.lua"), "count = 1\n");
+        assert_eq!(
+            affected,
+            vec![PathBuf::from("a.lua"), PathBuf::from("b.lua")]
+        );
+        assert_eq!(kinds(&checker, "b.lua"), Vec::new());
+        checker.update_file(Path::new("a.lua"), "count = \"one\"\n");
+        assert_eq!(kinds(&checker, "b.lua"), vec![DiagnosticKind::TypeMismatch]);
+        // an unchanged global rechecks nothing else
+        let affected = checker.update_file(Path::new("a.lua"), "count = \"two\"\n");
+        assert_eq!(affected, vec![PathBuf::from("a.lua")]);
+        checker.remove_file(Path::new("a.lua"));
+        assert_eq!(
+            kinds(&checker, "b.lua"),
+            vec![DiagnosticKind::NotDeclaredVariable]
+        );
+    }
+    #[test]
+    fn nearest_config_of_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("scripts");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            nested.join(typua_config::CONFIG_FILE_NAME),
+            "[runtime]\nversion = \"lua54\"\nstrict_globals = true\n",
+        )
+        .unwrap();
+        let config =
+            Config::load_from_str("[diagnostics]\ndisable = [\"undefined-global\"]\n").unwrap();
+        let mut checker =
+            IncrementalChecker::new(LuaVersion::Lua51).with_config(dir.path(), config);
+        let source = "count = 1\nlocal x = 1 // 2\nprint(y)\n";
+        let root_file = dir.path().join("main.lua");
+        let nested_file = nested.join("main.lua");
+        checker.update_file(&root_file, source);
+        checker.update_file(&nested_file, source);
+        // `//` is a syntax error of the default version
+        let mut root_kinds = kinds(&checker, root_file.to_str().unwrap());
+        root_kinds.dedup();
+        assert_eq!(root_kinds, vec![DiagnosticKind::ParseError]);
+        // the nested config replaces the one of the root, `undefined-global` is reported
+        assert_eq!(
+            kinds(&checker, nested_file.to_str().unwrap()),
+            vec![
+                DiagnosticKind::NotDeclaredVariable,
+                DiagnosticKind::ImplicitGlobal
+            ]
+        );
+    }
+}
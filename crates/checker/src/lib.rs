@@ -1,3 +1,16 @@
 mod checker;
+mod guard;
+mod incremental;
+mod narrowing;
 mod result;
-pub use checker::typecheck;
+mod run;
+mod timings;
+pub use checker::{check_implicit_globals, typecheck};
+pub use guard::catch_panic;
+pub use incremental::IncrementalChecker;
+pub use result::{CheckResult, InferredLocal, ScopeSnapshot};
+pub use run::{
+    CheckOptions, CheckReport, FileDiagnostic, configured_diagnostics, run, run_with_progress,
+    syntax_errors,
+};
+pub use timings::{FileTimings, Timings};
@@ -0,0 +1,209 @@
+use typua_binder::{Symbol, TypeEnv};
+use typua_parser::ast::{BinOp, Expression, UnOp};
+use typua_ty::kind::TypeKind;
+
+/// narrowing the environment under the assumption that `cond` is truthy
+///   x           => x is not nil
+///   x ~= nil    => x is not nil
+///   x == nil    => x is nil
+///   type(x) == "string" => x is string
+///   x == "a"    => x is "a"
+///   x ~= true   => x is not true
+///   a and b     => both a and b are truthy
+///   not a       => a is falsy
+pub fn analyze_condition(cond: &Expression, env: &TypeEnv) -> TypeEnv {
+    match cond {
+        Expression::Var { symbol, .. } => narrow(env, symbol, |ty| ty.non_nil_part()),
+        Expression::Parentheses { expr, .. } => analyze_condition(expr, env),
+        Expression::UnaryOperator {
+            unop: UnOp::Not,
+            expr,
+            ..
+        } => analyze_negated_condition(expr, env),
+        Expression::BinaryOperator { lhs, binop, rhs } => match binop {
+            BinOp::And(_) => analyze_condition(rhs, &analyze_condition(lhs, env)),
+            BinOp::Equal(_) => analyze_equality(lhs, rhs, env, true),
+            BinOp::NotEqual(_) => analyze_equality(lhs, rhs, env, false),
+            _ => env.clone(),
+        },
+        _ => env.clone(),
+    }
+}
+
+/// narrowing the environment under the assumption that `cond` is falsy
+///   x ~= nil    => x is nil
+///   x == nil    => x is not nil
+///   type(x) == "string" => x is not string
+///   x == "a"    => x is not "a"
+///   x ~= true   => x is true
+///   a or b      => both a and b are falsy
+///   not a       => a is truthy
+pub fn analyze_negated_condition(cond: &Expression, env: &TypeEnv) -> TypeEnv {
+    match cond {
+        Expression::Parentheses { expr, .. } => analyze_negated_condition(expr, env),
+        Expression::UnaryOperator {
+            unop: UnOp::Not,
+            expr,
+            ..
+        } => analyze_condition(expr, env),
+        Expression::BinaryOperator { lhs, binop, rhs } => match binop {
+            BinOp::Or(_) => analyze_negated_condition(rhs, &analyze_negated_condition(lhs, env)),
+            BinOp::Equal(_) => analyze_equality(lhs, rhs, env, false),
+            BinOp::NotEqual(_) => analyze_equality(lhs, rhs, env, true),
+            _ => env.clone(),
+        },
+        _ => env.clone(),
+    }
+}
+
+/// narrowing by `lhs == rhs` if `equal`, `lhs ~= rhs` otherwise
+///   comparisons with nil, type(x) and literals narrow, others don't
+fn analyze_equality(lhs: &Expression, rhs: &Expression, env: &TypeEnv, equal: bool) -> TypeEnv {
+    let (symbol, target) = match (nil_comparison(lhs, rhs), type_comparison(lhs, rhs)) {
+        (Some(symbol), _) => (symbol, TypeKind::Nil),
+        (None, Some((symbol, target))) => (symbol, target),
+        (None, None) => match literal_comparison(lhs, rhs) {
+            Some((symbol, literal)) => (symbol, literal),
+            None => return env.clone(),
+        },
+    };
+    if equal {
+        narrow(env, symbol, |ty| require_type(ty, &target))
+    } else {
+        narrow(env, symbol, |ty| ty.exclude(&target))
+    }
+}
+
+/// `x op nil` or `nil op x` returns x
+fn nil_comparison<'a>(lhs: &'a Expression, rhs: &'a Expression) -> Option<&'a String> {
+    match (lhs, rhs) {
+        (Expression::Var { symbol, .. }, Expression::Nil { .. })
+        | (Expression::Nil { .. }, Expression::Var { symbol, .. }) => Some(symbol),
+        _ => None,
+    }
+}
+
+/// `x == "a"` or `true == x` returns x and the literal type
+fn literal_comparison<'a>(
+    lhs: &'a Expression,
+    rhs: &'a Expression,
+) -> Option<(&'a String, TypeKind)> {
+    let (symbol, literal) = match (lhs, rhs) {
+        (Expression::Var { symbol, .. }, literal) | (literal, Expression::Var { symbol, .. }) => {
+            (symbol, literal)
+        }
+        _ => return None,
+    };
+    match literal {
+        Expression::String { value, .. } => Some((symbol, TypeKind::StringLiteral(value.clone()))),
+        Expression::Boolean { value, .. } => Some((symbol, TypeKind::BooleanLiteral(*value))),
+        _ => None,
+    }
+}
+
+/// `type(x) == "name"` or `"name" == type(x)` returns x and the type named
+fn type_comparison<'a>(lhs: &'a Expression, rhs: &'a Expression) -> Option<(&'a String, TypeKind)> {
+    let (call, name) = match (lhs, rhs) {
+        (Expression::FunctionCall(call), Expression::String { value, .. })
+        | (Expression::String { value, .. }, Expression::FunctionCall(call)) => (call, value),
+        _ => return None,
+    };
+    let symbol = match (call.callee.as_ref(), call.args.as_slice()) {
+        (Expression::Var { symbol: callee, .. }, [Expression::Var { symbol, .. }])
+            if callee == "type" =>
+        {
+            symbol
+        }
+        _ => return None,
+    };
+    let target = match name.as_str() {
+        "nil" => TypeKind::Nil,
+        "number" => TypeKind::Number,
+        "string" => TypeKind::String,
+        "boolean" => TypeKind::Boolean,
+        "table" => TypeKind::Table,
+        _ => return None,
+    };
+    Some((symbol, target))
+}
+
+/// members of `ty` which are `target`, or `target` itself if none of them
+///   number|string, number => number
+///   number[]|nil, table   => number[]
+fn require_type(ty: &TypeKind, target: &TypeKind) -> TypeKind {
+    match ty {
+        TypeKind::Union(tys) => {
+            let mut rest: Vec<TypeKind> = tys
+                .iter()
+                .filter(|ty| TypeKind::subtype(ty, target))
+                .cloned()
+                .collect();
+            match rest.len() {
+                0 => target.clone(),
+                1 => rest.remove(0),
+                _ => TypeKind::Union(rest),
+            }
+        }
+        _ => target.clone(),
+    }
+}
+
+fn narrow(env: &TypeEnv, symbol: &str, f: impl Fn(&TypeKind) -> TypeKind) -> TypeEnv {
+    let symbol = Symbol::new(symbol.to_string());
+    let mut narrowed = env.clone();
+    if let Some(ty) = env.get(&symbol) {
+        narrowed.narrow(&symbol, &f(&ty));
+    }
+    narrowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use typua_span::{Position, Span};
+    fn var(symbol: &str) -> Expression {
+        Expression::Var {
+            span: Span::new(Position::new(0, 0), Position::new(0, 0)),
+            symbol: symbol.to_string(),
+        }
+    }
+    fn nil() -> Expression {
+        Expression::Nil {
+            span: Span::new(Position::new(0, 0), Position::new(0, 0)),
+        }
+    }
+    #[test]
+    fn nil_check() {
+        let mut env = TypeEnv::new();
+        let x = Symbol::new("x".to_string());
+        let _ = env.insert(&x, &TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]));
+        // x ~= nil
+        let cond = Expression::BinaryOperator {
+            lhs: Box::new(var("x")),
+            binop: BinOp::NotEqual(Span::new(Position::new(0, 0), Position::new(0, 0))),
+            rhs: Box::new(nil()),
+        };
+        assert_eq!(
+            analyze_condition(&cond, &env).get(&x),
+            Some(TypeKind::Number)
+        );
+        // nil == x
+        let cond = Expression::BinaryOperator {
+            lhs: Box::new(nil()),
+            binop: BinOp::Equal(Span::new(Position::new(0, 0), Position::new(0, 0))),
+            rhs: Box::new(var("x")),
+        };
+        assert_eq!(analyze_condition(&cond, &env).get(&x), Some(TypeKind::Nil));
+        // x
+        assert_eq!(
+            analyze_condition(&var("x"), &env).get(&x),
+            Some(TypeKind::Number)
+        );
+        // original env is untouched
+        assert_eq!(
+            env.get(&x),
+            Some(TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]))
+        );
+    }
+}
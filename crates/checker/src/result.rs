@@ -1,24 +1,94 @@
-use typua_span::Span;
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use typua_binder::{Symbol, TypeEnv};
+use typua_span::{Position, Span};
 use typua_ty::{diagnostic::Diagnostic, kind::TypeKind};
 
 #[derive(Debug, Clone, Default)]
 pub struct CheckResult {
     pub diagnostics: Vec<Diagnostic>,
+    /// variables visible in each checked block, outer blocks first
+    pub scopes: Vec<ScopeSnapshot>,
+    /// unannotated locals typed from their values
+    pub inferred: Vec<InferredLocal>,
+}
+
+/// `local x = 1` => x is number
+///   serializable to be carried in the `data` of lsp messages and sent back by the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InferredLocal {
+    /// span of the name
+    pub span: Span,
+    pub ty: TypeKind,
+}
+
+/// environment of a block
+///   span is None for the main chunk, which covers the whole file
+///   env has every local of the block, `locals` tells from where each one is visible
+#[derive(Debug, Clone)]
+pub struct ScopeSnapshot {
+    pub span: Option<Span>,
+    pub env: TypeEnv,
+    /// locals declared directly in the block, with the position their scope starts at
+    ///   `local x = 1` from the end of the statement, `local function f` from the name
+    pub locals: Vec<(String, Position)>,
 }
 
 impl CheckResult {
     pub fn new() -> Self {
         Self {
             diagnostics: Vec::new(),
+            scopes: Vec::new(),
+            inferred: Vec::new(),
         }
     }
     pub fn merge(&self, other: &CheckResult) -> CheckResult {
         let mut new_diagnostics = self.diagnostics.clone();
         new_diagnostics.extend(other.diagnostics.clone());
+        let mut new_scopes = self.scopes.clone();
+        new_scopes.extend(other.scopes.clone());
+        let mut new_inferred = self.inferred.clone();
+        new_inferred.extend(other.inferred.clone());
         CheckResult {
             diagnostics: new_diagnostics,
+            scopes: new_scopes,
+            inferred: new_inferred,
         }
     }
+    /// environment of the innermost block containing `position`, without the locals of
+    /// the enclosing blocks declared after it
+    ///   inner blocks are recorded after the outer ones
+    ///   a local declared before is kept though another of the same name is declared after
+    pub fn scope_at(&self, position: &Position) -> Option<TypeEnv> {
+        let contains = |scope: &&ScopeSnapshot| {
+            scope
+                .span
+                .as_ref()
+                .is_none_or(|span| span.contains(position))
+        };
+        let mut env = self.scopes.iter().rev().find(contains)?.env.clone();
+        let mut before = HashSet::new();
+        let mut after = HashSet::new();
+        for (name, from) in self.scopes.iter().filter(contains).flat_map(|s| &s.locals) {
+            if from <= position {
+                before.insert(name);
+            } else {
+                after.insert(name);
+            }
+        }
+        for name in after.difference(&before) {
+            env.remove(&Symbol::new(name.to_string()));
+        }
+        Some(env)
+    }
+    /// inferred local whose name is at the 1-based `line` and `character`
+    pub fn type_at(&self, line: u32, character: u32) -> Option<&InferredLocal> {
+        let position = Position::new(line, character);
+        self.inferred
+            .iter()
+            .find(|local| local.span.contains(&position))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,3 +102,42 @@ pub struct EvalErr {
     pub span: Span,
     pub diagnostic: Diagnostic,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    #[test]
+    fn type_at_inferred_local() {
+        let mut result = CheckResult::new();
+        result.inferred.push(InferredLocal {
+            span: Span::new(Position::new(2, 7), Position::new(2, 11)),
+            ty: TypeKind::Number,
+        });
+        assert_eq!(
+            result.type_at(2, 9).map(|local| &local.ty),
+            Some(&TypeKind::Number)
+        );
+        assert_eq!(result.type_at(2, 12), None);
+        assert_eq!(result.type_at(1, 9), None);
+    }
+    #[test]
+    fn inferred_local_round_trip() {
+        let local = InferredLocal {
+            span: Span::new(Position::new(1, 7), Position::new(1, 12)),
+            ty: TypeKind::Function {
+                generics: Vec::new(),
+                param_names: Vec::new(),
+                params: vec![TypeKind::Union(vec![TypeKind::Integer, TypeKind::Nil])],
+                returns: vec![TypeKind::StringLiteral("a".to_string())],
+                return_names: Vec::new(),
+                vararg: Some(Box::new(TypeKind::Any)),
+            },
+        };
+        let json = serde_json::to_value(&local).unwrap();
+        assert_eq!(
+            serde_json::from_value::<InferredLocal>(json).unwrap(),
+            local
+        );
+    }
+}
@@ -0,0 +1,691 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use typua_binder::{Binder, Symbol, TypeEnv};
+use typua_config::{CONFIG_FILE_NAME, Config, LUARC_FILE_NAME, LuaVersion};
+use typua_parser::ast::TypeAst;
+use typua_parser::parse;
+use typua_ty::TypeKind;
+use typua_ty::diagnostic::Diagnostic;
+use typua_ty::error::{ParseError, TypuaError};
+use typua_ty::message::Message;
+use typua_vfs::collect_source_files;
+
+use crate::checker::{check_implicit_globals, top_level_globals, typecheck};
+use crate::guard::catch_panic;
+use crate::timings::{FileTimings, Timings};
+
+/// options for checking a workspace, built without cli
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// file or directory to check
+    pub target: PathBuf,
+    pub config: Config,
+    pub version: LuaVersion,
+    /// the version was given on the command line, nested configs don't override it
+    pub explicit_version: bool,
+}
+
+impl CheckOptions {
+    pub fn new(target: impl Into<PathBuf>, config: Config) -> Self {
+        Self {
+            target: target.into(),
+            config,
+            version: LuaVersion::default(),
+            explicit_version: false,
+        }
+    }
+    /// version of files whose nearest config has no `runtime.version`
+    pub fn with_version(mut self, version: LuaVersion) -> Self {
+        self.version = version;
+        self
+    }
+    /// version of every file, whatever their configs say
+    pub fn with_explicit_version(mut self, version: LuaVersion) -> Self {
+        self.version = version;
+        self.explicit_version = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    pub files_checked: usize,
+    pub diagnostics: Vec<FileDiagnostic>,
+    pub timings: Timings,
+}
+
+impl CheckReport {
+    /// reports of separately checked files combined into one
+    pub fn merge(&self, other: &CheckReport) -> CheckReport {
+        let mut diagnostics = self.diagnostics.clone();
+        diagnostics.extend(other.diagnostics.clone());
+        CheckReport {
+            files_checked: self.files_checked + other.files_checked,
+            diagnostics,
+            timings: self.timings.merge(&other.timings),
+        }
+    }
+    /// report with diagnostics ordered by path, then by position in the file
+    pub fn sorted(&self) -> CheckReport {
+        let mut sorted = self.clone();
+        sorted.diagnostics.sort_by(|a, b| {
+            a.path
+                .cmp(&b.path)
+                .then_with(|| a.diagnostic.cmp_position(&b.diagnostic))
+        });
+        sorted
+    }
+    /// diagnostics of each file in reported order, files without diagnostics are absent
+    pub fn by_file(&self) -> HashMap<PathBuf, Vec<&Diagnostic>> {
+        let mut grouped: HashMap<PathBuf, Vec<&Diagnostic>> = HashMap::new();
+        for file_diagnostic in self.diagnostics.iter() {
+            grouped
+                .entry(file_diagnostic.path.clone())
+                .or_default()
+                .push(&file_diagnostic.diagnostic);
+        }
+        grouped
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiagnostic {
+    pub path: PathBuf,
+    pub diagnostic: Diagnostic,
+}
+
+/// entry point for checking files under `options.target`
+pub fn run(options: &CheckOptions) -> Result<CheckReport, TypuaError> {
+    run_with_progress(options, |_, _| ())
+}
+
+/// `run` calling `progress(current, total)` before checking each file, `current` is 1-based
+pub fn run_with_progress(
+    options: &CheckOptions,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<CheckReport, TypuaError> {
+    std::fs::metadata(&options.target).map_err(|source| TypuaError::SourceRead {
+        path: options.target.display().to_string(),
+        source,
+    })?;
+    let mut report = CheckReport::default();
+    let start = Instant::now();
+    let paths = collect_source_files(&options.target, &options.config.workspace);
+    report.timings.collect = start.elapsed();
+    let total = paths.len();
+    // nearest config of each directory, None => the config of options
+    let mut configs = HashMap::new();
+    let files = paths
+        .into_iter()
+        .map(|path| {
+            let nested = match path.parent() {
+                Some(dir) => nearest_config(dir, &options.target, &mut configs)?,
+                None => None,
+            };
+            let version = match &nested {
+                Some(config) if !options.explicit_version => {
+                    config.runtime.version.unwrap_or(options.version)
+                }
+                _ => options.version,
+            };
+            Ok((path, nested, version))
+        })
+        .collect::<Result<Vec<_>, TypuaError>>()?;
+    let globals = if options.config.workspace.shared_globals {
+        shared_globals(
+            files
+                .iter()
+                .map(|(path, _, version)| (path.as_path(), *version)),
+        )
+    } else {
+        TypeEnv::new()
+    };
+    for (i, (path, nested, version)) in files.into_iter().enumerate() {
+        progress(i + 1, total);
+        let content = std::fs::read_to_string(&path).map_err(|source| TypuaError::SourceRead {
+            path: path.display().to_string(),
+            source,
+        })?;
+        let config = nested.as_ref().unwrap_or(&options.config);
+        // parser and checker panic on syntax not supported yet, the file is reported and skipped
+        let diagnostics = match catch_panic(|| check_source(&content, config, version, &globals)) {
+            Ok((diagnostics, parse, bind, check)) => {
+                report.timings.files.push(FileTimings {
+                    path: path.clone(),
+                    parse,
+                    bind,
+                    check,
+                });
+                diagnostics
+            }
+            Err(internal) => vec![internal],
+        };
+        report.files_checked += 1;
+        let tab_width = config.runtime.tab_width;
+        report
+            .diagnostics
+            .extend(diagnostics.into_iter().map(|diagnostic| FileDiagnostic {
+                path: path.clone(),
+                diagnostic: Diagnostic {
+                    span: diagnostic.span.expand_tabs(&content, tab_width),
+                    ..diagnostic
+                },
+            }));
+    }
+    Ok(report)
+}
+
+/// config in `dir` or the nearest directory above it, up to `root` which is excluded
+///   the config of `root` is the one of the options, subdirectories may override it
+pub(crate) fn nearest_config(
+    dir: &Path,
+    root: &Path,
+    configs: &mut HashMap<PathBuf, Option<Config>>,
+) -> Result<Option<Config>, TypuaError> {
+    if dir == root || !dir.starts_with(root) {
+        return Ok(None);
+    }
+    if let Some(config) = configs.get(dir) {
+        return Ok(config.clone());
+    }
+    let config = if dir.join(CONFIG_FILE_NAME).is_file() || dir.join(LUARC_FILE_NAME).is_file() {
+        Some(Config::load_from_dir(dir)?)
+    } else {
+        match dir.parent() {
+            Some(parent) => nearest_config(parent, root, configs)?,
+            None => None,
+        }
+    };
+    configs.insert(dir.to_path_buf(), config.clone());
+    Ok(config)
+}
+
+/// globals assigned at the top level of any of the files, each parsed with its version
+///   unreadable files and files with syntax not supported yet declare nothing
+fn shared_globals<'a>(files: impl Iterator<Item = (&'a Path, LuaVersion)>) -> TypeEnv {
+    let mut globals = TypeEnv::new();
+    for (path, version) in files {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let Ok(declared) = catch_panic(|| {
+            let (ast, _errors) = parse(&content, version);
+            let mut binder = Binder::new();
+            binder.bind(&ast);
+            top_level_globals(&ast, &binder)
+        }) else {
+            continue;
+        };
+        declare_globals(&mut globals, &declared);
+    }
+    globals
+}
+
+/// `declared` added to `globals`, a name assigned in several files has the union of the types
+pub(crate) fn declare_globals(globals: &mut TypeEnv, declared: &[(String, TypeKind)]) {
+    for (name, ty) in declared.iter() {
+        let symbol = Symbol::new(name.clone());
+        let ty = match globals.get(&symbol) {
+            Some(declared) => declared.union(ty),
+            None => ty.clone(),
+        };
+        let _ = globals.insert(&symbol, &ty);
+    }
+}
+
+/// syntax errors among the errors of `parse`, as diagnostics at their ranges
+pub fn syntax_errors(errors: &[TypuaError]) -> Vec<Diagnostic> {
+    errors
+        .iter()
+        .filter_map(|error| match error {
+            TypuaError::Parse(ParseError::SyntaxError { message, span }) => Some(Diagnostic::new(
+                Message::SyntaxError {
+                    message: message.clone(),
+                },
+                span.clone(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// unknown types in annotations, then type errors in code, with the time of each phase
+///   `globals` are declared before the locals of the file
+///   a file with syntax errors is reported with them only
+fn check_source(
+    content: &str,
+    config: &Config,
+    version: LuaVersion,
+    globals: &TypeEnv,
+) -> (Vec<Diagnostic>, Duration, Duration, Duration) {
+    let start = Instant::now();
+    let (ast, errors) = parse(content, version);
+    let parsed = Instant::now();
+    if !errors.is_empty() {
+        return (
+            syntax_errors(&errors),
+            parsed - start,
+            Duration::ZERO,
+            Duration::ZERO,
+        );
+    }
+    let mut binder = Binder::new();
+    binder.type_env = globals.clone();
+    binder
+        .registry
+        .set_integer_strict(config.runtime.integer_strict);
+    binder.bind(&ast);
+    let registry_diagnostics = binder.registry.validate();
+    let bound = Instant::now();
+    let result = typecheck(&ast, &binder);
+    let diagnostics = configured_diagnostics(
+        &ast,
+        config,
+        registry_diagnostics
+            .into_iter()
+            .chain(result.diagnostics)
+            .collect(),
+    );
+    (diagnostics, parsed - start, bound - parsed, bound.elapsed())
+}
+
+/// diagnostics of a checked file as `config` asks for them
+///   `runtime.strict_globals` adds implicit globals, kinds named in `diagnostics.disable` are dropped
+pub fn configured_diagnostics(
+    ast: &TypeAst,
+    config: &Config,
+    mut diagnostics: Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    if config.runtime.strict_globals {
+        diagnostics.extend(check_implicit_globals(ast));
+    }
+    let disabled = &config.diagnostics.disable;
+    diagnostics.retain(|d| !disabled.iter().any(|code| code == d.kind.code()));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use typua_span::{Position, Span};
+    use typua_ty::diagnostic::DiagnosticKind;
+    #[test]
+    fn run_with_manual_options() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ok.lua"), "local x = 1\n").unwrap();
+        std::fs::write(dir.path().join("ng.lua"), "local x = 1 + true\n").unwrap();
+        let options = CheckOptions::new(dir.path(), Config::default());
+        let report = run(&options).unwrap();
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.timings.files.len(), 2);
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].path, dir.path().join("ng.lua"));
+        assert_eq!(
+            report.diagnostics[0].diagnostic.kind,
+            DiagnosticKind::TypeMismatch
+        );
+    }
+    #[test]
+    fn missing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("missing.lua");
+        let options = CheckOptions::new(&target, Config::default());
+        let err = run(&options).unwrap_err();
+        assert_eq!(err.exit_code(), 66);
+        assert!(
+            err.to_string()
+                .starts_with(&format!("failed to read {}: ", target.display()))
+        );
+        assert!(
+            err.to_string()
+                .ends_with("hint: check that the path exists")
+        );
+    }
+    #[test]
+    fn progress_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.lua", "b.lua", "c.lua"] {
+            std::fs::write(dir.path().join(name), "local x = 1\n").unwrap();
+        }
+        let mut calls = Vec::new();
+        let report =
+            run_with_progress(&CheckOptions::new(dir.path(), Config::default()), |i, n| {
+                calls.push((i, n))
+            })
+            .unwrap();
+        assert_eq!(report.files_checked, 3);
+        assert_eq!(calls, vec![(1, 3), (2, 3), (3, 3)]);
+    }
+    #[test]
+    fn group_by_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.lua"),
+            "local x = 1 + true\nlocal y = z\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("b.lua"), "local x = w\n").unwrap();
+        std::fs::write(dir.path().join("ok.lua"), "local x = 1\n").unwrap();
+        let report = run(&CheckOptions::new(dir.path(), Config::default())).unwrap();
+        let grouped = report.by_file();
+        assert_eq!(grouped.len(), 2);
+        let kinds = |name: &str| {
+            grouped[&dir.path().join(name)]
+                .iter()
+                .map(|diagnostic| diagnostic.kind.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds("a.lua"),
+            vec![
+                DiagnosticKind::TypeMismatch,
+                DiagnosticKind::NotDeclaredVariable
+            ]
+        );
+        assert_eq!(kinds("b.lua"), vec![DiagnosticKind::NotDeclaredVariable]);
+    }
+    #[test]
+    fn merge_reports() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::write(first.path().join("ng.lua"), "local x = 1 + true\n").unwrap();
+        std::fs::write(first.path().join("ok.lua"), "local x = 1\n").unwrap();
+        std::fs::write(second.path().join("ng.lua"), "local x = y\n").unwrap();
+        let first_report = run(&CheckOptions::new(first.path(), Config::default())).unwrap();
+        let second_report = run(&CheckOptions::new(second.path(), Config::default())).unwrap();
+        let merged = first_report.merge(&second_report);
+        assert_eq!(merged.files_checked, 3);
+        assert_eq!(
+            merged
+                .diagnostics
+                .iter()
+                .map(|d| d.path.clone())
+                .collect::<Vec<_>>(),
+            vec![first.path().join("ng.lua"), second.path().join("ng.lua")]
+        );
+    }
+    #[test]
+    fn sorted_report() {
+        let dir = tempfile::tempdir().unwrap();
+        // the unknown type is validated before the code is checked
+        std::fs::write(
+            dir.path().join("b.lua"),
+            "local x = y\n---@param a Unknown\nlocal function f(a) end\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("a.lua"), "local x = 1 + true\n").unwrap();
+        let report = run(&CheckOptions::new(dir.path(), Config::default()))
+            .unwrap()
+            .sorted();
+        let positions: Vec<(PathBuf, u32, DiagnosticKind)> = report
+            .diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.path.clone(),
+                    d.diagnostic.span.start.line(),
+                    d.diagnostic.kind.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (dir.path().join("a.lua"), 1, DiagnosticKind::TypeMismatch),
+                (
+                    dir.path().join("b.lua"),
+                    1,
+                    DiagnosticKind::NotDeclaredVariable
+                ),
+                (dir.path().join("b.lua"), 2, DiagnosticKind::UnknownType),
+            ]
+        );
+    }
+    #[test]
+    fn tab_width() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("tab.lua"),
+            "local function f()\n\tlocal x = 1 + true\nend\n",
+        )
+        .unwrap();
+        let columns = |tab_width: u32| {
+            let mut config = Config::default();
+            config.runtime.tab_width = tab_width;
+            let report = run(&CheckOptions::new(dir.path(), config)).unwrap();
+            let span = &report.diagnostics[0].diagnostic.span;
+            (span.start.character(), span.end.character())
+        };
+        // `true` is at 16..20 when a tab is one column
+        assert_eq!(columns(1), (16, 20));
+        assert_eq!(columns(4), (19, 23));
+        assert_eq!(columns(8), (23, 27));
+    }
+    #[test]
+    fn strict_globals() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.lua"),
+            "local x = 1\nx = 2\nundeclared = 1\nprint = nil\n",
+        )
+        .unwrap();
+        let kinds = |strict_globals: bool| {
+            let mut config = Config::default();
+            config.runtime.strict_globals = strict_globals;
+            run(&CheckOptions::new(dir.path(), config))
+                .unwrap()
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.diagnostic.span.start.line(), d.diagnostic.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(false), Vec::new());
+        assert_eq!(kinds(true), vec![(3, DiagnosticKind::ImplicitGlobal)]);
+    }
+    #[test]
+    fn integer_strict() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.lua"),
+            "---@class Size\n---@field width integer\n\n---@type Size\nlocal size = { width = 1 }\nsize.width = 1.5\n",
+        )
+        .unwrap();
+        let kinds = |integer_strict: bool| {
+            let mut config = Config::default();
+            config.runtime.integer_strict = integer_strict;
+            run(&CheckOptions::new(dir.path(), config))
+                .unwrap()
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.diagnostic.span.start.line(), d.diagnostic.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds(true), vec![(6, DiagnosticKind::TypeMismatch)]);
+        assert_eq!(kinds(false), Vec::new());
+    }
+    #[test]
+    fn shared_globals() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.lua"),
+            "count = 1\nfunction greet() end\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("b.lua"),
+            "---@type number\nlocal n = count\nlocal f = greet\n",
+        )
+        .unwrap();
+        let kinds = |shared_globals: bool| {
+            let mut config = Config::default();
+            config.workspace.shared_globals = shared_globals;
+            run(&CheckOptions::new(dir.path(), config))
+                .unwrap()
+                .sorted()
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.diagnostic.span.start.line(), d.diagnostic.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(false),
+            vec![
+                (2, DiagnosticKind::NotDeclaredVariable),
+                (3, DiagnosticKind::NotDeclaredVariable),
+            ]
+        );
+        assert_eq!(kinds(true), Vec::new());
+        // globals are read with the version of the file declaring them, `~` is lua 5.3 and later
+        let nested = dir.path().join("new");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            nested.join(CONFIG_FILE_NAME),
+            "[runtime]\nversion = \"lua54\"\n",
+        )
+        .unwrap();
+        std::fs::write(nested.join("c.lua"), "half = ~1\n").unwrap();
+        std::fs::write(
+            dir.path().join("d.lua"),
+            "---@type string\nlocal s = half\n",
+        )
+        .unwrap();
+        assert_eq!(kinds(true), vec![(2, DiagnosticKind::TypeMismatch)]);
+    }
+    #[test]
+    fn disabled_diagnostics() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.lua"),
+            "local table = {}\nlocal x = 1 + true\n",
+        )
+        .unwrap();
+        let kinds = |disable: &[&str]| {
+            let mut config = Config::default();
+            config.diagnostics.disable = disable.iter().map(|code| code.to_string()).collect();
+            run(&CheckOptions::new(dir.path(), config))
+                .unwrap()
+                .diagnostics
+                .into_iter()
+                .map(|d| d.diagnostic.kind)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(&[]),
+            vec![
+                DiagnosticKind::ShadowedBuiltin,
+                DiagnosticKind::TypeMismatch
+            ]
+        );
+        assert_eq!(
+            kinds(&["shadowed-builtin"]),
+            vec![DiagnosticKind::TypeMismatch]
+        );
+    }
+    #[test]
+    fn unsupported_syntax_skips_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.lua"), "do\n    local x = 1\nend\n").unwrap();
+        std::fs::write(dir.path().join("b.lua"), "local y = 1 + true\n").unwrap();
+        let report = run(&CheckOptions::new(dir.path(), Config::default())).unwrap();
+        assert_eq!(report.files_checked, 2);
+        let kinds: Vec<(PathBuf, DiagnosticKind)> = report
+            .sorted()
+            .diagnostics
+            .into_iter()
+            .map(|d| (d.path, d.diagnostic.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (dir.path().join("a.lua"), DiagnosticKind::InternalError),
+                (dir.path().join("b.lua"), DiagnosticKind::TypeMismatch),
+            ]
+        );
+    }
+    #[test]
+    fn syntax_error() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.lua"),
+            "local x = 1 +
+local y = 1 + true
+",
+        )
+        .unwrap();
+        let report = run(&CheckOptions::new(dir.path(), Config::default())).unwrap();
+        assert_eq!(report.files_checked, 1);
+        assert_eq!(
+            report
+                .diagnostics
+                .into_iter()
+                .map(|d| d.diagnostic)
+                .collect::<Vec<_>>(),
+            vec![Diagnostic {
+                message: "syntax error: expected expression after binary operator".to_string(),
+                kind: DiagnosticKind::ParseError,
+                span: Span::new(Position::new(1, 13), Position::new(1, 14)),
+            }]
+        );
+    }
+    #[test]
+    fn nearest_config_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        for (sub, version) in [("old", "lua51"), ("new", "lua54")] {
+            let sub = dir.path().join(sub).join("src");
+            std::fs::create_dir_all(&sub).unwrap();
+            std::fs::write(
+                sub.parent().unwrap().join(CONFIG_FILE_NAME),
+                format!("[runtime]\nversion = \"{version}\"\n"),
+            )
+            .unwrap();
+            // floor division is a syntax error before lua 5.3
+            std::fs::write(sub.join("div.lua"), "local x = 1 // true\n").unwrap();
+        }
+        std::fs::write(dir.path().join("root.lua"), "local x = 1 // true\n").unwrap();
+        let kinds = |options: CheckOptions| {
+            let mut kinds: Vec<(PathBuf, DiagnosticKind)> = run(&options)
+                .unwrap()
+                .sorted()
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.path, d.diagnostic.kind))
+                .collect();
+            // `//` is more than one syntax error
+            kinds.dedup();
+            kinds
+        };
+        let options = CheckOptions::new(dir.path(), Config::default());
+        assert_eq!(
+            kinds(options.clone().with_version(LuaVersion::Lua53)),
+            vec![
+                (
+                    dir.path().join("new").join("src").join("div.lua"),
+                    DiagnosticKind::TypeMismatch
+                ),
+                (
+                    dir.path().join("old").join("src").join("div.lua"),
+                    DiagnosticKind::ParseError
+                ),
+                (dir.path().join("root.lua"), DiagnosticKind::TypeMismatch),
+            ]
+        );
+        // the version given on the command line wins over every config
+        assert_eq!(
+            kinds(options.with_explicit_version(LuaVersion::Lua51)),
+            vec![
+                (
+                    dir.path().join("new").join("src").join("div.lua"),
+                    DiagnosticKind::ParseError
+                ),
+                (
+                    dir.path().join("old").join("src").join("div.lua"),
+                    DiagnosticKind::ParseError
+                ),
+                (dir.path().join("root.lua"), DiagnosticKind::ParseError),
+            ]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// wall-clock time spent in each phase of a check run
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    /// walking the target for source files
+    pub collect: Duration,
+    pub files: Vec<FileTimings>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileTimings {
+    pub path: PathBuf,
+    pub parse: Duration,
+    /// binding locals and indexing annotated types
+    pub bind: Duration,
+    pub check: Duration,
+}
+
+impl FileTimings {
+    pub fn total(&self) -> Duration {
+        self.parse + self.bind + self.check
+    }
+}
+
+impl Timings {
+    /// timings of separately checked files combined into one
+    pub fn merge(&self, other: &Timings) -> Timings {
+        let mut files = self.files.clone();
+        files.extend(other.files.clone());
+        Timings {
+            collect: self.collect + other.collect,
+            files,
+        }
+    }
+    /// phases summed over all files, then the `slowest` files which took longest
+    pub fn render(&self, slowest: usize) -> String {
+        let sum = |phase: fn(&FileTimings) -> Duration| self.files.iter().map(phase).sum();
+        let phases: [(&str, Duration); 4] = [
+            ("collect", self.collect),
+            ("parse", sum(|file| file.parse)),
+            ("bind", sum(|file| file.bind)),
+            ("check", sum(|file| file.check)),
+        ];
+        let total: Duration = phases.iter().map(|(_, time)| *time).sum();
+        let mut out = String::from("timings:\n");
+        for (label, time) in phases.iter().chain([("total", total)].iter()) {
+            let _ = writeln!(out, "  {:<8}{:>12.2?}", label, time);
+        }
+        let mut files: Vec<&FileTimings> = self.files.iter().collect();
+        files.sort_by_key(|file| std::cmp::Reverse(file.total()));
+        let _ = writeln!(out, "slowest files:");
+        for file in files.into_iter().take(slowest) {
+            let _ = writeln!(
+                out,
+                "  {:>12.2?}  {} (parse {:.2?}, bind {:.2?}, check {:.2?})",
+                file.total(),
+                file.path.display(),
+                file.parse,
+                file.bind,
+                file.check
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    fn file(path: &str, parse: u64, bind: u64, check: u64) -> FileTimings {
+        FileTimings {
+            path: PathBuf::from(path),
+            parse: Duration::from_millis(parse),
+            bind: Duration::from_millis(bind),
+            check: Duration::from_millis(check),
+        }
+    }
+    #[test]
+    fn render_phases_and_slowest_files() {
+        let timings = Timings {
+            collect: Duration::from_millis(1),
+            files: vec![
+                file("fast.lua", 1, 1, 1),
+                file("slow.lua", 5, 2, 10),
+                file("middle.lua", 2, 2, 2),
+            ],
+        };
+        assert_eq!(
+            timings.render(2),
+            [
+                "timings:",
+                "  collect       1.00ms",
+                "  parse         8.00ms",
+                "  bind          5.00ms",
+                "  check        13.00ms",
+                "  total        27.00ms",
+                "slowest files:",
+                "       17.00ms  slow.lua (parse 5.00ms, bind 2.00ms, check 10.00ms)",
+                "        6.00ms  middle.lua (parse 2.00ms, bind 2.00ms, check 2.00ms)",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 use typua_config::LuaVersion;
 
@@ -13,6 +13,9 @@ pub struct Args {
 pub enum Commands {
     Serve(ServeCommand),
     Check(CheckCommand),
+    /// print internal representations, for debugging typua itself
+    #[command(subcommand)]
+    Debug(DebugCommand),
 }
 
 #[derive(Debug, Parser)]
@@ -22,4 +25,50 @@ pub struct ServeCommand {}
 pub struct CheckCommand {
     pub path: Option<PathBuf>,
     pub version: Option<LuaVersion>,
+    /// coloring diagnostics, auto colors only when stdout is a terminal
+    #[arg(long, value_enum, default_value_t = ColorMode::Auto)]
+    pub color: ColorMode,
+    /// show at most N diagnostics, unlimited by default
+    #[arg(long, value_name = "N")]
+    pub max_diagnostics: Option<usize>,
+    /// print time spent in each phase and the N slowest files, 5 if N is omitted
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "5")]
+    pub timings: Option<usize>,
+    /// insert stub annotations for undocumented functions and locals before checking
+    #[arg(long)]
+    pub fix: bool,
+    /// let --fix edit files which are not committed in git
+    #[arg(long, requires = "fix")]
+    pub allow_dirty: bool,
+    /// report only diagnostics on lines changed since HEAD, or since --since
+    #[arg(long)]
+    pub diff: bool,
+    /// git ref the changed lines of --diff are compared to, implies --diff
+    #[arg(long, value_name = "REF")]
+    pub since: Option<String>,
+    /// don't show progress while checking, it is shown only when stderr is a terminal
+    #[arg(long, short)]
+    pub quiet: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DebugCommand {
+    /// print the typed ast of a file
+    Ast(AstCommand),
+}
+
+#[derive(Debug, Parser)]
+pub struct AstCommand {
+    pub file: PathBuf,
+    pub version: Option<LuaVersion>,
+    /// print the classes and aliases declared in the file too
+    #[arg(long)]
+    pub registry: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
 }
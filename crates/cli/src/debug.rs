@@ -0,0 +1,51 @@
+use typua_binder::Binder;
+use typua_checker::catch_panic;
+use typua_config::LuaVersion;
+use typua_parser::parse;
+use typua_ty::{ParseError, TypuaError};
+
+/// typed ast of `content` in `{:#?}` form, followed by the registry if `registry`
+///   syntax errors are listed first, the ast is of the statements parsed anyway
+///   Err => parser or binder panicked on syntax not supported yet
+pub fn debug_ast(content: &str, version: LuaVersion, registry: bool) -> Result<String, TypuaError> {
+    catch_panic(|| {
+        let (ast, errors) = parse(content, version);
+        let mut out = String::new();
+        for error in errors.iter() {
+            out.push_str(&format!("error: {error}\n"));
+        }
+        out.push_str(&format!("{ast:#?}\n"));
+        if registry {
+            let mut binder = Binder::new();
+            binder.bind(&ast);
+            out.push_str(&format!("{:#?}\n", binder.registry));
+        }
+        out
+    })
+    .map_err(|internal| TypuaError::Parse(ParseError::Internal(internal.message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn print_ast() {
+        let content = "---@class Point\n---@field x number\nlocal p = {}\nreturn p\n";
+        let out = debug_ast(content, LuaVersion::Lua54, false).unwrap();
+        for name in ["TypeAst", "Block", "LocalAssign", "Return", "Class"] {
+            assert!(out.contains(name), "{name} is not in {out}");
+        }
+        assert!(!out.contains("TypeRegistry"));
+        let out = debug_ast(content, LuaVersion::Lua54, true).unwrap();
+        assert!(out.contains("TypeRegistry"), "{out}");
+    }
+    #[test]
+    fn unsupported_syntax_is_an_error() {
+        let err = debug_ast("do\nend\n", LuaVersion::Lua54, false).unwrap_err();
+        assert_eq!(err.exit_code(), 65);
+        assert_eq!(
+            err.to_string(),
+            "parse error: internal error: not implemented, the file is not checked"
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use typua_checker::CheckReport;
+
+/// lines added or modified since a git ref, as 1-based inclusive ranges for each file
+pub type ChangedLines = HashMap<PathBuf, Vec<(u32, u32)>>;
+
+/// lines changed in the working tree of the repository containing `target` since `base`
+///   uncommitted changes are included, files not added to git are not
+pub fn changed_lines(target: &Path, base: &str) -> anyhow::Result<ChangedLines> {
+    let dir = if target.is_dir() {
+        target
+    } else {
+        target.parent().unwrap_or(Path::new("."))
+    };
+    let git = |args: &[&str]| -> anyhow::Result<String> {
+        // non-ASCII paths are printed as they are, not as octal escapes
+        let out = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "core.quotePath=false"])
+            .args(args)
+            .output()?;
+        if !out.status.success() {
+            anyhow::bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    };
+    let root = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+    let diff = git(&[
+        "diff",
+        "--unified=0",
+        "--no-color",
+        "--no-ext-diff",
+        // diff.noprefix or diff.mnemonicPrefix in the user's config change them
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+        base,
+        "--",
+    ])?;
+    let root = root.canonicalize().unwrap_or(root);
+    Ok(parse_diff(&diff, &root))
+}
+
+/// added lines of each file in the output of `git diff --unified=0`, paths joined to `root`
+///   @@ -10,2 +12,3 @@ => lines 12 to 14 of the new file, a count of 0 is a pure deletion
+///   `+++ ` is the header only before the first hunk of a file, after it the line is added text
+///   paths with special characters are quoted, `+++ "b/a\"b.lua"`, and those with spaces end in a tab
+pub fn parse_diff(diff: &str, root: &Path) -> ChangedLines {
+    let mut changed = ChangedLines::new();
+    let mut file = None;
+    let mut in_header = true;
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_header = true;
+            file = None;
+            continue;
+        }
+        if in_header && let Some(path) = line.strip_prefix("+++ ") {
+            // deleted files are `+++ /dev/null`
+            let path = unquote(path.trim_end_matches('\t'));
+            file = path.strip_prefix("b/").map(|path| root.join(path));
+            continue;
+        }
+        if line.starts_with("@@ ") {
+            in_header = false;
+        }
+        let Some(file) = &file else {
+            continue;
+        };
+        let Some(hunk) = line.strip_prefix("@@ ") else {
+            continue;
+        };
+        let Some(added) = hunk.split(' ').find_map(|part| part.strip_prefix('+')) else {
+            continue;
+        };
+        let (start, count) = match added.split_once(',') {
+            Some((start, count)) => (start.parse::<u32>(), count.parse::<u32>()),
+            None => (added.parse::<u32>(), Ok(1)),
+        };
+        if let (Ok(start), Ok(count)) = (start, count)
+            && count > 0
+        {
+            changed
+                .entry(file.clone())
+                .or_default()
+                .push((start, start + count - 1));
+        }
+    }
+    changed
+}
+
+/// path as git prints it, a quoted one has C escapes and octal escaped bytes
+fn unquote(path: &str) -> String {
+    let Some(quoted) = path
+        .strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+    else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            bytes.extend(c.to_string().bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('a') => bytes.push(0x07),
+            Some('b') => bytes.push(0x08),
+            Some('f') => bytes.push(0x0c),
+            Some('v') => bytes.push(0x0b),
+            Some(digit @ '0'..='7') => {
+                let octal: String = std::iter::once(digit)
+                    .chain(chars.clone().take(2))
+                    .collect();
+                chars.nth(1);
+                bytes.push(u8::from_str_radix(&octal, 8).unwrap_or(b'?'));
+            }
+            // `\\` and `\"`
+            Some(c) => bytes.extend(c.to_string().bytes()),
+            None => (),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// report with only the diagnostics starting on a changed line
+pub fn filter_changed(report: &CheckReport, changed: &ChangedLines) -> CheckReport {
+    let mut filtered = report.clone();
+    filtered.diagnostics.retain(|file_diagnostic| {
+        let path = file_diagnostic
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| file_diagnostic.path.clone());
+        let line = file_diagnostic.diagnostic.span.start.line();
+        changed.get(&path).is_some_and(|ranges| {
+            ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&line))
+        })
+    });
+    filtered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use typua_checker::FileDiagnostic;
+    use typua_span::{Position, Span};
+    use typua_ty::diagnostic::Diagnostic;
+    use typua_ty::message::Message;
+    #[test]
+    fn parse_hunks() {
+        let diff = "\
+diff --git a/src/a.lua b/src/a.lua
+index 1111111..2222222 100644
+--- a/src/a.lua
++++ b/src/a.lua
+@@ -3 +3 @@ local x = 1
+-local y = 2
++local y = 3
+@@ -10,2 +12,3 @@
++++ x
++local s = 1
++local t = 2
+@@ -20,4 +22,0 @@
+diff --git a/old.lua b/old.lua
+deleted file mode 100644
+--- a/old.lua
++++ /dev/null
+@@ -1,2 +0,0 @@
+diff --git a/my file.lua b/my file.lua
+--- a/my file.lua\t
++++ b/my file.lua\t
+@@ -1 +1 @@
+";
+        // special characters are escaped in quotes, non-ASCII bytes with core.quotePath
+        let quoted = r#"diff --git "a/say \"hi\".lua" "b/say \"hi\".lua"
+--- "a/say \"hi\".lua"
++++ "b/say \"hi\".lua"
+@@ -1 +1 @@
+diff --git "a/\346\227\245.lua" "b/\346\227\245.lua"
+--- "a/\346\227\245.lua"
++++ "b/\346\227\245.lua"
+@@ -2 +2 @@
+"#;
+        let changed = parse_diff(&format!("{diff}{quoted}"), Path::new("/repo"));
+        assert_eq!(
+            changed,
+            ChangedLines::from([
+                (PathBuf::from("/repo/src/a.lua"), vec![(3, 3), (12, 14)]),
+                (PathBuf::from("/repo/my file.lua"), vec![(1, 1)]),
+                (PathBuf::from("/repo/say \"hi\".lua"), vec![(1, 1)]),
+                (PathBuf::from("/repo/日.lua"), vec![(2, 2)]),
+            ])
+        );
+    }
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=typua",
+                "-c",
+                "user.email=typua@example.com",
+            ])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?}");
+    }
+    #[test]
+    fn changed_lines_with_noprefix_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| git(dir.path(), args);
+        git(&["init", "-q"]);
+        git(&["config", "diff.noprefix", "true"]);
+        let file = dir.path().join("a.lua");
+        std::fs::write(&file, "local x = 1\n").unwrap();
+        git(&["add", "a.lua"]);
+        git(&["commit", "-q", "-m", "init"]);
+        std::fs::write(&file, "local x = 1\nlocal y = 2\n").unwrap();
+        let changed = changed_lines(dir.path(), "HEAD").unwrap();
+        assert_eq!(
+            changed,
+            ChangedLines::from([(file.canonicalize().unwrap(), vec![(2, 2)])])
+        );
+    }
+    #[test]
+    fn changed_lines_of_paths_git_quotes() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| git(dir.path(), args);
+        git(&["init", "-q"]);
+        // the user's config is overridden
+        git(&["config", "core.quotePath", "true"]);
+        let files = [
+            dir.path().join("my file.lua"),
+            dir.path().join("日本語.lua"),
+        ];
+        for file in files.iter() {
+            std::fs::write(file, "local x = 1\n").unwrap();
+        }
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+        for file in files.iter() {
+            std::fs::write(file, "local x = 2\n").unwrap();
+        }
+        let changed = changed_lines(dir.path(), "HEAD").unwrap();
+        assert_eq!(
+            changed,
+            ChangedLines::from(files.map(|file| (file.canonicalize().unwrap(), vec![(1, 1)])))
+        );
+    }
+    #[test]
+    fn keep_diagnostics_on_changed_lines() {
+        let diagnostic = |path: &str, line: u32| FileDiagnostic {
+            path: PathBuf::from(path),
+            diagnostic: Diagnostic::new(
+                Message::BreakOutsideLoop,
+                Span::new(Position::new(line, 1), Position::new(line, 6)),
+            ),
+        };
+        let report = CheckReport {
+            files_checked: 2,
+            diagnostics: vec![
+                diagnostic("/repo/a.lua", 2),
+                diagnostic("/repo/a.lua", 5),
+                diagnostic("/repo/a.lua", 13),
+                diagnostic("/repo/b.lua", 5),
+            ],
+            ..CheckReport::default()
+        };
+        let changed = ChangedLines::from([(PathBuf::from("/repo/a.lua"), vec![(5, 5), (10, 12)])]);
+        let filtered = filter_changed(&report, &changed);
+        assert_eq!(filtered.files_checked, 2);
+        assert_eq!(
+            filtered
+                .diagnostics
+                .iter()
+                .map(|d| (d.path.clone(), d.diagnostic.span.start.line()))
+                .collect::<Vec<_>>(),
+            vec![(PathBuf::from("/repo/a.lua"), 5)]
+        );
+    }
+}
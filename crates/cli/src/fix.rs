@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use typua_binder::Binder;
+use typua_checker::{CheckOptions, InferredLocal, catch_panic, typecheck};
+use typua_config::LuaVersion;
+use typua_parser::ast::{Block, Stmt};
+use typua_parser::parse;
+use typua_ty::TypeKind;
+use typua_vfs::collect_source_files;
+
+/// rewrite files under `options.target` with `fix_source`
+///   files with uncommitted changes are skipped unless `allow_dirty`
+pub fn fix_files(options: &CheckOptions, allow_dirty: bool) -> anyhow::Result<()> {
+    for path in collect_source_files(&options.target, &options.config.workspace) {
+        let content = std::fs::read_to_string(&path)?;
+        let Some(fixed) = fix_source(&content, options.version) else {
+            continue;
+        };
+        if fixed == content {
+            continue;
+        }
+        if !allow_dirty && !is_clean_in_git(&path) {
+            eprintln!(
+                "skipped {}: not committed in git, pass --allow-dirty to fix anyway",
+                path.display()
+            );
+            continue;
+        }
+        std::fs::write(&path, fixed)?;
+        writeln!(std::io::stdout().lock(), "fixed {}", path.display())?;
+    }
+    Ok(())
+}
+
+/// file is tracked by git and has no uncommitted changes
+fn is_clean_in_git(path: &Path) -> bool {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .arg(path)
+            .output()
+    };
+    let tracked = git(&["ls-files", "--error-unmatch", "--"]).is_ok_and(|out| out.status.success());
+    let unchanged = git(&["status", "--porcelain", "--"])
+        .is_ok_and(|out| out.status.success() && out.stdout.is_empty());
+    tracked && unchanged
+}
+
+/// `content` with stub annotations inserted
+///   `---@param x any` and `---@return any` above local functions without annotations
+///   `---@type T` above locals without annotations whose values have concrete types
+///   statements already annotated are left as is, so fixing twice changes nothing
+///   None => the file has syntax errors or syntax not supported yet
+pub fn fix_source(content: &str, version: LuaVersion) -> Option<String> {
+    let (block, inferred) = catch_panic(|| {
+        let (ast, errors) = parse(content, version);
+        if !errors.is_empty() {
+            return None;
+        }
+        let mut binder = Binder::new();
+        binder.bind(&ast);
+        let result = typecheck(&ast, &binder);
+        Some((ast.block, result.inferred))
+    })
+    .ok()??;
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut stubs = BTreeMap::new();
+    collect_stubs(&block, 0, &inferred, &lines, &mut stubs);
+    let mut fixed = String::with_capacity(content.len());
+    for (i, line) in lines.iter().enumerate() {
+        // stubs take the indentation and line ending of the statement
+        for stub in stubs.get(&(i as u32 + 1)).into_iter().flatten() {
+            let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+            fixed.push_str(&line[..indent_len]);
+            fixed.push_str(stub);
+            fixed.push_str(if line.ends_with("\r\n") { "\r\n" } else { "\n" });
+        }
+        fixed.push_str(line);
+    }
+    Some(fixed)
+}
+
+/// stubs of the statements in the block keyed by the line to insert them above
+///   a statement not starting its own line is skipped, annotations above it would belong to
+///   another statement
+fn collect_stubs(
+    block: &Block,
+    header_line: u32,
+    inferred: &[InferredLocal],
+    lines: &[&str],
+    stubs: &mut BTreeMap<u32, Vec<String>>,
+) {
+    let mut prev_line = header_line;
+    for stmt in block.stmts.iter() {
+        let span = stmt.span();
+        let line = span.start.line();
+        let starts_line = line != prev_line
+            && lines
+                .get(line.saturating_sub(1) as usize)
+                .is_some_and(|text| text.trim_start().starts_with("local"));
+        prev_line = span.end.line();
+        match stmt {
+            Stmt::LocalAssign(local_assign) if starts_line && local_assign.annotates.is_empty() => {
+                // every name needs a type, `---@type` binds to the names in order
+                let tys: Option<Vec<&TypeKind>> = local_assign
+                    .vars
+                    .iter()
+                    .map(|var| {
+                        inferred
+                            .iter()
+                            .find(|local| local.span == var.span)
+                            .map(|local| &local.ty)
+                            .filter(|ty| is_concrete(ty))
+                    })
+                    .collect();
+                if let Some(tys) = tys {
+                    stubs
+                        .entry(line)
+                        .or_default()
+                        .extend(tys.iter().map(|ty| format!("---@type {}", ty)));
+                }
+            }
+            Stmt::LocalFunction(local_function) => {
+                if starts_line && local_function.annotates.is_empty() {
+                    let params = local_function
+                        .params
+                        .iter()
+                        .map(|param| format!("---@param {} any", param.name));
+                    let returns = match local_function.body.stmts.last() {
+                        Some(Stmt::Return(ret)) => ret.values.len(),
+                        _ => 0,
+                    };
+                    let function_stubs: Vec<String> = params
+                        .chain(std::iter::repeat_n("---@return any".to_string(), returns))
+                        .collect();
+                    if !function_stubs.is_empty() {
+                        stubs.entry(line).or_default().extend(function_stubs);
+                    }
+                }
+                collect_stubs(&local_function.body, line, inferred, lines, stubs);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// type which says more than any and reads back as the same annotation
+fn is_concrete(ty: &TypeKind) -> bool {
+    match ty {
+        TypeKind::Number
+        | TypeKind::Integer
+        | TypeKind::Boolean
+        | TypeKind::BooleanLiteral(_)
+        | TypeKind::String
+        | TypeKind::Table
+        | TypeKind::Custom(_) => true,
+        // number|string[] would read as number|(string[])
+        TypeKind::Array(elem) => !matches!(**elem, TypeKind::Union(_)) && is_concrete(elem),
+        TypeKind::Union(tys) => tys.iter().all(is_concrete),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use unindent::unindent;
+    fn fix(code: &str) -> Option<String> {
+        fix_source(&unindent(code), LuaVersion::Lua51)
+    }
+    #[test]
+    fn insert_stubs() {
+        let fixed = fix(r#"
+            local count = 1
+            local names = { "a", "b" }
+            local unknown = nil
+            ---@type string
+            local name = "typua"
+            local function add(a, b)
+                local sum = a + b
+                return sum
+            end
+            "#);
+        assert_eq!(
+            fixed,
+            Some(unindent(
+                r#"
+                ---@type number
+                local count = 1
+                ---@type string[]
+                local names = { "a", "b" }
+                local unknown = nil
+                ---@type string
+                local name = "typua"
+                ---@param a any
+                ---@param b any
+                ---@return any
+                local function add(a, b)
+                    local sum = a + b
+                    return sum
+                end
+                "#,
+            ))
+        );
+    }
+    #[test]
+    fn idempotent() {
+        let code = r#"
+            local count = 1
+            local function greet(name)
+                local message = "hello"
+            end
+            "#;
+        let once = fix(code).unwrap();
+        assert_eq!(fix_source(&once, LuaVersion::Lua51), Some(once.clone()));
+    }
+    #[test]
+    fn shared_line_is_skipped() {
+        assert_eq!(
+            fix("local a = 1 local b = 2\n"),
+            Some("---@type number\nlocal a = 1 local b = 2\n".to_string())
+        );
+    }
+    #[test]
+    fn unsupported_file_is_not_fixed() {
+        assert_eq!(fix("local x = \n"), None);
+    }
+}
@@ -1,34 +1,148 @@
+use std::io::{IsTerminal, Write};
+use std::process::ExitCode;
+
 use clap::Parser;
 
 mod args;
+mod debug;
+mod diff;
+mod fix;
+mod render;
 
-use crate::args::{Args, CheckCommand, Commands};
-use std::{fs::File, io::Read};
-use typua_binder::Binder;
-use typua_checker::typecheck;
+use crate::args::{Args, AstCommand, CheckCommand, Commands, DebugCommand};
+use crate::debug::debug_ast;
+use crate::diff::{changed_lines, filter_changed};
+use crate::fix::fix_files;
+use crate::render::{has_errors, render_diagnostics, render_summary};
+use typua_checker::{CheckOptions, run_with_progress};
+use typua_config::Config;
 use typua_lsp::handle_lsp_service;
-use typua_parser::parse;
+use typua_ty::TypuaError;
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
     let args = Args::parse();
 
-    match args.command {
-        Commands::Serve(_) => handle_lsp_service(),
-        Commands::Check(CheckCommand { path, version }) => {
-            let mut f = File::open(
-                path.unwrap_or_else(|| std::env::current_dir().expect("failed get cwd")),
-            )?;
-            let mut content = String::new();
-            f.read_to_string(&mut content)?;
-            let (ast, errors) = parse(&content, version.unwrap_or_default());
-            let mut binder = Binder::new();
-            binder.bind(&ast);
-            let env = binder.get_env();
-            println!("Env: {:#?}", env);
-            let report = typecheck(&ast, &env);
-            println!("{:#?}", report);
+    let result = match args.command {
+        Commands::Serve(_) => {
+            handle_lsp_service();
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Check(command) => handle_check(command),
+        Commands::Debug(DebugCommand::Ast(command)) => {
+            handle_debug_ast(command).map(|()| ExitCode::SUCCESS)
+        }
+    };
+    match result {
+        Ok(code) => code,
+        // the reader went away, like `typua check | head`, nothing is left to tell it
+        Err(err)
+            if err
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            // errors from outside typua, like writing the fixed files, exit with 1
+            ExitCode::from(err.downcast_ref().map_or(1, TypuaError::exit_code))
         }
     }
+}
+
+/// output written to stdout at once, locked, the error returned instead of a panic
+fn print_out(out: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(out.as_bytes())?;
+    stdout.flush()
+}
 
+/// exits with 1 when an error is reported, warnings and hints alone exit with 0
+fn handle_check(command: CheckCommand) -> anyhow::Result<ExitCode> {
+    let CheckCommand {
+        path,
+        version,
+        color,
+        max_diagnostics,
+        timings,
+        fix,
+        allow_dirty,
+        diff,
+        since,
+        quiet,
+    } = command;
+    let cwd = std::env::current_dir().map_err(|source| TypuaError::CurrentDir { source })?;
+    let root = path.unwrap_or_else(|| cwd.clone());
+    let config = Config::load_from_dir(if root.is_dir() { &root } else { &cwd })?;
+    // the version on the command line wins over the config and the nested ones
+    let options = match version {
+        Some(version) => CheckOptions::new(root, config).with_explicit_version(version),
+        None => {
+            let version = config.runtime.version.unwrap_or_default();
+            CheckOptions::new(root, config).with_version(version)
+        }
+    };
+    if fix {
+        fix_files(&options, allow_dirty)?;
+    }
+    // the whole project is checked, as unchanged lines may be affected by the changed ones
+    let changed = match since.or(diff.then(|| "HEAD".to_string())) {
+        Some(base) => Some(changed_lines(&options.target, &base)?),
+        None => None,
+    };
+    let show_progress = !quiet && std::io::stderr().is_terminal();
+    let report = run_with_progress(&options, |current, total| {
+        if show_progress {
+            // \r overwrites the line, the last one is cleared below
+            eprint!("\rchecking {current}/{total}");
+            let _ = std::io::stderr().flush();
+        }
+    })?
+    .sorted();
+    let report = match &changed {
+        Some(changed) => filter_changed(&report, changed),
+        None => report,
+    };
+    if show_progress {
+        eprint!("\r\x1b[2K");
+    }
+    let mut out = render_diagnostics(&report.diagnostics, color, max_diagnostics);
+    out.push_str(&render_summary(
+        report.files_checked,
+        &report.diagnostics,
+        quiet,
+    ));
+    if let Some(slowest) = timings {
+        out.push_str(&report.timings.render(slowest));
+    }
+    print_out(&out)?;
+    Ok(if has_errors(&report.diagnostics) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn handle_debug_ast(command: AstCommand) -> anyhow::Result<()> {
+    let AstCommand {
+        file,
+        version,
+        registry,
+    } = command;
+    let content = std::fs::read_to_string(&file).map_err(|source| TypuaError::SourceRead {
+        path: file.display().to_string(),
+        source,
+    })?;
+    let version = match version {
+        Some(version) => version,
+        None => {
+            let dir = file.parent().unwrap_or(std::path::Path::new("."));
+            Config::load_from_dir(dir)?
+                .runtime
+                .version
+                .unwrap_or_default()
+        }
+    };
+    print_out(&debug_ast(&content, version, registry)?)?;
     Ok(())
 }
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::IsTerminal;
+
+use typua_checker::FileDiagnostic;
+use typua_ty::diagnostic::Severity;
+use typua_ty::message::plural;
+
+use crate::args::ColorMode;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+
+/// diagnostic codes listed in the summary, the most frequent ones
+const SUMMARY_CODES: usize = 5;
+
+impl ColorMode {
+    /// auto colors only when stdout is a terminal
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => std::io::stdout().is_terminal(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+/// human readable diagnostic like rustc
+///   error: cannot assign `string` to `number`
+///     --> src/main.lua:2:11
+pub struct Rendered<'a> {
+    pub diagnostic: &'a FileDiagnostic,
+    pub color: bool,
+}
+
+impl<'a> Rendered<'a> {
+    pub fn new(diagnostic: &'a FileDiagnostic, color: ColorMode) -> Self {
+        Self {
+            diagnostic,
+            color: color.enabled(),
+        }
+    }
+    fn paint(&self, style: &'static str) -> (&'static str, &'static str) {
+        if self.color { (style, RESET) } else { ("", "") }
+    }
+}
+
+impl fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let FileDiagnostic { path, diagnostic } = self.diagnostic;
+        let severity = diagnostic.kind.severity();
+        let (sev_on, sev_off) = self.paint(match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Hint => CYAN,
+        });
+        let (msg_on, msg_off) = self.paint(BOLD);
+        let (arrow_on, arrow_off) = self.paint(BLUE);
+        writeln!(
+            f,
+            "{sev_on}{severity}{sev_off}{msg_on}: {}{msg_off}",
+            diagnostic.message
+        )?;
+        write!(
+            f,
+            "  {arrow_on}-->{arrow_off} {}:{}:{}",
+            path.display(),
+            diagnostic.span.start.line(),
+            diagnostic.span.start.character()
+        )
+    }
+}
+
+/// diagnostics in the given order, at most `max` of them
+///   the rest is summarized as "... and N more"
+///   `CheckReport::sorted` orders them by file and position
+pub fn render_diagnostics(
+    diagnostics: &[FileDiagnostic],
+    color: ColorMode,
+    max: Option<usize>,
+) -> String {
+    let shown = max.unwrap_or(diagnostics.len()).min(diagnostics.len());
+    let mut out = String::new();
+    for diagnostic in diagnostics.iter().take(shown) {
+        out.push_str(&format!("{}\n\n", Rendered::new(diagnostic, color)));
+    }
+    if shown < diagnostics.len() {
+        out.push_str(&format!("... and {} more\n", diagnostics.len() - shown));
+    }
+    out
+}
+
+/// any of the diagnostics is an error, `typua check` fails then
+pub fn has_errors(diagnostics: &[FileDiagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|d| d.diagnostic.kind.severity() == Severity::Error)
+}
+
+/// last lines of `typua check`
+///   checked 2 files, 3 diagnostics: 1 error, 2 warnings
+///     unreachable-code: 2
+///     type-mismatch: 1
+///   hints are counted only if there are any
+///   codes are listed by frequency unless `quiet`
+pub fn render_summary(files_checked: usize, diagnostics: &[FileDiagnostic], quiet: bool) -> String {
+    let mut out = format!(
+        "checked {}, {}",
+        plural(files_checked, "file"),
+        plural(diagnostics.len(), "diagnostic")
+    );
+    if diagnostics.is_empty() {
+        out.push('\n');
+        return out;
+    }
+    let count = |severity: Severity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.diagnostic.kind.severity() == severity)
+            .count()
+    };
+    out.push_str(&format!(
+        ": {}, {}",
+        plural(count(Severity::Error), "error"),
+        plural(count(Severity::Warning), "warning")
+    ));
+    let hints = count(Severity::Hint);
+    if hints > 0 {
+        out.push_str(&format!(", {}", plural(hints, "hint")));
+    }
+    out.push('\n');
+    if quiet {
+        return out;
+    }
+    let mut codes: BTreeMap<&str, usize> = BTreeMap::new();
+    for d in diagnostics.iter() {
+        *codes.entry(d.diagnostic.kind.code()).or_default() += 1;
+    }
+    let mut codes: Vec<(&str, usize)> = codes.into_iter().collect();
+    // stable, so codes of the same count stay in name order
+    codes.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    for (code, n) in codes.iter().take(SUMMARY_CODES) {
+        out.push_str(&format!("  {code}: {n}\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+    use typua_checker::CheckReport;
+    use typua_span::{Position, Span};
+    use typua_ty::diagnostic::{Diagnostic, DiagnosticKind};
+    fn diagnostic() -> FileDiagnostic {
+        FileDiagnostic {
+            path: PathBuf::from("main.lua"),
+            diagnostic: Diagnostic {
+                message: "cannot assign `string` to `number`".to_string(),
+                kind: DiagnosticKind::TypeMismatch,
+                span: Span::new(Position::new(2, 11), Position::new(2, 14)),
+            },
+        }
+    }
+    #[test]
+    fn color_never() {
+        let diag = diagnostic();
+        let rendered = Rendered::new(&diag, ColorMode::Never).to_string();
+        assert_eq!(
+            rendered,
+            "error: cannot assign `string` to `number`\n  --> main.lua:2:11"
+        );
+        assert!(!rendered.contains('\x1b'));
+    }
+    #[test]
+    fn color_always() {
+        let diag = diagnostic();
+        let rendered = Rendered::new(&diag, ColorMode::Always).to_string();
+        assert_eq!(
+            rendered,
+            "\x1b[1;31merror\x1b[0m\x1b[1m: cannot assign `string` to `number`\x1b[0m\n  \x1b[1;34m-->\x1b[0m main.lua:2:11"
+        );
+    }
+    #[test]
+    fn max_diagnostics() {
+        let at = |line| FileDiagnostic {
+            path: PathBuf::from("main.lua"),
+            diagnostic: Diagnostic {
+                message: "unreachable code".to_string(),
+                kind: DiagnosticKind::UnreachableCode,
+                span: Span::new(Position::new(line, 1), Position::new(line, 2)),
+            },
+        };
+        let diagnostics = CheckReport {
+            files_checked: 1,
+            diagnostics: vec![at(3), at(1), at(2)],
+            timings: Default::default(),
+        }
+        .sorted()
+        .diagnostics;
+        assert_eq!(
+            render_diagnostics(&diagnostics, ColorMode::Never, Some(2)),
+            "warning: unreachable code\n  --> main.lua:1:1\n\n\
+             warning: unreachable code\n  --> main.lua:2:1\n\n\
+             ... and 1 more\n"
+        );
+        assert_eq!(
+            render_diagnostics(&diagnostics, ColorMode::Never, None)
+                .matches("warning")
+                .count(),
+            3
+        );
+    }
+    #[test]
+    fn summary_by_severity_and_code() {
+        let at = |line, kind| FileDiagnostic {
+            path: PathBuf::from("main.lua"),
+            diagnostic: Diagnostic {
+                message: String::new(),
+                kind,
+                span: Span::new(Position::new(line, 1), Position::new(line, 2)),
+            },
+        };
+        let diagnostics = vec![
+            at(1, DiagnosticKind::UnreachableCode),
+            at(2, DiagnosticKind::TypeMismatch),
+            at(3, DiagnosticKind::UnreachableCode),
+            at(4, DiagnosticKind::NilArithmetic),
+        ];
+        assert_eq!(
+            render_summary(2, &diagnostics, false),
+            "checked 2 files, 4 diagnostics: 2 errors, 2 warnings\n  \
+             unreachable-code: 2\n  \
+             need-check-nil: 1\n  \
+             type-mismatch: 1\n"
+        );
+        assert_eq!(
+            render_summary(2, &diagnostics[..1], true),
+            "checked 2 files, 1 diagnostic: 0 errors, 1 warning\n"
+        );
+        assert_eq!(
+            render_summary(1, &[at(1, DiagnosticKind::ShadowedBuiltin)], true),
+            "checked 1 file, 1 diagnostic: 0 errors, 0 warnings, 1 hint\n"
+        );
+        assert_eq!(
+            render_summary(2, &[], false),
+            "checked 2 files, 0 diagnostics\n"
+        );
+    }
+    #[test]
+    fn errors_fail_the_check() {
+        assert!(has_errors(&[diagnostic()]));
+        let warning = FileDiagnostic {
+            diagnostic: Diagnostic {
+                kind: DiagnosticKind::UnreachableCode,
+                ..diagnostic().diagnostic
+            },
+            ..diagnostic()
+        };
+        assert!(!has_errors(&[warning]));
+        assert!(!has_errors(&[]));
+    }
+}
@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use typua_ty::{ConfigError, TypuaError};
+
+use crate::luarc::LUARC_FILE_NAME;
+use crate::version::LuaVersion;
+
+pub const CONFIG_FILE_NAME: &str = ".typua.toml";
+
+/// latest schema version of `.typua.toml`
+pub const CONFIG_VERSION: u32 = 1;
+
+/// contents of `.typua.toml`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// schema version, reserved for future migration
+    pub version: Option<u32>,
+    /// json schema url for editor support, not used by typua
+    #[serde(rename = "$schema")]
+    pub schema: Option<String>,
+    pub runtime: RuntimeConfig,
+    pub workspace: WorkspaceConfig,
+    pub diagnostics: DiagnosticsConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// lua51, lua52, lua53, lua54 or luajit, `lua5.1` and `5.1` are accepted too
+    pub version: Option<LuaVersion>,
+    /// module search path for `require`
+    pub path: Vec<String>,
+    pub path_strict: bool,
+    /// columns a tab counts as in reported positions, 1 is the same as full-moon
+    pub tab_width: u32,
+    /// report assignment to globals which are neither declared nor known
+    pub strict_globals: bool,
+    /// number which may have a fraction can't be assigned to integer, interchangeable if false
+    pub integer_strict: bool,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            version: None,
+            path: Vec::new(),
+            path_strict: false,
+            tab_width: 1,
+            strict_globals: false,
+            integer_strict: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// directory names skipped in addition to the defaults (`.git`)
+    pub ignore_dir: Vec<String>,
+    /// skip files matched by `.gitignore`, `use_gitignore` before
+    #[serde(alias = "use_gitignore")]
+    pub respect_gitignore: bool,
+    /// directories of library definitions outside the workspace
+    pub library: Vec<String>,
+    /// globals assigned at the top level of a file are declared in every file,
+    /// for scripts sharing one global environment
+    pub shared_globals: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiagnosticsConfig {
+    /// names of diagnostics turned off
+    pub disable: Vec<String>,
+}
+
+impl Config {
+    pub fn load_from_file(path: &Path) -> Result<Config, TypuaError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFailed {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Config::load_from_str(&content).map_err(|e| match e {
+            TypuaError::Config(ConfigError::InvalidConfig { message, .. }) => {
+                TypuaError::Config(ConfigError::InvalidConfig {
+                    path: path.display().to_string(),
+                    message,
+                })
+            }
+            e => e,
+        })
+    }
+    /// load `.typua.toml` in `dir`, `.luarc.json` if not exists, default config if neither
+    pub fn load_from_dir(dir: &Path) -> Result<Config, TypuaError> {
+        let path = dir.join(CONFIG_FILE_NAME);
+        let luarc = dir.join(LUARC_FILE_NAME);
+        if path.is_file() {
+            Config::load_from_file(&path)
+        } else if luarc.is_file() {
+            Config::load_from_luarc_file(&luarc)
+        } else {
+            Ok(Config::default())
+        }
+    }
+    pub fn load_from_str(content: &str) -> Result<Config, TypuaError> {
+        let config: Config = toml::from_str(content).map_err(|e| {
+            TypuaError::Config(ConfigError::InvalidConfig {
+                path: CONFIG_FILE_NAME.to_string(),
+                message: e.message().to_string(),
+            })
+        })?;
+        match config.version {
+            Some(version) if version > CONFIG_VERSION => {
+                Err(TypuaError::Config(ConfigError::InvalidConfig {
+                    path: CONFIG_FILE_NAME.to_string(),
+                    message: format!(
+                        "unsupported config version {version}, latest is {CONFIG_VERSION}"
+                    ),
+                }))
+            }
+            _ => Ok(config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    #[test]
+    fn load_workspace() {
+        let config = Config::load_from_str(
+            r#"
+            [workspace]
+            ignore_dir = ["target"]
+            respect_gitignore = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.workspace,
+            WorkspaceConfig {
+                ignore_dir: vec!["target".to_string()],
+                respect_gitignore: true,
+                library: Vec::new(),
+                shared_globals: false,
+            }
+        );
+        // missing section falls back to default
+        let config = Config::load_from_str("").unwrap();
+        assert_eq!(config, Config::default());
+    }
+    #[test]
+    fn load_versioned() {
+        let config = Config::load_from_str(
+            r#"
+            "$schema" = "https://example.com/typua.schema.json"
+            version = 1
+            [runtime]
+            version = "luajit"
+            path = ["?.lua", "?/init.lua"]
+            path_strict = false
+            tab_width = 4
+            strict_globals = true
+            integer_strict = false
+            [workspace]
+            ignore_dir = ["target"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.version, Some(1));
+        assert_eq!(config.runtime.version, Some(LuaVersion::LuaJit));
+        assert_eq!(config.runtime.tab_width, 4);
+        assert_eq!(RuntimeConfig::default().tab_width, 1);
+        assert!(config.runtime.strict_globals);
+        assert!(!RuntimeConfig::default().strict_globals);
+        assert!(!config.runtime.integer_strict);
+        assert!(RuntimeConfig::default().integer_strict);
+        let err = Config::load_from_str("version = 2").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config error: invalid config .typua.toml: unsupported config version 2, latest is 1"
+        );
+    }
+    #[test]
+    fn reject_unknown_keys() {
+        let err = Config::load_from_str("[runtim]\nversion = \"lua51\"\n").unwrap_err();
+        assert!(err.to_string().contains("unknown field `runtim`"), "{err}");
+        let err = Config::load_from_str("[workspace]\nignore_dirs = []\n").unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `ignore_dirs`"),
+            "{err}"
+        );
+    }
+    #[test]
+    fn reject_unknown_version() {
+        let config = Config::load_from_str("[runtime]\nversion = \"5.2\"\n").unwrap();
+        assert_eq!(config.runtime.version, Some(LuaVersion::Lua52));
+        let err = Config::load_from_str("[runtime]\nversion = \"lua55\"\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config error: invalid config .typua.toml: \
+             invalid lua version `lua55`, expected one of lua51, lua52, lua53, lua54, luajit"
+        );
+    }
+    #[test]
+    fn load_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            Config::load_from_dir(dir.path()).unwrap(),
+            Config::default()
+        );
+        // the former name is still read
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[workspace]\nuse_gitignore = true\n",
+        )
+        .unwrap();
+        assert!(
+            Config::load_from_dir(dir.path())
+                .unwrap()
+                .workspace
+                .respect_gitignore
+        );
+    }
+}
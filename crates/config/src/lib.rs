@@ -1,2 +1,8 @@
+pub mod config;
+pub mod luarc;
 pub mod version;
+pub use config::{
+    CONFIG_FILE_NAME, CONFIG_VERSION, Config, DiagnosticsConfig, RuntimeConfig, WorkspaceConfig,
+};
+pub use luarc::LUARC_FILE_NAME;
 pub use version::LuaVersion;
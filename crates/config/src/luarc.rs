@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use serde_json::Value;
+use typua_ty::{ConfigError, TypuaError};
+
+use crate::config::Config;
+
+pub const LUARC_FILE_NAME: &str = ".luarc.json";
+
+impl Config {
+    /// load `.luarc.json` of lua-language-server
+    ///   only `runtime.version`, `workspace.library` and `diagnostics.disable` are read
+    pub fn load_from_luarc_file(path: &Path) -> Result<Config, TypuaError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFailed {
+            path: path.display().to_string(),
+            source,
+        })?;
+        Config::load_from_luarc_str(&content).map_err(|e| match e {
+            TypuaError::Config(ConfigError::InvalidConfig { message, .. }) => {
+                TypuaError::Config(ConfigError::InvalidConfig {
+                    path: path.display().to_string(),
+                    message,
+                })
+            }
+            e => e,
+        })
+    }
+    /// keys are nested objects or dotted names, `"runtime.version": "LuaJIT"`
+    ///   other keys are ignored, they are settings typua doesn't have
+    pub fn load_from_luarc_str(content: &str) -> Result<Config, TypuaError> {
+        let invalid = |message: String| {
+            TypuaError::Config(ConfigError::InvalidConfig {
+                path: LUARC_FILE_NAME.to_string(),
+                message,
+            })
+        };
+        let json: Value = serde_json::from_str(content).map_err(|e| invalid(e.to_string()))?;
+        let strings = |key: &str| -> Result<Vec<String>, TypuaError> {
+            match lookup(&json, key) {
+                None => Ok(Vec::new()),
+                Some(value) => serde_json::from_value(value.clone())
+                    .map_err(|e| invalid(format!("`{key}`: {e}"))),
+            }
+        };
+        let mut config = Config::default();
+        config.workspace.library = strings("workspace.library")?;
+        config.diagnostics.disable = strings("diagnostics.disable")?;
+        if let Some(version) = lookup(&json, "runtime.version") {
+            let version = version
+                .as_str()
+                .ok_or_else(|| invalid("`runtime.version` must be a string".to_string()))?;
+            config.runtime.version = Some(version.parse().map_err(invalid)?);
+        }
+        Ok(config)
+    }
+}
+
+/// `section.name` as a dotted key or as a nested object
+fn lookup<'a>(json: &'a Value, key: &str) -> Option<&'a Value> {
+    json.get(key).or_else(|| {
+        let (section, name) = key.split_once('.')?;
+        json.get(section)?.get(name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CONFIG_FILE_NAME;
+    use crate::version::LuaVersion;
+    use pretty_assertions::assert_eq;
+    #[test]
+    fn load_luarc() {
+        let config = Config::load_from_luarc_str(
+            r#"{
+                "runtime": { "version": "LuaJIT", "path": ["?.lua"] },
+                "workspace.library": ["/usr/share/nvim/runtime"],
+                "diagnostics.disable": ["undefined-global"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.runtime.version, Some(LuaVersion::LuaJit));
+        assert_eq!(config.workspace.library, vec!["/usr/share/nvim/runtime"]);
+        assert_eq!(config.diagnostics.disable, vec!["undefined-global"]);
+        let config = Config::load_from_luarc_str(r#"{ "runtime.version": "Lua 5.2" }"#).unwrap();
+        assert_eq!(config.runtime.version, Some(LuaVersion::Lua52));
+        let err = Config::load_from_luarc_str(r#"{ "runtime.version": "Lua 5.5" }"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config error: invalid config .luarc.json: \
+             invalid lua version `Lua 5.5`, expected one of lua51, lua52, lua53, lua54, luajit"
+        );
+    }
+    #[test]
+    fn typua_toml_shadows_luarc() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(LUARC_FILE_NAME),
+            r#"{ "runtime.version": "LuaJIT" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Config::load_from_dir(dir.path()).unwrap().runtime.version,
+            Some(LuaVersion::LuaJit)
+        );
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "[runtime]\nversion = \"lua52\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Config::load_from_dir(dir.path()).unwrap().runtime.version,
+            Some(LuaVersion::Lua52)
+        );
+    }
+}
@@ -1,16 +1,70 @@
 use std::str::FromStr;
-#[derive(Debug, Clone, Copy, Default)]
+
+use serde::{Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum LuaVersion {
     #[default]
     Lua51,
+    /// adds goto and labels
+    Lua52,
+    /// adds integer division and bitwise operators
+    Lua53,
+    Lua54,
+    LuaJit,
 }
 
 impl FromStr for LuaVersion {
     type Err = String;
+    /// lua51, lua5.1, `Lua 5.1` and 5.1 are the same version, case is ignored
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "lua51" => Ok(Self::Lua51),
-            _ => Err(format!("invalid lua version: {}", s)),
+        let lower = s.trim().to_ascii_lowercase();
+        let number = lower
+            .strip_prefix("lua")
+            .unwrap_or(&lower)
+            .trim_start()
+            .replace('.', "");
+        match number.as_str() {
+            "51" => Ok(Self::Lua51),
+            "52" => Ok(Self::Lua52),
+            "53" => Ok(Self::Lua53),
+            "54" => Ok(Self::Lua54),
+            "jit" => Ok(Self::LuaJit),
+            _ => Err(format!(
+                "invalid lua version `{}`, expected one of lua51, lua52, lua53, lua54, luajit",
+                s
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LuaVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    #[test]
+    fn parse_spellings() {
+        for s in ["lua51", "lua5.1", "5.1", "Lua51", "Lua 5.1"] {
+            assert_eq!(s.parse::<LuaVersion>(), Ok(LuaVersion::Lua51), "{s}");
+        }
+        for s in ["lua52", "lua5.2", "5.2"] {
+            assert_eq!(s.parse::<LuaVersion>(), Ok(LuaVersion::Lua52), "{s}");
         }
+        assert_eq!("Lua 5.4".parse::<LuaVersion>(), Ok(LuaVersion::Lua54));
+        assert_eq!("luajit".parse::<LuaVersion>(), Ok(LuaVersion::LuaJit));
+        assert_eq!(
+            "lua55".parse::<LuaVersion>(),
+            Err(
+                "invalid lua version `lua55`, expected one of lua51, lua52, lua53, lua54, luajit"
+                    .to_string()
+            )
+        );
     }
 }
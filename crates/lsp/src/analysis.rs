@@ -0,0 +1,224 @@
+use tower_lsp::lsp_types::{
+    Diagnostic, Hover, InlayHint, InlayHintKind, InlayHintLabel, Position, Range, SignatureHelp,
+};
+use typua_binder::Binder;
+use typua_checker::{CheckResult, catch_panic, configured_diagnostics, syntax_errors, typecheck};
+use typua_config::{Config, LuaVersion};
+use typua_parser::ast::TypeAst;
+use typua_parser::parse;
+
+use crate::diagnostics::{to_lsp_diagnostic, to_lsp_range};
+use crate::hover::hover_checked;
+use crate::signature::signature_help;
+
+/// a document parsed and checked once, the diagnostics, hover and inlay hints of it
+///   kept by the server until the document changes
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// None => syntax not supported yet, nothing is known about the document
+    ast: Option<TypeAst>,
+    result: CheckResult,
+    diagnostics: Vec<typua_ty::diagnostic::Diagnostic>,
+    /// version the document is parsed with
+    version: LuaVersion,
+}
+
+impl Analysis {
+    /// a document with syntax errors is reported with them only,
+    /// but what could be parsed is still checked for hover and inlay hints
+    pub fn analyze(content: &str, config: &Config) -> Self {
+        let version = config.runtime.version.unwrap_or_default();
+        // parser and checker panic on syntax not supported yet, an internal error is reported then
+        let (ast, errors) = match catch_panic(|| parse(content, version)) {
+            Ok(parsed) => parsed,
+            Err(internal) => {
+                return Self {
+                    diagnostics: vec![internal],
+                    version,
+                    ..Self::default()
+                };
+            }
+        };
+        let checked = catch_panic(|| {
+            let mut binder = Binder::new();
+            binder.bind(&ast);
+            binder
+                .registry
+                .set_integer_strict(config.runtime.integer_strict);
+            (binder.registry.validate(), typecheck(&ast, &binder))
+        });
+        match (checked, errors.is_empty()) {
+            (Ok((registry_diagnostics, result)), true) => Self {
+                diagnostics: configured_diagnostics(
+                    &ast,
+                    config,
+                    registry_diagnostics
+                        .into_iter()
+                        .chain(result.diagnostics.iter().cloned())
+                        .collect(),
+                ),
+                ast: Some(ast),
+                result,
+                version,
+            },
+            (Ok((_, result)), false) => Self {
+                ast: Some(ast),
+                result,
+                diagnostics: syntax_errors(&errors),
+                version,
+            },
+            (Err(internal), true) => Self {
+                diagnostics: vec![internal],
+                version,
+                ..Self::default()
+            },
+            (Err(_), false) => Self {
+                diagnostics: syntax_errors(&errors),
+                version,
+                ..Self::default()
+            },
+        }
+    }
+    pub fn version(&self) -> LuaVersion {
+        self.version
+    }
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .iter()
+            .cloned()
+            .map(to_lsp_diagnostic)
+            .collect()
+    }
+    /// `content` is the source analyzed, the name under the cursor is read from it
+    pub fn hover(&self, content: &str, position: Position) -> Option<Hover> {
+        hover_checked(self.ast.as_ref()?, &self.result, content, position)
+    }
+    /// signature of the function called around `position` in `content`
+    pub fn signature_help(&self, content: &str, position: Position) -> Option<SignatureHelp> {
+        signature_help(&self.result, content, position)
+    }
+    /// `: type` after the name of each unannotated local in `range`
+    ///   `data` is the type serialized, for clients resolving the hint
+    pub fn inlay_hints(&self, range: Range) -> Vec<InlayHint> {
+        self.result
+            .inferred
+            .iter()
+            .filter_map(|local| {
+                let position = to_lsp_range(&local.span).end;
+                (range.start <= position && position <= range.end).then(|| InlayHint {
+                    position,
+                    label: InlayHintLabel::String(format!(": {}", local.ty)),
+                    kind: Some(InlayHintKind::TYPE),
+                    text_edits: None,
+                    tooltip: None,
+                    padding_left: None,
+                    padding_right: None,
+                    data: serde_json::to_value(&local.ty).ok(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tower_lsp::lsp_types::{HoverContents, NumberOrString};
+    #[test]
+    fn diagnostics_of_analyze() {
+        let analysis = Analysis::analyze("---@type string\nlocal name = 1\n", &Config::default());
+        let diagnostics = analysis.diagnostics();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.code.clone(), d.range.start))
+                .collect::<Vec<_>>(),
+            vec![(
+                Some(NumberOrString::String("assign-type-mismatch".to_string())),
+                Position::new(1, 13)
+            )]
+        );
+    }
+    #[test]
+    fn diagnostics_of_config() {
+        let content = "local x = 1 // 2\nprint(y)\n";
+        let codes = |config: &str| {
+            Analysis::analyze(content, &Config::load_from_str(config).unwrap())
+                .diagnostics()
+                .into_iter()
+                .filter_map(|d| d.code)
+                .collect::<Vec<_>>()
+        };
+        // `//` is a syntax error before lua 5.3
+        assert!(codes("").len() > 1);
+        assert_eq!(
+            codes("[runtime]\nversion = \"lua54\"\n"),
+            vec![NumberOrString::String("undefined-global".to_string())]
+        );
+        assert_eq!(
+            codes(
+                "[runtime]\nversion = \"lua54\"\n[diagnostics]\ndisable = [\"undefined-global\"]\n"
+            ),
+            Vec::new()
+        );
+    }
+    #[test]
+    fn hover_and_inlay_hints_of_analyze() {
+        let content = "local count = 1\nlocal name = \"typua\"\nprint(count)\n";
+        let analysis = Analysis::analyze(content, &Config::default());
+        let Some(HoverContents::Markup(markup)) = analysis
+            .hover(content, Position::new(0, 8))
+            .map(|hover| hover.contents)
+        else {
+            panic!("no hover on count");
+        };
+        assert_eq!(markup.value, "```lua\ncount: number\n```");
+        let hints = |range: Range| {
+            analysis
+                .inlay_hints(range)
+                .into_iter()
+                .map(|hint| match hint.label {
+                    InlayHintLabel::String(label) => (hint.position, label),
+                    InlayHintLabel::LabelParts(_) => panic!("label is not a string"),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            hints(Range::new(Position::new(0, 0), Position::new(3, 0))),
+            vec![
+                (Position::new(0, 11), ": number".to_string()),
+                (Position::new(1, 10), ": string".to_string()),
+            ]
+        );
+        // only those in the requested range
+        assert_eq!(
+            hints(Range::new(Position::new(1, 0), Position::new(2, 0))).len(),
+            1
+        );
+        assert_eq!(
+            analysis
+                .inlay_hints(Range::new(Position::new(0, 0), Position::new(3, 0)))
+                .into_iter()
+                .map(|hint| hint.data)
+                .collect::<Vec<_>>(),
+            vec![
+                Some(serde_json::json!("Number")),
+                Some(serde_json::json!("String"))
+            ]
+        );
+    }
+    #[test]
+    fn unsupported_syntax_is_an_internal_error() {
+        let analysis = Analysis::analyze("do\n    local x = 1\nend\n", &Config::default());
+        assert_eq!(
+            analysis
+                .diagnostics()
+                .iter()
+                .map(|d| d.code.clone())
+                .collect::<Vec<_>>(),
+            vec![Some(NumberOrString::String("internal-error".to_string()))]
+        );
+        assert_eq!(analysis.hover("do\n", Position::new(0, 0)), None);
+    }
+}
@@ -1,22 +1,117 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tokio::sync::RwLock;
 use tower_lsp::jsonrpc::Result as LspResult;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 use tracing::info;
+use typua_checker::IncrementalChecker;
+use typua_config::{Config, LuaVersion};
+
+use crate::analysis::Analysis;
+use crate::completion::complete_scope;
+use crate::diagnostics::{document_report, to_lsp_diagnostic};
+use crate::formatting::format_annotations;
+use crate::index::SymbolIndex;
 
 #[derive(Debug)]
 pub struct Backend {
     pub client: Client,
+    /// workspace symbols for cross-file goto-definition
+    pub index: RwLock<SymbolIndex>,
+    /// contents of opened documents
+    pub documents: RwLock<HashMap<Url, String>>,
+    /// opened documents checked on their own, hover and inlay hints are read from them
+    pub analyses: RwLock<HashMap<Url, Analysis>>,
+    /// diagnostics of the files edited, checked against each other's declarations
+    pub checker: RwLock<IncrementalChecker>,
+}
+
+impl Backend {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            index: RwLock::new(SymbolIndex::new()),
+            documents: RwLock::new(HashMap::new()),
+            analyses: RwLock::new(HashMap::new()),
+            checker: RwLock::new(IncrementalChecker::new(LuaVersion::default())),
+        }
+    }
+    async fn update_document(&self, uri: Url, text: String) {
+        let analysis = match uri.to_file_path() {
+            Ok(path) => {
+                let config = self.checker.write().await.config_of(&path);
+                self.index.write().await.index_file(&path, &text);
+                let affected = self.checker.write().await.update_file(&path, &text);
+                self.publish_checked(affected).await;
+                Analysis::analyze(&text, &config)
+            }
+            Err(_) => {
+                let analysis = Analysis::analyze(&text, self.checker.read().await.config());
+                self.client
+                    .publish_diagnostics(uri.clone(), analysis.diagnostics(), None)
+                    .await;
+                analysis
+            }
+        };
+        self.analyses.write().await.insert(uri.clone(), analysis);
+        self.documents.write().await.insert(uri, text);
+    }
+    /// a changed declaration may change diagnostics of other files
+    async fn publish_checked(&self, paths: Vec<PathBuf>) {
+        let checker = self.checker.read().await;
+        for path in paths {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            let diagnostics = checker
+                .diagnostics(&path)
+                .iter()
+                .cloned()
+                .map(to_lsp_diagnostic)
+                .collect();
+            self.client
+                .publish_diagnostics(uri, diagnostics, None)
+                .await;
+        }
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> LspResult<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> LspResult<InitializeResult> {
         info!("initialize");
+        #[allow(deprecated)]
+        if let Some(root) = params.root_uri.and_then(|uri| uri.to_file_path().ok()) {
+            let config = Config::load_from_dir(&root).unwrap_or_default();
+            *self.index.write().await = SymbolIndex::build(&root, &config);
+            *self.checker.write().await =
+                IncrementalChecker::new(LuaVersion::default()).with_config(root, config);
+        }
         Ok(InitializeResult {
             server_info: None,
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::INCREMENTAL,
+                    TextDocumentSyncKind::FULL,
+                )),
+                definition_provider: Some(OneOf::Left(true)),
+                completion_provider: Some(CompletionOptions::default()),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: None,
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                // diagnostics are pushed too, for clients which don't pull them
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                    DiagnosticOptions {
+                        identifier: Some("typua".to_string()),
+                        inter_file_dependencies: true,
+                        ..DiagnosticOptions::default()
+                    },
                 )),
                 ..ServerCapabilities::default()
             },
@@ -24,10 +119,58 @@ impl LanguageServer for Backend {
     }
     async fn initialized(&self, _: InitializedParams) {
         info!("initialized");
+        // watch lua files changed outside the editor, e.g. git checkout
+        let watchers = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*.lua".to_string()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "typua-watched-files".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(watchers).ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            info!("failed to register file watchers: {e}");
+        }
         self.client
             .log_message(MessageType::INFO, "initialized")
             .await;
     }
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        info!("did change watched files: {}", params.changes.len());
+        // open documents are newer than the files on disk
+        let documents = self.documents.read().await;
+        let events: Vec<FileEvent> = params
+            .changes
+            .into_iter()
+            .filter(|event| !documents.contains_key(&event.uri))
+            .collect();
+        drop(documents);
+        self.index.write().await.apply_file_events(&events);
+        let mut affected = Vec::new();
+        let mut checker = self.checker.write().await;
+        for event in events.iter() {
+            let Ok(path) = event.uri.to_file_path() else {
+                continue;
+            };
+            match std::fs::read_to_string(&path) {
+                Ok(content) if event.typ != FileChangeType::DELETED => {
+                    affected.extend(checker.update_file(&path, &content))
+                }
+                // publishing nothing clears the diagnostics of the deleted file
+                _ => {
+                    affected.extend(checker.remove_file(&path));
+                    affected.push(path);
+                }
+            }
+        }
+        drop(checker);
+        affected.sort();
+        affected.dedup();
+        self.publish_checked(affected).await;
+    }
     async fn shutdown(&self) -> LspResult<()> {
         info!("shutdown");
         Ok(())
@@ -40,6 +183,15 @@ impl LanguageServer for Backend {
                 format!("File open {}", params.text_document.uri),
             )
             .await;
+        self.update_document(params.text_document.uri, params.text_document.text)
+            .await;
+    }
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // full sync, the last change is the whole document
+        if let Some(change) = params.content_changes.into_iter().last() {
+            self.update_document(params.text_document.uri, change.text)
+                .await;
+        }
     }
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         info!("did close: {}", params.text_document.uri);
@@ -49,5 +201,227 @@ impl LanguageServer for Backend {
                 format!("File close {}", params.text_document.uri),
             )
             .await;
+        self.analyses
+            .write()
+            .await
+            .remove(&params.text_document.uri);
+        self.documents
+            .write()
+            .await
+            .remove(&params.text_document.uri);
+    }
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> LspResult<Option<GotoDefinitionResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let content = match self.documents.read().await.get(&text_document.uri) {
+            Some(content) => content.clone(),
+            None => match text_document
+                .uri
+                .to_file_path()
+                .ok()
+                .and_then(|path| std::fs::read_to_string(path).ok())
+            {
+                Some(content) => content,
+                None => return Ok(None),
+            },
+        };
+        let location = self.index.read().await.definition(&content, position);
+        Ok(location.map(GotoDefinitionResponse::Scalar))
+    }
+    async fn completion(&self, params: CompletionParams) -> LspResult<Option<CompletionResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position;
+        let Some(content) = self.documents.read().await.get(&text_document.uri).cloned() else {
+            return Ok(None);
+        };
+        let version = self
+            .analyses
+            .read()
+            .await
+            .get(&text_document.uri)
+            .map(Analysis::version)
+            .unwrap_or_default();
+        Ok(Some(CompletionResponse::Array(complete_scope(
+            &content, position, version,
+        ))))
+    }
+    async fn hover(&self, params: HoverParams) -> LspResult<Option<Hover>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let Some(content) = self.documents.read().await.get(&text_document.uri).cloned() else {
+            return Ok(None);
+        };
+        Ok(self
+            .analyses
+            .read()
+            .await
+            .get(&text_document.uri)
+            .and_then(|analysis| analysis.hover(&content, position)))
+    }
+    async fn signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> LspResult<Option<SignatureHelp>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let Some(content) = self.documents.read().await.get(&text_document.uri).cloned() else {
+            return Ok(None);
+        };
+        Ok(self
+            .analyses
+            .read()
+            .await
+            .get(&text_document.uri)
+            .and_then(|analysis| analysis.signature_help(&content, position)))
+    }
+    async fn inlay_hint(&self, params: InlayHintParams) -> LspResult<Option<Vec<InlayHint>>> {
+        Ok(self
+            .analyses
+            .read()
+            .await
+            .get(&params.text_document.uri)
+            .map(|analysis| analysis.inlay_hints(params.range)))
+    }
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> LspResult<DocumentDiagnosticReportResult> {
+        let uri = params.text_document.uri;
+        let items = match uri.to_file_path() {
+            Ok(path) => self
+                .checker
+                .read()
+                .await
+                .diagnostics(&path)
+                .iter()
+                .cloned()
+                .map(to_lsp_diagnostic)
+                .collect(),
+            Err(_) => self
+                .analyses
+                .read()
+                .await
+                .get(&uri)
+                .map(Analysis::diagnostics)
+                .unwrap_or_default(),
+        };
+        Ok(document_report(items).into())
+    }
+    async fn formatting(
+        &self,
+        params: DocumentFormattingParams,
+    ) -> LspResult<Option<Vec<TextEdit>>> {
+        let Some(content) = self
+            .documents
+            .read()
+            .await
+            .get(&params.text_document.uri)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        Ok(Some(format_annotations(&content)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tower_lsp::LspService;
+    #[tokio::test]
+    async fn requests_use_the_document_analysis() {
+        let (service, socket) = LspService::new(Backend::new);
+        // messages to the client fail instead of waiting for it to read them
+        drop(socket);
+        let backend = service.inner();
+        // not a file, checked on its own
+        let uri = Url::parse("untitled:Untitled-1").unwrap();
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "lua".to_string(),
+                    0,
+                    "local count = 1\nprint(count + true)\n".to_string(),
+                ),
+            })
+            .await;
+        let document = TextDocumentIdentifier::new(uri.clone());
+        let position = TextDocumentPositionParams::new(document.clone(), Position::new(0, 8));
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) = backend
+            .hover(HoverParams {
+                text_document_position_params: position,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+        else {
+            panic!("no hover on count");
+        };
+        assert_eq!(markup.value, "```lua\ncount: number\n```");
+        let hints = backend
+            .inlay_hint(InlayHintParams {
+                text_document: document.clone(),
+                range: Range::new(Position::new(0, 0), Position::new(2, 0)),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap_or_default();
+        assert_eq!(
+            hints
+                .into_iter()
+                .map(|hint| match hint.label {
+                    InlayHintLabel::String(label) => (hint.position, label),
+                    InlayHintLabel::LabelParts(_) => panic!("label is not a string"),
+                })
+                .collect::<Vec<_>>(),
+            vec![(Position::new(0, 11), ": number".to_string())]
+        );
+        let DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report)) =
+            backend
+                .diagnostic(DocumentDiagnosticParams {
+                    text_document: document,
+                    identifier: None,
+                    previous_result_id: None,
+                    work_done_progress_params: WorkDoneProgressParams::default(),
+                    partial_result_params: PartialResultParams::default(),
+                })
+                .await
+                .unwrap()
+        else {
+            panic!("report is not full");
+        };
+        assert_eq!(
+            report
+                .full_document_diagnostic_report
+                .items
+                .iter()
+                .map(|d| d.code.clone())
+                .collect::<Vec<_>>(),
+            vec![Some(NumberOrString::String("type-mismatch".to_string()))]
+        );
+        // closing forgets the analysis
+        backend
+            .did_close(DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+            })
+            .await;
+        assert!(backend.analyses.read().await.is_empty());
     }
 }
@@ -0,0 +1,154 @@
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Documentation, Position};
+use typua_binder::Binder;
+use typua_checker::{catch_panic, typecheck};
+use typua_config::LuaVersion;
+use typua_parser::parse;
+use typua_ty::TypeKind;
+
+use crate::index::byte_offset;
+
+/// kind of completion item for a symbol of `ty`
+///   `is_field` distinguishes table fields from variables in scope
+pub fn to_lsp_completion_kind(ty: &TypeKind, is_field: bool) -> CompletionItemKind {
+    match ty {
+        TypeKind::Function { .. } if is_field => CompletionItemKind::METHOD,
+        TypeKind::Function { .. } => CompletionItemKind::FUNCTION,
+        TypeKind::Class => CompletionItemKind::CLASS,
+        _ if is_field => CompletionItemKind::FIELD,
+        _ => CompletionItemKind::VARIABLE,
+    }
+}
+
+/// completion item with the type as detail and the doc comment as documentation
+pub fn completion_item(
+    name: &str,
+    ty: &TypeKind,
+    is_field: bool,
+    doc: Option<&str>,
+) -> CompletionItem {
+    CompletionItem {
+        label: name.to_string(),
+        kind: Some(to_lsp_completion_kind(ty, is_field)),
+        detail: Some(ty.to_string()),
+        documentation: doc.map(|doc| Documentation::String(doc.to_string())),
+        ..CompletionItem::default()
+    }
+}
+
+/// variables visible at the 0-based `position` whose names start with the typed prefix
+/// member access after `.` or `:` is not completed here
+pub fn complete_scope(
+    content: &str,
+    position: Position,
+    version: LuaVersion,
+) -> Vec<CompletionItem> {
+    let Some(line) = content.lines().nth(position.line as usize) else {
+        return Vec::new();
+    };
+    let before = &line[..byte_offset(line, position.character)];
+    let prefix_start = before
+        .char_indices()
+        .rfind(|(_, c)| !(c.is_ascii_alphanumeric() || *c == '_'))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let prefix = &before[prefix_start..];
+    if before[..prefix_start].ends_with(['.', ':']) {
+        return Vec::new();
+    }
+    // parser panics on syntax not supported yet, nothing is completed then
+    let Ok(result) = catch_panic(|| {
+        let (ast, _errors) = parse(content, version);
+        let mut binder = Binder::new();
+        binder.bind(&ast);
+        typecheck(&ast, &binder)
+    }) else {
+        return Vec::new();
+    };
+    // spans are 1-based
+    let cursor = typua_span::Position::new(position.line + 1, position.character + 1);
+    let Some(env) = result.scope_at(&cursor) else {
+        return Vec::new();
+    };
+    let mut items: Vec<CompletionItem> = env
+        .iter()
+        .filter(|(symbol, _)| symbol.val.starts_with(prefix))
+        .map(|(symbol, ty)| completion_item(&symbol.val, ty, false, None))
+        .collect();
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use unindent::unindent;
+    fn labels(items: Vec<CompletionItem>) -> Vec<String> {
+        items.into_iter().map(|item| item.label).collect()
+    }
+    #[test]
+    fn complete_params_and_enclosing_locals() {
+        let content = unindent(
+            r#"
+            local count = 1
+            ---@param name string
+            ---@param age number
+            local function greet(name, age)
+                local message = "hi"
+                local x = na
+            end
+            local y = 2
+            local z = y
+            "#,
+        );
+        // `na` inside greet
+        let items = complete_scope(&content, Position::new(5, 16), LuaVersion::Lua51);
+        assert_eq!(labels(items), vec!["name".to_string()]);
+        // empty prefix inside greet, x is in scope after its statement and y after greet
+        let items = complete_scope(&content, Position::new(5, 14), LuaVersion::Lua51);
+        assert_eq!(
+            labels(items),
+            vec!["age", "count", "greet", "message", "name"]
+        );
+        // params are not visible outside greet
+        let items = complete_scope(&content, Position::new(7, 10), LuaVersion::Lua51);
+        assert_eq!(labels(items), vec!["count", "greet"]);
+        let items = complete_scope(&content, Position::new(8, 10), LuaVersion::Lua51);
+        assert_eq!(labels(items), vec!["count", "greet", "y"]);
+    }
+    #[test]
+    fn complete_after_non_ascii_text() {
+        let content = "local count = 1\nlocal s = \"日本語\" local t = co\n";
+        // `co` after 日本語, which is 3 UTF-16 units in 9 bytes
+        let items = complete_scope(content, Position::new(1, 28), LuaVersion::Lua51);
+        assert_eq!(labels(items), vec!["count".to_string()]);
+    }
+    #[test]
+    fn function_completion_item() {
+        let ty = TypeKind::Function {
+            generics: Vec::new(),
+            param_names: Vec::new(),
+            params: vec![TypeKind::Number, TypeKind::String],
+            returns: vec![TypeKind::Boolean],
+            return_names: Vec::new(),
+            vararg: None,
+        };
+        let item = completion_item("check", &ty, false, Some("checks a value"));
+        assert_eq!(item.kind, Some(CompletionItemKind::FUNCTION));
+        assert_eq!(
+            item.detail,
+            Some("fun(number, string): boolean".to_string())
+        );
+        assert_eq!(
+            item.documentation,
+            Some(Documentation::String("checks a value".to_string()))
+        );
+        assert_eq!(
+            to_lsp_completion_kind(&TypeKind::Number, true),
+            CompletionItemKind::FIELD
+        );
+        assert_eq!(
+            to_lsp_completion_kind(&TypeKind::Number, false),
+            CompletionItemKind::VARIABLE
+        );
+    }
+}
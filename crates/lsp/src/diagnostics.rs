@@ -0,0 +1,84 @@
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentDiagnosticReport, FullDocumentDiagnosticReport,
+    NumberOrString, Position, Range, RelatedFullDocumentDiagnosticReport,
+};
+use typua_span::Span;
+use typua_ty::diagnostic::Severity;
+
+/// lsp positions are 0-based, spans are 1-based
+pub fn to_lsp_range(span: &Span) -> Range {
+    let position = |p: &typua_span::Position| {
+        Position::new(p.line().saturating_sub(1), p.character().saturating_sub(1))
+    };
+    Range::new(position(&span.start), position(&span.end))
+}
+
+pub fn to_lsp_diagnostic(diagnostic: typua_ty::diagnostic::Diagnostic) -> Diagnostic {
+    Diagnostic {
+        range: to_lsp_range(&diagnostic.span),
+        severity: Some(match diagnostic.kind.severity() {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Hint => DiagnosticSeverity::HINT,
+        }),
+        code: Some(NumberOrString::String(diagnostic.kind.code().to_string())),
+        source: Some("typua".to_string()),
+        message: diagnostic.message,
+        ..Diagnostic::default()
+    }
+}
+
+/// answer to `textDocument/diagnostic`, every diagnostic of the document
+///   no result id is given, so the client never gets an unchanged report
+pub fn document_report(items: Vec<Diagnostic>) -> DocumentDiagnosticReport {
+    DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+        related_documents: None,
+        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+            result_id: None,
+            items,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::Analysis;
+    use pretty_assertions::assert_eq;
+    use typua_config::Config;
+    #[test]
+    fn report_document_error() {
+        let DocumentDiagnosticReport::Full(report) = document_report(
+            Analysis::analyze("local x = 1 + true\n", &Config::default()).diagnostics(),
+        ) else {
+            panic!("report is not full");
+        };
+        let items = report.full_document_diagnostic_report.items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].code,
+            Some(NumberOrString::String("type-mismatch".to_string()))
+        );
+        assert_eq!(
+            items[0].range,
+            Range::new(Position::new(0, 14), Position::new(0, 18))
+        );
+    }
+    #[test]
+    fn syntax_error_at_its_range() {
+        let items = Analysis::analyze(
+            "local x = 1 +
+local y = 2
+",
+            &Config::default(),
+        )
+        .diagnostics();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].code,
+            Some(NumberOrString::String("parse-error".to_string()))
+        );
+        assert!(items[0].message.starts_with("syntax error: "));
+        assert_eq!(items[0].range.start, Position::new(0, 12));
+    }
+}
@@ -0,0 +1,124 @@
+use full_moon::tokenizer::{Lexer, LexerResult, TokenType};
+use tower_lsp::lsp_types::TextEdit;
+use typua_span::Span;
+
+use crate::diagnostics::to_lsp_range;
+
+/// edits rewriting annotation comments into canonical spacing
+///   `---  @param   x  number` => `---@param x  number`
+///   comments are found by the tokenizer, so lua code and strings are never touched
+pub fn format_annotations(content: &str) -> Vec<TextEdit> {
+    let tokens = match Lexer::new(content, full_moon::LuaVersion::lua52()).collect() {
+        LexerResult::Ok(tokens) | LexerResult::Recovered(tokens, _) => tokens,
+        LexerResult::Fatal(_) => return Vec::new(),
+    };
+    tokens
+        .into_iter()
+        .filter_map(|token| {
+            let TokenType::SingleLineComment { comment } = token.token_type() else {
+                return None;
+            };
+            let canonical = canonical_annotation(comment)?;
+            (canonical != comment.as_str()).then(|| TextEdit {
+                range: to_lsp_range(&Span::from(token.clone())),
+                new_text: format!("--{canonical}"),
+            })
+        })
+        .collect()
+}
+
+/// canonical form of a comment body following `--`, None => not an annotation
+///   no space before the tag and a single one after it, the operands are kept as written,
+///   spaces may be part of a literal type or a description
+fn canonical_annotation(comment: &str) -> Option<String> {
+    let rest = comment.strip_prefix('-')?.trim_start();
+    let rest = rest.strip_prefix('@')?;
+    if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let (tag, operands) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some(match operands.trim_start() {
+        "" => format!("-@{tag}"),
+        operands => format!("-@{tag} {operands}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use unindent::unindent;
+    /// content with the edits applied, edits don't span lines
+    fn apply(content: &str, edits: &[TextEdit]) -> String {
+        content
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let mut line = line.to_string();
+                for edit in edits.iter().rev() {
+                    if edit.range.start.line as usize == i {
+                        let byte = |character: u32| {
+                            line.char_indices()
+                                .nth(character as usize)
+                                .map_or(line.len(), |(byte, _)| byte)
+                        };
+                        let range =
+                            byte(edit.range.start.character)..byte(edit.range.end.character);
+                        line.replace_range(range, &edit.new_text);
+                    }
+                }
+                line
+            })
+            .collect()
+    }
+    #[test]
+    fn canonicalize_annotation_spacing() {
+        let content = unindent(
+            r#"
+            ---   @class   Point
+            ---@field  x    number
+            ---@field y number
+            --- plain   doc   comment
+            local s = "---  @type   string"
+            local function f(a)
+                ---@type    number
+                local n = a -- trailing   comment
+                return n
+            end
+            "#,
+        );
+        let edits = format_annotations(&content);
+        assert_eq!(edits.len(), 3);
+        assert_eq!(
+            apply(&content, &edits),
+            unindent(
+                r#"
+                ---@class Point
+                ---@field x    number
+                ---@field y number
+                --- plain   doc   comment
+                local s = "---  @type   string"
+                local function f(a)
+                    ---@type number
+                    local n = a -- trailing   comment
+                    return n
+                end
+                "#,
+            )
+        );
+    }
+    #[test]
+    fn operands_keep_their_spacing() {
+        let content =
+            "---  @alias   Mode \"a  b\"\n---@param   x number  the   value\n---@meta\t\n";
+        assert_eq!(
+            apply(content, &format_annotations(content)),
+            "---@alias Mode \"a  b\"\n---@param x number  the   value\n---@meta\n"
+        );
+    }
+    #[test]
+    fn canonical_document_is_unchanged() {
+        let content = "---@type number\nlocal x = 1\n";
+        assert_eq!(format_annotations(content), Vec::new());
+    }
+}
@@ -0,0 +1,222 @@
+use tower_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position};
+use typua_binder::{Symbol, TypeEnv};
+use typua_checker::CheckResult;
+use typua_config::Config;
+use typua_parser::annotation::{AnnotationInfo, AnnotationTag, DocKind};
+use typua_parser::ast::{Block, Stmt, TypeAst};
+use typua_ty::TypeKind;
+
+use crate::analysis::Analysis;
+use crate::index::word_at;
+
+/// type of the variable under the 0-based `position`
+///   syntax not supported yet shows nothing
+pub fn hover(content: &str, position: Position) -> Option<Hover> {
+    Analysis::analyze(content, &Config::default()).hover(content, position)
+}
+
+/// hover of the variable under `position` in the `ast` of `content` checked into `result`
+pub fn hover_checked(
+    ast: &TypeAst,
+    result: &CheckResult,
+    content: &str,
+    position: Position,
+) -> Option<Hover> {
+    // spans are 1-based
+    let (line, character) = (position.line + 1, position.character + 1);
+    let cursor = typua_span::Position::new(line, character);
+    let name = word_at(content, position)?;
+    let annotates = declaration_annotates(&ast.block, name, &cursor).unwrap_or_default();
+    // the name of a local declaration has the type of its value
+    if let Some(local) = result.type_at(line, character) {
+        return Some(type_hover(name, &local.ty, annotates));
+    }
+    let ty = result
+        .scope_at(&cursor)?
+        .get(&Symbol::new(name.to_string()))?;
+    Some(type_hover(name, &ty, annotates))
+}
+
+/// hover of the variable under `position` whose type is in `env`, no parsing or checking
+pub fn hover_in_scope(content: &str, position: Position, env: &TypeEnv) -> Option<Hover> {
+    let name = word_at(content, position)?;
+    let ty = env.get(&Symbol::new(name.to_string()))?;
+    Some(type_hover(name, &ty, &[]))
+}
+
+/// annotations of the last declaration of the local `name` before `cursor`
+///   only blocks containing the cursor are searched, locals of the others are out of scope
+fn declaration_annotates<'a>(
+    block: &'a Block,
+    name: &str,
+    cursor: &typua_span::Position,
+) -> Option<&'a [AnnotationInfo]> {
+    let mut found = None;
+    for stmt in block.stmts.iter() {
+        let span = stmt.span();
+        if span.start > *cursor {
+            break;
+        }
+        let blocks: Vec<&Block> = match stmt {
+            Stmt::LocalAssign(local_assign) => {
+                if local_assign.vars.iter().any(|var| var.name == name) {
+                    found = Some(local_assign.annotates.as_slice());
+                }
+                Vec::new()
+            }
+            Stmt::LocalFunction(local_function) => {
+                if local_function.name.name == name {
+                    found = Some(local_function.annotates.as_slice());
+                }
+                vec![&local_function.body]
+            }
+            Stmt::FunctionDeclaration(function) => vec![&function.body],
+            Stmt::If(if_stmt) => if_stmt
+                .branches
+                .iter()
+                .map(|branch| &branch.block)
+                .chain(if_stmt.else_block.iter())
+                .collect(),
+            Stmt::While(while_stmt) => vec![&while_stmt.block],
+            Stmt::Repeat(repeat) => vec![&repeat.block],
+            _ => Vec::new(),
+        };
+        if !span.contains(cursor) {
+            continue;
+        }
+        for block in blocks {
+            if let Some(annotates) = declaration_annotates(block, name, cursor) {
+                found = Some(annotates);
+            }
+        }
+    }
+    found
+}
+
+/// type of the variable, then the documentation annotations of its declaration
+///   descriptions of params and fields come first, a multi-line one keeps its lines
+fn type_hover(name: &str, ty: &TypeKind, annotates: &[AnnotationInfo]) -> Hover {
+    let mut value = format!("```lua\n{}: {}\n```", name, ty);
+    let docs: Vec<String> = annotates
+        .iter()
+        .filter_map(|ann| match &ann.tag {
+            AnnotationTag::Param {
+                name,
+                description: Some(description),
+                ..
+            } => Some(format!("@param `{name}` — {description}")),
+            AnnotationTag::Field {
+                name,
+                description: Some(description),
+                ..
+            } => Some(format!("@field `{name}` — {description}")),
+            AnnotationTag::Doc {
+                kind: DocKind::See,
+                text,
+            } => Some(format!("@see {text}")),
+            AnnotationTag::Doc {
+                kind: DocKind::Since,
+                text,
+            } => Some(format!("@since {text}")),
+            _ => None,
+        })
+        .collect();
+    if !docs.is_empty() {
+        value.push_str("\n\n---\n\n");
+        value.push_str(&docs.join("\n\n"));
+    }
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    fn markdown(hover: Option<Hover>) -> Option<String> {
+        match hover?.contents {
+            HoverContents::Markup(markup) => Some(markup.value),
+            _ => None,
+        }
+    }
+    #[test]
+    fn hover_crafted_scope() {
+        let mut env = TypeEnv::new();
+        let _ = env.insert(
+            &Symbol::new("count".to_string()),
+            &TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]),
+        );
+        let content = "print(count)\n";
+        assert_eq!(
+            markdown(hover_in_scope(content, Position::new(0, 8), &env)),
+            Some("```lua\ncount: number|nil\n```".to_string())
+        );
+        // `print` is not in the scope
+        assert_eq!(hover_in_scope(content, Position::new(0, 2), &env), None);
+    }
+    #[test]
+    fn hover_document() {
+        let content = "---@type string\nlocal name = \"typua\"\nlocal copy = name\n";
+        assert_eq!(
+            markdown(hover(content, Position::new(2, 14))),
+            Some("```lua\nname: string\n```".to_string())
+        );
+        // inferred from the value at the declaration
+        assert_eq!(
+            markdown(hover(content, Position::new(2, 8))),
+            Some("```lua\ncopy: string\n```".to_string())
+        );
+    }
+    #[test]
+    fn hover_named_returns() {
+        let content = "---@param x number\n---@return number result\n---@return string? err\nlocal function parse(x)\n    return x, nil\nend\nlocal r = parse(1)\n";
+        assert_eq!(
+            markdown(hover(content, Position::new(6, 12))),
+            Some(
+                "```lua\nparse: fun(x: number): (result: number, err: string|nil)\n```".to_string()
+            )
+        );
+    }
+    #[test]
+    fn hover_descriptions() {
+        let content = "---@param name string the person\n--- to greet\n--- by name\n---@param loud boolean\nlocal function greet(name, loud)\nend\ngreet(\"typua\", true)\n---@class Point\n---@field x number horizontal\n--- from the left\nlocal Point = {}\nprint(Point)\n";
+        assert_eq!(
+            markdown(hover(content, Position::new(6, 1))),
+            Some(
+                "```lua\ngreet: fun(name: string, loud: boolean)\n```\n\n---\n\n@param `name` — the person\nto greet\nby name"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            markdown(hover(content, Position::new(11, 8))),
+            Some(
+                "```lua\nPoint: Point\n```\n\n---\n\n@field `x` — horizontal\nfrom the left"
+                    .to_string()
+            )
+        );
+    }
+    #[test]
+    fn hover_see_and_since() {
+        let content = "---@see other\n---@since 0.2\nlocal function greet()\nend\ngreet()\nlocal function other()\n    local greet = 1\n    return greet\nend\n";
+        let expected = "```lua\ngreet: fun()\n```\n\n---\n\n@see other\n\n@since 0.2";
+        assert_eq!(
+            markdown(hover(content, Position::new(4, 1))),
+            Some(expected.to_string())
+        );
+        // on the declaration too
+        assert_eq!(
+            markdown(hover(content, Position::new(2, 16))),
+            Some(expected.to_string())
+        );
+        // the inner local shadows the documented function
+        assert_eq!(
+            markdown(hover(content, Position::new(7, 12))),
+            Some("```lua\ngreet: any\n```".to_string())
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::{FileChangeType, FileEvent, Location, Position, Url};
+use typua_checker::catch_panic;
+use typua_config::{Config, LuaVersion};
+use typua_parser::annotation::{AnnotationTag, module_name};
+use typua_parser::ast::Stmt;
+use typua_parser::parse;
+use typua_span::Span;
+use typua_vfs::collect_source_files;
+
+use crate::diagnostics::to_lsp_range;
+
+/// where a workspace symbol is declared
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolLocation {
+    pub path: PathBuf,
+    pub span: Span,
+}
+
+impl SymbolLocation {
+    pub fn to_lsp_location(&self) -> Option<Location> {
+        let uri = Url::from_file_path(&self.path).ok()?;
+        Some(Location::new(uri, to_lsp_range(&self.span)))
+    }
+}
+
+/// name => declaration of `---@class`, `---@alias` and annotated top-level functions
+/// across workspace files
+///   classes and aliases are located at their annotation, functions at their name
+///   `function M.f()` and `function M:m()` are named `M.f` and `M:m`
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    symbols: HashMap<String, SymbolLocation>,
+    /// `---@module "name"` => file declaring it
+    modules: HashMap<String, PathBuf>,
+    /// version files are parsed with
+    version: LuaVersion,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// index every lua file under `root` as `config` selects them, unreadable files are skipped
+    pub fn build(root: &Path, config: &Config) -> Self {
+        let mut index = Self {
+            version: config.runtime.version.unwrap_or_default(),
+            ..Self::new()
+        };
+        for path in collect_source_files(root, &config.workspace) {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                index.index_file(&path, &content);
+            }
+        }
+        index
+    }
+    pub fn get(&self, name: &str) -> Option<&SymbolLocation> {
+        self.symbols.get(name)
+    }
+    /// file `require(name)` loads, found by `---@module` regardless of the path
+    pub fn resolve_module(&self, name: &str) -> Option<&Path> {
+        self.modules.get(name).map(PathBuf::as_path)
+    }
+    /// re-read files changed outside the editor
+    pub fn apply_file_events(&mut self, events: &[FileEvent]) {
+        for event in events.iter() {
+            let Ok(path) = event.uri.to_file_path() else {
+                continue;
+            };
+            match std::fs::read_to_string(&path) {
+                Ok(content) if event.typ != FileChangeType::DELETED => {
+                    self.index_file(&path, &content)
+                }
+                _ => self.remove_file(&path),
+            }
+        }
+    }
+    pub fn remove_file(&mut self, path: &Path) {
+        self.symbols.retain(|_, loc| loc.path != path);
+        self.modules.retain(|_, module_path| module_path != path);
+    }
+    /// replace symbols declared in `path` with the ones in `content`
+    pub fn index_file(&mut self, path: &Path, content: &str) {
+        self.remove_file(path);
+        if let Some(name) = module_name(content) {
+            self.modules.insert(name, path.to_path_buf());
+        }
+        // parser panics on syntax not supported yet, such files declare nothing
+        let Ok((ast, _errors)) = catch_panic(|| parse(content, self.version)) else {
+            return;
+        };
+        for stmt in ast.block.stmts.iter() {
+            match stmt {
+                Stmt::LocalAssign(local_assign) => {
+                    for ann in local_assign.annotates.iter() {
+                        if let AnnotationTag::Class { name, .. }
+                        | AnnotationTag::Alias { name, .. } = &ann.tag
+                        {
+                            self.insert(name, path, ann.span.clone());
+                        }
+                    }
+                }
+                Stmt::LocalFunction(local_function) if !local_function.annotates.is_empty() => {
+                    self.insert(
+                        &local_function.name.name,
+                        path,
+                        local_function.name.span.clone(),
+                    );
+                }
+                Stmt::FunctionDeclaration(function) if !function.annotates.is_empty() => {
+                    let mut name = function.name.name.clone();
+                    for field in function.fields.iter() {
+                        name = format!("{name}.{}", field.name);
+                    }
+                    let last = match &function.method {
+                        Some(method) => {
+                            name = format!("{name}:{}", method.name);
+                            method
+                        }
+                        None => function.fields.last().unwrap_or(&function.name),
+                    };
+                    self.insert(&name, path, last.span.clone());
+                }
+                _ => (),
+            }
+        }
+    }
+    fn insert(&mut self, name: &str, path: &Path, span: Span) {
+        self.symbols.insert(
+            name.to_string(),
+            SymbolLocation {
+                path: path.to_path_buf(),
+                span,
+            },
+        );
+    }
+    /// declaration of the identifier under `position` in `content`
+    ///   on the string of `require("name")`, the start of the module file
+    ///   a dotted name, `---@see M.f`, is looked up as a whole before the identifier
+    pub fn definition(&self, content: &str, position: Position) -> Option<Location> {
+        if let Some(module) = required_module_at(content, position) {
+            let uri = Url::from_file_path(self.resolve_module(module)?).ok()?;
+            return Some(Location::new(uri, Default::default()));
+        }
+        let symbol = path_at(content, position)
+            .and_then(|path| self.get(path))
+            .or_else(|| self.get(word_at(content, position)?))?;
+        symbol.to_lsp_location()
+    }
+}
+
+/// module name of `require("name")` or `require "name"` whose string contains the 0-based
+/// `position`
+fn required_module_at(content: &str, position: Position) -> Option<&str> {
+    let line = content.lines().nth(position.line as usize)?;
+    let cursor = byte_offset(line, position.character);
+    let open = line[..cursor].rfind(['"', '\''])?;
+    let quote = line[open..].chars().next()?;
+    let close = open + 1 + line[open + 1..].find(quote)?;
+    let callee = line[..open].trim_end();
+    let callee = callee.strip_suffix('(').unwrap_or(callee).trim_end();
+    (cursor <= close && callee.ends_with("require")).then(|| &line[open + 1..close])
+}
+
+/// identifier containing the 0-based `position`, annotation comments included
+pub fn word_at(content: &str, position: Position) -> Option<&str> {
+    span_at(content, position, |c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// identifiers joined by `.` or `:` up to the one containing the 0-based `position`
+///   M.f:m with the cursor on f => M.f
+fn path_at(content: &str, position: Position) -> Option<&str> {
+    span_at(content, position, |c| {
+        c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':')
+    })
+}
+
+/// `is_prefix` characters before the 0-based `position` and identifier characters after it
+fn span_at(content: &str, position: Position, is_prefix: impl Fn(char) -> bool) -> Option<&str> {
+    let line = content.lines().nth(position.line as usize)?;
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let cursor = byte_offset(line, position.character);
+    let start = line[..cursor]
+        .char_indices()
+        .rfind(|(_, c)| !is_prefix(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = line[cursor..]
+        .find(|c: char| !is_ident(c))
+        .map_or(line.len(), |i| cursor + i);
+    if start < end {
+        Some(&line[start..end])
+    } else {
+        None
+    }
+}
+
+/// byte offset in `line` of the 0-based `character`, which lsp counts in UTF-16 code units
+///   the end of the line if `character` is past it
+pub fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (byte, c) in line.char_indices() {
+        if units >= character as usize {
+            return byte;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+    use tower_lsp::lsp_types::Range;
+    #[test]
+    fn class_definition_in_other_file() {
+        let dir = tempdir().unwrap();
+        let decl = dir.path().join("person.lua");
+        std::fs::write(&decl, "---@class Person\nlocal Person = {}\n").unwrap();
+        let usage = dir.path().join("main.lua");
+        let usage_content = "---@param p Person\nlocal function greet(p)\nend\n";
+        std::fs::write(&usage, usage_content).unwrap();
+
+        let index = SymbolIndex::build(dir.path(), &Config::default());
+        // cursor on `Person` in `---@param p Person`
+        let location = index.definition(usage_content, Position::new(0, 14));
+        assert_eq!(
+            location,
+            Some(Location::new(
+                Url::from_file_path(&decl).unwrap(),
+                Range::new(Position::new(0, 0), Position::new(0, 16)),
+            ))
+        );
+        // unknown identifier
+        assert_eq!(index.definition(usage_content, Position::new(0, 11)), None);
+    }
+    #[test]
+    fn word_after_non_ascii_text() {
+        // 日本語 is 3 UTF-16 units in 9 bytes, 🎉 is 2 units in 4 bytes
+        let content = "-- 日本語 🎉 value\n";
+        assert_eq!(word_at(content, Position::new(0, 11)), Some("value"));
+        assert_eq!(word_at(content, Position::new(0, 15)), Some("value"));
+        assert_eq!(word_at(content, Position::new(0, 5)), None);
+        assert_eq!(byte_offset("-- 日本語 🎉 value", 10), 18);
+    }
+    #[test]
+    fn see_function_definition() {
+        let dir = tempdir().unwrap();
+        let decl = dir.path().join("greet.lua");
+        std::fs::write(
+            &decl,
+            "local M = {}\n---@param name string\nfunction M.greet(name)\nend\n---@param x number\nlocal function double(x)\nend\n",
+        )
+        .unwrap();
+        let usage_content = "---@see M.greet\n---@see double\nlocal x = 1\n";
+        let index = SymbolIndex::build(dir.path(), &Config::default());
+        let uri = Url::from_file_path(&decl).unwrap();
+        // cursor on `greet` in `---@see M.greet`
+        assert_eq!(
+            index.definition(usage_content, Position::new(0, 12)),
+            Some(Location::new(
+                uri.clone(),
+                Range::new(Position::new(2, 11), Position::new(2, 16)),
+            ))
+        );
+        // cursor on `M` in `---@see M.greet`, M is not indexed
+        assert_eq!(index.definition(usage_content, Position::new(0, 8)), None);
+        // cursor on `double` in `---@see double`
+        assert_eq!(
+            index.definition(usage_content, Position::new(1, 10)),
+            Some(Location::new(
+                uri,
+                Range::new(Position::new(5, 15), Position::new(5, 21)),
+            ))
+        );
+    }
+    #[test]
+    fn require_tagged_module() {
+        let dir = tempdir().unwrap();
+        let module = dir.path().join("lib").join("settings.lua");
+        std::fs::create_dir(dir.path().join("lib")).unwrap();
+        std::fs::write(&module, "---@module \"config\"\nreturn { debug = true }\n").unwrap();
+        let usage_content = "local config = require(\"config\")\n";
+        std::fs::write(dir.path().join("main.lua"), usage_content).unwrap();
+
+        let index = SymbolIndex::build(dir.path(), &Config::default());
+        assert_eq!(index.resolve_module("config"), Some(module.as_path()));
+        assert_eq!(index.resolve_module("lib.settings"), None);
+        // cursor on `config` in `require("config")`
+        assert_eq!(
+            index.definition(usage_content, Position::new(0, 26)),
+            Some(Location::new(
+                Url::from_file_path(&module).unwrap(),
+                Range::default(),
+            ))
+        );
+        // cursor on the local name
+        assert_eq!(index.definition(usage_content, Position::new(0, 8)), None);
+        // positions after non-ascii text are in UTF-16 units
+        let usage_content = "local s, config = \"日本語\", require(\"config\")\n";
+        assert_eq!(
+            index.definition(usage_content, Position::new(0, 35)),
+            Some(Location::new(
+                Url::from_file_path(&module).unwrap(),
+                Range::default(),
+            ))
+        );
+        assert_eq!(index.definition(usage_content, Position::new(0, 20)), None);
+    }
+    #[test]
+    fn refresh_changed_files() {
+        let dir = tempdir().unwrap();
+        let decl = dir.path().join("person.lua");
+        std::fs::write(&decl, "---@class Person\nlocal Person = {}\n").unwrap();
+        let mut index = SymbolIndex::build(dir.path(), &Config::default());
+        assert!(index.get("Person").is_some());
+
+        // renamed outside the editor
+        std::fs::write(&decl, "---@class Human\nlocal Human = {}\n").unwrap();
+        index.apply_file_events(&[FileEvent::new(
+            Url::from_file_path(&decl).unwrap(),
+            FileChangeType::CHANGED,
+        )]);
+        assert_eq!(index.get("Person"), None);
+        assert_eq!(
+            index.get("Human").map(|loc| loc.path.clone()),
+            Some(decl.clone())
+        );
+
+        std::fs::remove_file(&decl).unwrap();
+        index.apply_file_events(&[FileEvent::new(
+            Url::from_file_path(&decl).unwrap(),
+            FileChangeType::DELETED,
+        )]);
+        assert_eq!(index.get("Human"), None);
+    }
+}
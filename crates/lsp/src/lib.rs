@@ -1,4 +1,11 @@
+mod analysis;
 mod backend;
+pub mod completion;
+mod diagnostics;
+mod formatting;
+pub mod hover;
+mod index;
+mod signature;
 use crate::backend::Backend;
 use std::fs::File;
 use std::sync::Arc;
@@ -10,7 +17,7 @@ use typua_ty::error::TypuaError;
 
 async fn run_lsp_service() {
     let (stdin, stdout) = (tokio::io::stdin(), tokio::io::stdout());
-    let (service, socket) = LspService::new(|client| Backend { client });
+    let (service, socket) = LspService::new(Backend::new);
     Server::new(stdin, stdout, socket).serve(service).await;
 }
 
@@ -63,8 +70,8 @@ pub fn handle_lsp_service() {
     {
         Ok(runtime) => runtime,
         Err(e) => {
-            eprintln!("Failed to start runtime: {e}");
-            return;
+            eprintln!("error: {e}");
+            std::process::exit(e.exit_code().into());
         }
     };
     runtime.block_on(run_lsp_service())
@@ -0,0 +1,226 @@
+use tower_lsp::lsp_types::{
+    ParameterInformation, ParameterLabel, Position, SignatureHelp, SignatureInformation,
+};
+use typua_binder::Symbol;
+use typua_checker::CheckResult;
+use typua_ty::TypeKind;
+
+use crate::index::byte_offset;
+
+/// signature of the function called around the 0-based `position`, the argument under it active
+///   the call is the last `(` of the line not closed before the cursor, strings are skipped
+///   only calls of variables in scope are resolved, not those of fields or methods
+pub fn signature_help(
+    result: &CheckResult,
+    content: &str,
+    position: Position,
+) -> Option<SignatureHelp> {
+    let line = content.lines().nth(position.line as usize)?;
+    let before = &line[..byte_offset(line, position.character)];
+    let (open, commas) = open_call(before)?;
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let callee_end = before[..open].trim_end();
+    let callee_start = callee_end
+        .char_indices()
+        .rfind(|(_, c)| !is_ident(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    if callee_end[..callee_start].ends_with(['.', ':']) {
+        return None;
+    }
+    let callee = &callee_end[callee_start..];
+    // spans are 1-based
+    let cursor = typua_span::Position::new(position.line + 1, position.character + 1);
+    let ty = result
+        .scope_at(&cursor)?
+        .get(&Symbol::new(callee.to_string()))?;
+    let TypeKind::Function {
+        params,
+        param_names,
+        ..
+    } = &ty
+    else {
+        return None;
+    };
+    let signature = signature_information(callee, &ty, params, param_names, commas);
+    let active_parameter = signature.active_parameter;
+    Some(SignatureHelp {
+        signatures: vec![signature],
+        active_signature: Some(0),
+        active_parameter,
+    })
+}
+
+/// byte offset of the `(` of the call enclosing the end of `before`, and the commas after it
+///   None in a table constructor or a comment
+fn open_call(before: &str) -> Option<(usize, u32)> {
+    // brackets not closed yet, with the commas directly inside them
+    let mut open: Vec<(usize, char, u32)> = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in before.char_indices() {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => (),
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '{' => open.push((i, c, 0)),
+            ')' | '}' => {
+                open.pop();
+            }
+            ',' => {
+                if let Some((_, _, commas)) = open.last_mut() {
+                    *commas += 1;
+                }
+            }
+            '-' if before[i..].starts_with("--") => return None,
+            _ => (),
+        }
+    }
+    match open.last() {
+        Some((i, '(', commas)) => Some((*i, *commas)),
+        _ => None,
+    }
+}
+
+/// `name(x: number): (ok: boolean)` as the type is displayed in hover, each param located in it
+///   the `commas`-th param is active, the trailing `...` takes the rest
+fn signature_information(
+    name: &str,
+    ty: &TypeKind,
+    params: &[TypeKind],
+    param_names: &[String],
+    commas: u32,
+) -> SignatureInformation {
+    let label = format!("{name}{}", ty.to_string().trim_start_matches("fun"));
+    // offsets are in UTF-16 code units
+    let utf16_len = |s: &str| s.encode_utf16().count() as u32;
+    let mut searched = label.find('(').unwrap_or(0);
+    let parameters = params
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| {
+            let param = match param_names.get(i) {
+                Some(name) => format!("{name}: {ty}"),
+                None => ty.to_string(),
+            };
+            let start = searched + label[searched..].find(&param).unwrap_or(0);
+            searched = start + param.len();
+            ParameterInformation {
+                label: ParameterLabel::LabelOffsets([
+                    utf16_len(&label[..start]),
+                    utf16_len(&label[..searched]),
+                ]),
+                documentation: None,
+            }
+        })
+        .collect::<Vec<_>>();
+    let last = parameters.len().saturating_sub(1) as u32;
+    let active = match param_names.last() {
+        Some(name) if name == "..." => commas.min(last),
+        _ => commas,
+    };
+    SignatureInformation {
+        label,
+        documentation: None,
+        parameters: Some(parameters),
+        active_parameter: Some(active),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::Analysis;
+    use pretty_assertions::assert_eq;
+    use typua_config::Config;
+    use unindent::unindent;
+    /// label of the signature, the labels of its params and the active one
+    fn signature(content: &str, position: Position) -> Option<(String, Vec<String>, u32)> {
+        let help =
+            Analysis::analyze(content, &Config::default()).signature_help(content, position)?;
+        let signature = help.signatures.into_iter().next()?;
+        let label: Vec<u16> = signature.label.encode_utf16().collect();
+        let params = signature
+            .parameters
+            .unwrap_or_default()
+            .into_iter()
+            .map(|param| match param.label {
+                ParameterLabel::LabelOffsets([start, end]) => {
+                    String::from_utf16(&label[start as usize..end as usize]).unwrap()
+                }
+                ParameterLabel::Simple(label) => label,
+            })
+            .collect();
+        Some((signature.label, params, help.active_parameter?))
+    }
+    #[test]
+    fn signature_with_named_returns() {
+        let content = unindent(
+            r#"
+            ---@param path string
+            ---@param mode? string
+            ---@return string? content
+            ---@return string? err
+            local function read(path, mode)
+                return nil, "unreadable"
+            end
+            local content, err = read("a.txt", "r")
+            "#,
+        );
+        let label = "read(path: string, mode: string|nil): (content: string|nil, err: string|nil)";
+        // after `read(`
+        assert_eq!(
+            signature(&content, Position::new(7, 26)),
+            Some((
+                label.to_string(),
+                vec!["path: string".to_string(), "mode: string|nil".to_string()],
+                0
+            ))
+        );
+        // after `, `
+        assert_eq!(
+            signature(&content, Position::new(7, 36)).map(|(_, _, active)| active),
+            Some(1)
+        );
+        // outside the call
+        assert_eq!(signature(&content, Position::new(7, 10)), None);
+        // while the call is typed, the document has a syntax error
+        let typing = content.replace("read(\"a.txt\", \"r\")", "read(\"a.txt\", ");
+        assert_eq!(
+            signature(&typing, Position::new(7, 35)).map(|(_, _, active)| active),
+            Some(1)
+        );
+    }
+    #[test]
+    fn signature_of_nested_and_vararg_calls() {
+        let content = unindent(
+            r#"
+            ---@param fmt string
+            local function log(fmt, ...) end
+            ---@param n number
+            ---@return number
+            local function double(n) return n * 2 end
+            log("%d, (%d)", double(1), 2, 3)
+            "#,
+        );
+        // inside `double(1)`
+        assert_eq!(
+            signature(&content, Position::new(5, 23)).map(|(label, _, active)| (label, active)),
+            Some(("double(n: number): number".to_string(), 0))
+        );
+        // the arguments after the closed call are taken by `...`, the string is skipped
+        assert_eq!(
+            signature(&content, Position::new(5, 30)).map(|(label, _, active)| (label, active)),
+            Some(("log(fmt: string, ...: unknown)".to_string(), 1))
+        );
+        // not in a call
+        assert_eq!(open_call("local t = { f(1), "), None);
+        assert_eq!(open_call("f(1) -- g("), None);
+    }
+}
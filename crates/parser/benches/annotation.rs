@@ -0,0 +1,81 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use typua_config::LuaVersion;
+use typua_parser::annotation::parse_annotation;
+use typua_parser::parse;
+
+/// system allocator counting allocations, timings alone are too noisy to compare passes
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// allocations made by `f`, printed with the benchmark results
+fn count_allocations<T>(name: &str, f: impl Fn() -> T) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let _ = f();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{name}: {allocations} allocations");
+}
+
+/// source where every statement is annotated, `n` classes with a method each
+fn annotated_source(n: usize) -> String {
+    let mut source = String::new();
+    for i in 0..n {
+        source.push_str(&format!(
+            "---@class Point{i}\n\
+             ---@field x number\n\
+             ---@field y number the vertical position\n\
+             local Point{i} = {{}}\n\
+             \n\
+             --- distance from the origin\n\
+             ---@param scale number|nil\n\
+             ---@param unit \"px\"|\"em\" # unit of the result\n\
+             ---@return number\n\
+             function Point{i}:length(scale, unit)\n\
+             \x20   ---@type number\n\
+             \x20   local length = 1\n\
+             \x20   return length\n\
+             end\n\
+             \n"
+        ));
+    }
+    source
+}
+
+fn bench_annotation(c: &mut Criterion) {
+    let source = annotated_source(500);
+    count_allocations("parse annotated file", || parse(&source, LuaVersion::Lua54));
+    c.bench_function("parse annotated file", |b| {
+        b.iter(|| parse(&source, LuaVersion::Lua54))
+    });
+    let comments: String = source.lines().filter(|line| line.starts_with("---")).fold(
+        String::new(),
+        |mut comments, line| {
+            comments.push_str(line);
+            comments.push('\n');
+            comments
+        },
+    );
+    count_allocations("parse annotation comments", || parse_annotation(&comments));
+    c.bench_function("parse annotation comments", |b| {
+        b.iter(|| parse_annotation(&comments))
+    });
+}
+
+criterion_group!(benches, bench_annotation);
+criterion_main!(benches);
@@ -1,3 +1,5 @@
+use std::fmt::Write;
+
 use typua_ty::TypeKind;
 use typua_span::{Position, Span};
 
@@ -5,12 +7,14 @@ use nom::sequence::terminated;
 use nom::{
     IResult, Parser,
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{char, multispace0, multispace1},
-    combinator::map,
+    bytes::complete::{is_not, tag, take},
+    character::complete::{
+        alpha1, alphanumeric1, char, multispace0, not_line_ending, space0, space1,
+    },
+    combinator::{eof, map, opt, recognize},
     error::ParseError,
-    multi::separated_list1,
-    sequence::{delimited, separated_pair},
+    multi::{many0_count, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair},
 };
 use nom_locate::LocatedSpan;
 
@@ -25,36 +29,647 @@ pub struct AnnotationInfo {
 #[derive(Debug, Clone, PartialEq)]
 pub enum AnnotationTag {
     Type(TypeKind),
-    Alias,
-    As,
-    Class,
+    /// ---@type type name, binds to the local of the name
+    NamedType {
+        ty: TypeKind,
+        name: String,
+    },
+    /// ---@alias Name type
+    Alias {
+        name: String,
+        ty: TypeKind,
+    },
+    /// --[[@as T]] following a value, the value is taken as T
+    As(TypeKind),
+    /// ---@class Name
+    /// ---@class (exact) Name, reading undeclared fields is warned
+    Class {
+        name: String,
+        exact: bool,
+    },
+    /// ---@field name type description
+    Field {
+        name: String,
+        ty: TypeKind,
+        /// text after the type and the bare `---` lines following it
+        description: Option<String>,
+    },
+    /// ---@param name type description
+    Param {
+        name: String,
+        ty: TypeKind,
+        /// text after the type and the bare `---` lines following it
+        description: Option<String>,
+    },
+    /// ---@return type [name]
+    Return {
+        ty: TypeKind,
+        name: Option<String>,
+    },
+    /// ---@cast name [+|-]type, changes the type of the variable for the following code
+    Cast {
+        name: String,
+        op: CastOp,
+        ty: TypeKind,
+    },
+    /// ---@meta, the file only declares types
+    Meta,
+    /// ---@async, the function may yield
+    Async,
+    /// ---@module "name", the name `require` finds the file by
+    Module(String),
+    /// annotation which can't be parsed, with the reason
+    Malformed(String),
+    /// documentation only, no effect on typechecking
+    Doc {
+        kind: DocKind,
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CastOp {
+    /// ---@cast x T, x is T
+    Set,
+    /// ---@cast x +T, T is added to x's type
+    Add,
+    /// ---@cast x -T, T is removed from x's type
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocKind {
+    /// ---@see OtherFunc
+    See,
+    /// ---@since 0.2
+    Since,
 }
 
 /// helper function for parsing
+///   tokens are written into one buffer, which is trimmed in place
 pub fn concat_tokens<'a>(tokens: impl Iterator<Item = &'a full_moon::tokenizer::Token>) -> String {
-    let strings: Vec<String> = tokens.map(|t| t.to_string()).collect();
-    strings.concat().trim().to_string()
+    let mut content = String::new();
+    for token in tokens {
+        let _ = write!(content, "{token}");
+    }
+    content.truncate(content.trim_end().len());
+    let leading = content.len() - content.trim_start().len();
+    content.drain(..leading);
+    content
+}
+
+/// annotations in the leading trivia of a statement
+/// spans are positions in the source, not in the concatenated comments
+pub fn parse_leading_annotation<'a>(
+    tokens: impl Iterator<Item = &'a full_moon::tokenizer::Token>,
+) -> Vec<AnnotationInfo> {
+    // leading whitespace is trimmed, so the content starts at the first comment
+    let mut tokens = tokens
+        .skip_while(|t| {
+            matches!(
+                t.token_type(),
+                full_moon::tokenizer::TokenType::Whitespace { .. }
+            )
+        })
+        .peekable();
+    let Some(first) = tokens.peek() else {
+        return Vec::new();
+    };
+    let base = Position::from(first.start_position());
+    let content = concat_tokens(tokens);
+    parse_annotation(&content)
+        .into_iter()
+        .map(|ann| AnnotationInfo {
+            span: Span::new(
+                ann.span.start.offset_from(&base),
+                ann.span.end.offset_from(&base),
+            ),
+            ..ann
+        })
+        .collect()
+}
+
+/// inline annotations written in block comments, `--[[@type integer]]`
+pub fn parse_inline_annotation<'a>(
+    tokens: impl Iterator<Item = &'a full_moon::tokenizer::Token>,
+) -> Vec<AnnotationInfo> {
+    tokens
+        .filter_map(|t| match t.token_type() {
+            full_moon::tokenizer::TokenType::MultiLineComment { comment, .. } => {
+                Some(parse_annotation(&format!("---{}", comment.trim())))
+            }
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// `---@module` in the comments at the top of the file
+///   read from the source, a file starting with `return` has no statement to attach it to
+pub fn module_name(content: &str) -> Option<String> {
+    let header: Vec<&str> = content
+        .lines()
+        .take_while(|line| line.trim().is_empty() || line.trim_start().starts_with("--"))
+        .collect();
+    parse_annotation(&header.join("\n"))
+        .into_iter()
+        .find_map(|ann| match ann.tag {
+            AnnotationTag::Module(name) => Some(name),
+            _ => None,
+        })
 }
 
 /// entry point for annotation parsing
+/// lines which are not annotation are skipped
 pub fn parse_annotation(content: &str) -> Vec<AnnotationInfo> {
-    let span = AnnotationSpan::new(content);
-    match parse_type_annotation(span) {
-        Ok((_, infos)) => infos,
-        Err(_) => Vec::new(),
+    let mut infos: Vec<AnnotationInfo> = Vec::new();
+    let mut described = 0;
+    let mut i = AnnotationSpan::new(content);
+    loop {
+        i = match multispace0::<_, nom::error::Error<_>>.parse(i) {
+            Ok((i, _)) => i,
+            Err(_) => break,
+        };
+        if i.fragment().is_empty() {
+            break;
+        }
+        if let Ok((rest, text)) = parse_continuation(i) {
+            for info in infos[described..].iter_mut() {
+                if let AnnotationTag::Param { description, .. }
+                | AnnotationTag::Field { description, .. } = &mut info.tag
+                {
+                    *description = Some(match description.take() {
+                        Some(prev) => format!("{prev}\n{text}"),
+                        None => text.clone(),
+                    });
+                }
+            }
+            i = rest;
+            continue;
+        }
+        // annotations from the last line, which continuation lines describe
+        described = infos.len();
+        // every annotation starts with `---@`, other comments are skipped without trying them
+        if !i.fragment().starts_with("---@") {
+            match not_line_ending::<_, nom::error::Error<_>>.parse(i) {
+                Ok((rest, _)) => i = rest,
+                Err(_) => break,
+            }
+            continue;
+        }
+        i = match alt((
+            parse_named_type_annotation,
+            parse_type_annotation,
+            parse_param_annotation,
+            parse_return_annotation,
+            parse_class_annotation,
+            parse_field_annotation,
+            parse_alias_annotation,
+            parse_cast_annotation,
+            parse_as_annotation,
+            parse_meta_annotation,
+            parse_async_annotation,
+            parse_module_annotation,
+            parse_doc_annotation,
+        ))
+        .parse(i)
+        {
+            Ok((rest, anns)) => {
+                infos.extend(anns);
+                rest
+            }
+            Err(err) => match not_line_ending::<_, nom::error::Error<_>>.parse(i) {
+                Ok((rest, _)) => {
+                    // an empty union member is an annotation recognized but written wrong, others are comments
+                    if let nom::Err::Failure(nom::error::Error {
+                        code: nom::error::ErrorKind::SeparatedList,
+                        ..
+                    }) = err
+                    {
+                        infos.push(AnnotationInfo {
+                            tag: AnnotationTag::Malformed("empty member in union type".to_string()),
+                            span: Span::new(
+                                Position::new(i.location_line(), i.get_column() as u32),
+                                Position::new(rest.location_line(), rest.get_column() as u32),
+                            ),
+                        });
+                    }
+                    rest
+                }
+                Err(_) => break,
+            },
+        };
+    }
+    infos
+}
+
+/// description following the type, None if the rest of the line is empty
+///   `# text` is the same as `text`
+fn description(rest: &AnnotationSpan) -> Option<String> {
+    let text = rest.fragment().trim();
+    let text = text.strip_prefix('#').unwrap_or(text).trim_start();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// bare `---` line continuing the description of the annotation above
+///   `---@tag` and `----` lines are not continuation
+fn parse_continuation(i: AnnotationSpan) -> IResult<AnnotationSpan, String> {
+    let (i, _) = tag("---").parse(i)?;
+    if i.fragment().starts_with(['@', '-']) {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::Tag,
+        )));
     }
+    let (i, text) = not_line_ending.parse(i)?;
+    Ok((i, text.fragment().trim().to_string()))
+}
+
+/// parsing param annotation
+///   ---@param x number
+///   ---@param x? number  => number|nil
+///   ---@param a, b number => both a and b are number
+fn parse_param_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@param").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    // names end at the first space not preceded by a comma, so commas in the type are kept
+    let (i, names) =
+        separated_list1(pair(char(','), space0), pair(parse_name, opt(char('?')))).parse(i)?;
+    let (i, _) = space1.parse(i)?;
+    // `ws` in type parsers swallows newlines, so the type is parsed within its own line
+    let (end_span, line) = not_line_ending.parse(i)?;
+    let (rest, ann) = parse_type.parse(line)?;
+    let ty = type_of(ann);
+    let description = description(&rest);
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        names
+            .into_iter()
+            .map(|(name, optional)| AnnotationInfo {
+                tag: AnnotationTag::Param {
+                    name: name.fragment().to_string(),
+                    ty: match optional {
+                        Some(_) => TypeKind::optional(ty.clone()),
+                        None => ty.clone(),
+                    },
+                    description: description.clone(),
+                },
+                span: Span {
+                    start: satrt_position.clone(),
+                    end: end_position.clone(),
+                },
+            })
+            .collect(),
+    ))
+}
+
+/// parsing return annotation
+///   ---@return number
+///   ---@return number result
+fn parse_return_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@return").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (end_span, line) = not_line_ending.parse(i)?;
+    let (line, ann) = parse_type.parse(line)?;
+    let ty = type_of(ann);
+    // `string?` leaves the space before the name unparsed
+    let (_, name) = opt(ws(parse_name)).parse(line)?;
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Return {
+                ty,
+                name: name.map(|n| n.fragment().to_string()),
+            },
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing cast annotation
+///   ---@cast x string
+///   ---@cast x -nil
+///   ---@cast x +string
+fn parse_cast_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@cast").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (i, name) = parse_name.parse(i)?;
+    let (i, _) = space1.parse(i)?;
+    let (end_span, line) = not_line_ending.parse(i)?;
+    let (line, op) = opt(alt((char('+'), char('-')))).parse(line)?;
+    let (_, ann) = parse_type.parse(line)?;
+    let ty = type_of(ann);
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Cast {
+                name: name.fragment().to_string(),
+                op: match op {
+                    Some('+') => CastOp::Add,
+                    Some(_) => CastOp::Remove,
+                    None => CastOp::Set,
+                },
+                ty,
+            },
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing class annotation
+///   ---@class Person
+///   ---@class (exact) Person
+fn parse_class_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@class").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (i, exact) = opt(terminated(tag("(exact)"), space1)).parse(i)?;
+    let (i, name) = parse_name.parse(i)?;
+    let (end_span, _) = not_line_ending.parse(i)?;
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Class {
+                name: name.fragment().to_string(),
+                exact: exact.is_some(),
+            },
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing field annotation
+///   ---@field name string
+fn parse_field_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@field").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (i, name) = recognize(separated_list1(char('.'), parse_name)).parse(i)?;
+    let (i, _) = space1.parse(i)?;
+    let (end_span, line) = not_line_ending.parse(i)?;
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    // `a.b` would be a field named literally so, which no code can read
+    if let Some((owner, _)) = name.fragment().split_once('.') {
+        return Ok((
+            end_span,
+            vec![AnnotationInfo {
+                tag: AnnotationTag::Malformed(format!(
+                    "field name `{}` is a path, declare the field in the class of `{}`",
+                    name.fragment(),
+                    owner
+                )),
+                span: Span {
+                    start: satrt_position,
+                    end: end_position,
+                },
+            }],
+        ));
+    }
+    let (rest, ann) = parse_type.parse(line)?;
+    let ty = type_of(ann);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Field {
+                name: name.fragment().to_string(),
+                ty,
+                description: description(&rest),
+            },
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing alias annotation
+///   ---@alias Id number|string
+fn parse_alias_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@alias").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (i, name) = parse_name.parse(i)?;
+    let (i, _) = space1.parse(i)?;
+    let (end_span, line) = not_line_ending.parse(i)?;
+    let (_, ann) = parse_type.parse(line)?;
+    let ty = type_of(ann);
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Alias {
+                name: name.fragment().to_string(),
+                ty,
+            },
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing meta annotation
+///   ---@meta
+///   ---@meta name
+fn parse_meta_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@meta").parse(start_span)?;
+    let (end_span, _) = not_line_ending.parse(i)?;
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Meta,
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing async annotation
+///   ---@async
+fn parse_async_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@async").parse(start_span)?;
+    let (end_span, _) = not_line_ending.parse(i)?;
+    let start_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Async,
+            span: Span {
+                start: start_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing module annotation
+///   ---@module "foo.bar"
+fn parse_module_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@module").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (i, name) = alt((
+        delimited(char('"'), is_not("\""), char('"')),
+        delimited(char('\''), is_not("'"), char('\'')),
+    ))
+    .parse(i)?;
+    let (end_span, _) = not_line_ending.parse(i)?;
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Module(name.fragment().to_string()),
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// lua identifier
+fn parse_name(i: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationSpan> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0_count(alt((alphanumeric1, tag("_")))),
+    ))
+    .parse(i)
+}
+
+/// parsing documentation annotation, ---@see and ---@since
+fn parse_doc_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@").parse(start_span)?;
+    let (i, kind) = alt((
+        map(tag("see"), |_| DocKind::See),
+        map(tag("since"), |_| DocKind::Since),
+    ))
+    .parse(i)?;
+    let (i, _) = space1.parse(i)?;
+    let (end_span, text) = not_line_ending.parse(i)?;
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::Doc {
+                kind,
+                text: text.fragment().trim().to_string(),
+            },
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing as annotation, written inline after a value
+///   v --[[@as Config]]
+fn parse_as_annotation(start_span: AnnotationSpan) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@as").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (end_span, line) = not_line_ending.parse(i)?;
+    let (_, ann) = parse_type.parse(line)?;
+    let ty = type_of(ann);
+    let start_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::As(ty),
+            span: Span {
+                start: start_position,
+                end: end_position,
+            },
+        }],
+    ))
+}
+
+/// parsing type annotation with the name of the local
+///   ---@type number x
+fn parse_named_type_annotation(
+    start_span: AnnotationSpan,
+) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
+    let (i, _) = tag("---@type").parse(start_span)?;
+    let (i, _) = space1.parse(i)?;
+    let (end_span, line) = not_line_ending.parse(i)?;
+    let (line, ann) = parse_type.parse(line)?;
+    let start_position = ann.span.start.clone();
+    let ty = type_of(ann);
+    let (_, name) = terminated(parse_name, pair(space0, eof)).parse(line)?;
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    Ok((
+        end_span,
+        vec![AnnotationInfo {
+            tag: AnnotationTag::NamedType {
+                ty,
+                name: name.fragment().to_string(),
+            },
+            span: Span {
+                start: start_position,
+                end: end_position,
+            },
+        }],
+    ))
 }
 
 /// parsing type annotation
 fn parse_type_annotation(i: AnnotationSpan) -> IResult<AnnotationSpan, Vec<AnnotationInfo>> {
     let (i, _) = tag("---@type").parse(i)?;
-    let (i, _) = multispace1.parse(i)?;
-    separated_list1(ws(tag(",")), parse_type).parse(i)
+    let (i, _) = space1.parse(i)?;
+    // `ws` in type parsers swallows newlines, so the types are parsed within their own line
+    let (rest, line) = not_line_ending.parse(i)?;
+    let (_, anns) = separated_list1(ws(tag(",")), parse_type).parse(line)?;
+    Ok((rest, anns))
 }
 
-/// parsing basictype number, string, boolean, any, nil
+/// parsing basictype number, string, boolean, any, nil, table and class names
 fn parse_type(i: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
     alt((
+        parse_function,
         parse_dict,
         parse_tabletype,
         parse_optional,
@@ -65,15 +680,132 @@ fn parse_type(i: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
     .parse(i)
 }
 
-fn parse_basictype(start_span: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
-    let (end_span, ty) = alt((
-        map(ws(tag("number")), |_| TypeKind::Number),
-        map(ws(tag("boolean")), |_| TypeKind::Boolean),
-        map(ws(tag("string")), |_| TypeKind::String),
-        map(ws(tag("nil")), |_| TypeKind::Nil),
-        map(ws(tag("any")), |_| TypeKind::Any),
+/// the type given by `parse_type` and the type parsers, which tag nothing else
+///   any other tag would be a bug of the parsers, it is any then instead of a panic
+fn type_of(ann: AnnotationInfo) -> TypeKind {
+    match ann.tag {
+        AnnotationTag::Type(ty) => ty,
+        _ => TypeKind::Any,
+    }
+}
+
+/// fun(x: number, y): number, string
+/// fun(): number, ...string
+/// fun<T>(x: T): T[]
+///   params without type are any
+///   trailing `...T` in returns is variadic, `...` alone is `...any`
+///   names of type parameters in params and returns are generic types
+fn parse_function(start_span: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
+    let (i, _) = ws(tag("fun")).parse(start_span)?;
+    let (i, generics) = opt(delimited(
+        char('<'),
+        separated_list1(char(','), ws(parse_name)),
+        char('>'),
     ))
-    .parse(start_span)?;
+    .parse(i)?;
+    let generics: Vec<String> = generics
+        .unwrap_or_default()
+        .iter()
+        .map(|name| name.fragment().to_string())
+        .collect();
+    let param = map(
+        pair(ws(parse_name), opt(preceded(char(':'), parse_type))),
+        |(name, ann)| {
+            (
+                name.fragment().to_string(),
+                ann.map_or(TypeKind::Any, type_of),
+            )
+        },
+    );
+    let (i, params) =
+        delimited(char('('), separated_list0(char(','), param), ws(char(')'))).parse(i)?;
+    let (param_names, params): (Vec<String>, Vec<TypeKind>) = params.into_iter().unzip();
+    let return_item = alt((
+        map(preceded(ws(tag("...")), opt(parse_type)), |ann| {
+            (true, ann.map_or(TypeKind::Any, type_of))
+        }),
+        map(parse_type, |ann| (false, type_of(ann))),
+    ));
+    let (end_span, items) =
+        opt(preceded(char(':'), separated_list1(char(','), return_item))).parse(i)?;
+    let mut returns = Vec::new();
+    let mut vararg = None;
+    let items = items.unwrap_or_default();
+    let count = items.len();
+    for (n, (is_vararg, ty)) in items.into_iter().enumerate() {
+        match (is_vararg, n + 1 == count) {
+            (false, _) => returns.push(ty),
+            (true, true) => vararg = Some(Box::new(ty)),
+            // vararg must be the last one
+            (true, false) => {
+                return Err(nom::Err::Failure(nom::error::Error::new(
+                    start_span,
+                    nom::error::ErrorKind::Verify,
+                )));
+            }
+        }
+    }
+    let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
+    let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
+    let ty = TypeKind::Function {
+        generics: generics.clone(),
+        param_names,
+        params,
+        returns,
+        return_names: Vec::new(),
+        vararg,
+    }
+    .replace(&|ty| match ty {
+        TypeKind::Custom(name) if generics.contains(name) => Some(TypeKind::Generic(name.clone())),
+        _ => None,
+    });
+    Ok((
+        end_span,
+        AnnotationInfo {
+            tag: AnnotationTag::Type(ty),
+            span: Span {
+                start: satrt_position,
+                end: end_position,
+            },
+        },
+    ))
+}
+
+/// string in double or single quotes, `\` makes the next character literal
+///   "a|b" => a|b, 'it\'s' => it's
+fn parse_string_literal(i: AnnotationSpan) -> IResult<AnnotationSpan, String> {
+    let (i, quote) = alt((char('"'), char('\''))).parse(i)?;
+    let mut value = String::new();
+    let mut chars = i.fragment().chars().enumerate();
+    while let Some((n, c)) = chars.next() {
+        match c {
+            '\\' => value.extend(chars.next().map(|(_, escaped)| escaped)),
+            c if c == quote => return take(n + 1).parse(i).map(|(i, _)| (i, value)),
+            c => value.push(c),
+        }
+    }
+    // unterminated
+    Err(nom::Err::Error(nom::error::Error::new(
+        i,
+        nom::error::ErrorKind::Char,
+    )))
+}
+
+fn parse_basictype(start_span: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
+    let string_literal = map(ws(parse_string_literal), TypeKind::StringLiteral);
+    let name = map(ws(parse_name), |name| match *name.fragment() {
+        "number" => TypeKind::Number,
+        "integer" => TypeKind::Integer,
+        "boolean" => TypeKind::Boolean,
+        "true" => TypeKind::BooleanLiteral(true),
+        "false" => TypeKind::BooleanLiteral(false),
+        "string" => TypeKind::String,
+        "nil" => TypeKind::Nil,
+        "any" => TypeKind::Any,
+        "table" => TypeKind::Table,
+        name => TypeKind::Custom(name.to_string()),
+    });
+    let (end_span, ty) = alt((string_literal, name)).parse(start_span)?;
     let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
     let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
     Ok((
@@ -89,17 +821,13 @@ fn parse_basictype(start_span: AnnotationSpan) -> IResult<AnnotationSpan, Annota
 }
 
 fn parse_optional(start_span: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
-    let (end_span, ty) = map(terminated(parse_basictype, tag("?")), |a| match a.tag {
-        AnnotationTag::Type(ty) => ty,
-        _ => unimplemented!(),
-    })
-    .parse(start_span)?;
+    let (end_span, ty) = map(terminated(parse_basictype, tag("?")), type_of).parse(start_span)?;
     let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
     let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
     Ok((
         end_span,
         AnnotationInfo {
-            tag: AnnotationTag::Type(TypeKind::Union(vec![ty, TypeKind::Nil])),
+            tag: AnnotationTag::Type(TypeKind::optional(ty)),
             span: Span {
                 start: satrt_position,
                 end: end_position,
@@ -108,27 +836,44 @@ fn parse_optional(start_span: AnnotationSpan) -> IResult<AnnotationSpan, Annotat
     ))
 }
 
+/// number|string
+///   `|string`, `number|` and `number||string` have an empty member, which fails the annotation
 fn parse_union(start_span: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
+    let empty_member = || {
+        nom::Err::Failure(nom::error::Error::new(
+            start_span,
+            nom::error::ErrorKind::SeparatedList,
+        ))
+    };
+    if opt(ws(char('|'))).parse(start_span)?.1.is_some() {
+        return Err(empty_member());
+    }
     let (end_span, tys) = map(
         separated_list1(ws(tag("|")), parse_basictype),
         |ann_infos| {
             ann_infos
-                .iter()
-                .map(|ann| match ann.tag.clone() {
-                    AnnotationTag::Type(ty) => ty,
-                    _ => unimplemented!(),
-                })
+                .into_iter()
+                .map(type_of)
                 .collect::<Vec<TypeKind>>()
         },
     )
     .parse(start_span)?;
+    // the member after the last `|` is not a type
+    if opt(ws(char('|'))).parse(end_span)?.1.is_some() {
+        return Err(empty_member());
+    }
     let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
     let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
     if tys.len() >= 2 {
+        let ty = if tys.contains(&TypeKind::Nil) {
+            TypeKind::optional(TypeKind::Union(tys))
+        } else {
+            TypeKind::Union(tys)
+        };
         Ok((
             end_span,
             AnnotationInfo {
-                tag: AnnotationTag::Type(TypeKind::Union(tys)),
+                tag: AnnotationTag::Type(ty),
                 span: Span {
                     start: satrt_position,
                     end: end_position,
@@ -143,14 +888,12 @@ fn parse_union(start_span: AnnotationSpan) -> IResult<AnnotationSpan, Annotation
     }
 }
 
+/// string[], (boolean|number)[]
+///   parentheses take a union or a function as the element, as the type is displayed
 fn parse_array(start_span: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationInfo> {
-    let (end_span, ty) = map(terminated(parse_basictype, tag("[]")), |ann| {
-        match ann.tag {
-            AnnotationTag::Type(ty) => ty,
-            _ => unimplemented!(),
-        }
-    })
-    .parse(start_span)?;
+    let parenthesized = delimited(ws(char('(')), parse_type, ws(char(')')));
+    let element = alt((parenthesized, parse_basictype));
+    let (end_span, ty) = map(terminated(element, tag("[]")), type_of).parse(start_span)?;
     let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
     let end_position = Position::new(end_span.location_line(), end_span.get_column() as u32);
     Ok((
@@ -170,13 +913,11 @@ fn parse_tabletype(start_span: AnnotationSpan) -> IResult<AnnotationSpan, Annota
     let (end_span, (key_ty, val_ty)) = map(
         delimited(
             char('<'),
-            separated_pair(parse_basictype, ws(char(',')), parse_basictype),
+            // value may be a nested table type
+            separated_pair(parse_basictype, ws(char(',')), parse_type),
             char('>'),
         ),
-        |(key, val)| match (key.tag, val.tag) {
-            (AnnotationTag::Type(key_ty), AnnotationTag::Type(val_ty)) => (key_ty, val_ty),
-            (_, _) => unimplemented!(),
-        },
+        |(key, val)| (type_of(key), type_of(val)),
     )
     .parse(end_span)?;
     let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
@@ -203,14 +944,11 @@ fn parse_dict(start_span: AnnotationSpan) -> IResult<AnnotationSpan, AnnotationI
             separated_pair(
                 delimited(char('['), parse_basictype, char(']')),
                 ws(char(':')),
-                parse_basictype,
+                parse_type,
             ),
             ws(char('}')),
         ),
-        |(key, val)| match (key.tag, val.tag) {
-            (AnnotationTag::Type(key_ty), AnnotationTag::Type(val_ty)) => (key_ty, val_ty),
-            (_, _) => unimplemented!(),
-        },
+        |(key, val)| (type_of(key), type_of(val)),
     )
     .parse(start_span)?;
     let satrt_position = Position::new(start_span.location_line(), start_span.get_column() as u32);
@@ -249,7 +987,7 @@ mod concat_tokens {
     use unindent::unindent;
     #[test]
     fn singleline() {
-        let tokens = vec![
+        let tokens = [
             Token::new(TokenType::Whitespace {
                 characters: ShortString::new("\n"),
             }),
@@ -265,7 +1003,7 @@ mod concat_tokens {
     }
     #[test]
     fn multiline() {
-        let tokens = vec![
+        let tokens = [
             Token::new(TokenType::Whitespace {
                 characters: ShortString::new("\n"),
             }),
@@ -373,6 +1111,12 @@ mod parse_annotation_normal {
                 }
             }
         );
+        // nil is moved last, the same as `string?`
+        let ann_info = parse_annotation("---@type nil|string");
+        assert_eq!(
+            ann_info[0].tag,
+            AnnotationTag::Type(TypeKind::optional(TypeKind::String))
+        );
         // if no annotation, return any type
         let content = "";
         let ann_info = parse_annotation(content);
@@ -426,4 +1170,492 @@ mod parse_annotation_normal {
             }
         );
     }
+    #[test]
+    fn inline_annotation() {
+        use full_moon::ShortString;
+        use full_moon::tokenizer::{Token, TokenType};
+        let tokens = [
+            Token::new(TokenType::Whitespace {
+                characters: ShortString::new(" "),
+            }),
+            Token::new(TokenType::MultiLineComment {
+                blocks: 0,
+                comment: ShortString::new("@type number"),
+            }),
+        ];
+        let ann_infos = parse_inline_annotation(tokens.iter());
+        assert_eq!(ann_infos.len(), 1);
+        assert_eq!(ann_infos[0].tag, AnnotationTag::Type(TypeKind::Number));
+        // plain block comment is not annotation
+        let tokens = [Token::new(TokenType::MultiLineComment {
+            blocks: 0,
+            comment: ShortString::new("count of items"),
+        })];
+        assert_eq!(parse_inline_annotation(tokens.iter()), Vec::new());
+    }
+    #[test]
+    fn doc_annotation() {
+        let content = "---@see OtherFunc\n---@since 0.2\n---@type number";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(
+            ann_infos
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>(),
+            vec![
+                AnnotationTag::Doc {
+                    kind: DocKind::See,
+                    text: "OtherFunc".to_string(),
+                },
+                AnnotationTag::Doc {
+                    kind: DocKind::Since,
+                    text: "0.2".to_string(),
+                },
+                AnnotationTag::Type(TypeKind::Number),
+            ]
+        );
+        // plain comments between annotations are skipped
+        let content = "-- helper\n---@see OtherFunc";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(ann_infos.len(), 1);
+    }
+    #[test]
+    fn function_annotation() {
+        let content =
+            "---@param x number\n---@param y? string\n---@return boolean ok\n---@return string";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(
+            ann_infos
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>(),
+            vec![
+                AnnotationTag::Param {
+                    name: "x".to_string(),
+                    ty: TypeKind::Number,
+                    description: None,
+                },
+                AnnotationTag::Param {
+                    name: "y".to_string(),
+                    ty: TypeKind::Union(vec![TypeKind::String, TypeKind::Nil]),
+                    description: None,
+                },
+                AnnotationTag::Return {
+                    ty: TypeKind::Boolean,
+                    name: Some("ok".to_string()),
+                },
+                AnnotationTag::Return {
+                    ty: TypeKind::String,
+                    name: None,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn param_description() {
+        let content = "---@param x number the first line\n--- second line\n---   third line\n---@param y string\n---@return number\n--- not a param description";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(
+            ann_infos
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>(),
+            vec![
+                AnnotationTag::Param {
+                    name: "x".to_string(),
+                    ty: TypeKind::Number,
+                    description: Some("the first line\nsecond line\nthird line".to_string()),
+                },
+                AnnotationTag::Param {
+                    name: "y".to_string(),
+                    ty: TypeKind::String,
+                    description: None,
+                },
+                AnnotationTag::Return {
+                    ty: TypeKind::Number,
+                    name: None,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn empty_union_member() {
+        let malformed = || AnnotationTag::Malformed("empty member in union type".to_string());
+        for content in [
+            "---@type number|",
+            "---@type |string",
+            "---@type number||string",
+            "---@param x |number the count",
+        ] {
+            assert_eq!(
+                parse_annotation(content)
+                    .into_iter()
+                    .map(|ann| ann.tag)
+                    .collect::<Vec<AnnotationTag>>(),
+                vec![malformed()],
+                "{content}"
+            );
+        }
+        let ann_infos = parse_annotation(
+            "---@type number|
+---@type number | string",
+        );
+        assert_eq!(
+            ann_infos[0].span,
+            Span::new(Position::new(1, 1), Position::new(1, 17))
+        );
+        assert_eq!(
+            ann_infos[1].tag,
+            AnnotationTag::Type(TypeKind::Union(vec![TypeKind::Number, TypeKind::String]))
+        );
+    }
+    #[test]
+    fn dotted_field_name() {
+        let ann_infos = parse_annotation("---@class Config\n---@field a.b number");
+        assert_eq!(
+            ann_infos[1],
+            AnnotationInfo {
+                tag: AnnotationTag::Malformed(
+                    "field name `a.b` is a path, declare the field in the class of `a`".to_string()
+                ),
+                span: Span::new(Position::new(2, 1), Position::new(2, 21)),
+            }
+        );
+    }
+    #[test]
+    fn string_literal_type() {
+        let ty = |content: &str| match parse_annotation(content).pop().map(|ann| ann.tag) {
+            Some(AnnotationTag::Type(ty)) => ty,
+            tag => panic!("{tag:?}"),
+        };
+        let literal = |value: &str| TypeKind::StringLiteral(value.to_string());
+        // `|` in quotes is not a union
+        assert_eq!(
+            ty(r#"---@type "a|b"|"c""#),
+            TypeKind::Union(vec![literal("a|b"), literal("c")])
+        );
+        assert_eq!(
+            ty(r#"---@type 'a|b'|'it\'s'"#),
+            TypeKind::Union(vec![literal("a|b"), literal("it's")])
+        );
+        let escaped = ty(r#"---@type "a\"b""#);
+        assert_eq!(escaped, literal("a\"b"));
+        assert_eq!(escaped.to_string(), r#""a\"b""#);
+        // unterminated
+        assert_eq!(parse_annotation(r#"---@type "a"#), Vec::new());
+    }
+    #[test]
+    fn parenthesized_array_type() {
+        let content = "---@type (boolean|number)[]";
+        let ty = TypeKind::Array(Box::new(TypeKind::Union(vec![
+            TypeKind::Boolean,
+            TypeKind::Number,
+        ])));
+        assert_eq!(
+            parse_annotation(content),
+            vec![AnnotationInfo {
+                tag: AnnotationTag::Type(ty.clone()),
+                span: Span::new(Position::new(1, 10), Position::new(1, 28)),
+            }]
+        );
+        // displayed as written
+        assert_eq!(format!("---@type {ty}"), content);
+    }
+    #[test]
+    fn module_annotation() {
+        let content = "---@module \"foo.bar\"\n---@module 'config'\n---@module config";
+        assert_eq!(
+            parse_annotation(content)
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>(),
+            vec![
+                AnnotationTag::Module("foo.bar".to_string()),
+                AnnotationTag::Module("config".to_string()),
+            ]
+        );
+    }
+    #[test]
+    fn async_annotation() {
+        let content = "---@async\n---@param f fun()";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(ann_infos[0].tag, AnnotationTag::Async);
+        assert_eq!(
+            ann_infos[0].span,
+            Span::new(Position::new(1, 1), Position::new(1, 10))
+        );
+        assert_eq!(ann_infos.len(), 2);
+    }
+    #[test]
+    fn class_alias_annotation() {
+        let content = "---@class Person\n---@alias Id number|string\n---@class (exact) Point";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(
+            ann_infos
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>(),
+            vec![
+                AnnotationTag::Class {
+                    name: "Person".to_string(),
+                    exact: false,
+                },
+                AnnotationTag::Alias {
+                    name: "Id".to_string(),
+                    ty: TypeKind::Union(vec![TypeKind::Number, TypeKind::String]),
+                },
+                AnnotationTag::Class {
+                    name: "Point".to_string(),
+                    exact: true,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn param_annotation_multiple_names() {
+        let content = "---@param a, b number\n---@param x table<a,b>";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(
+            ann_infos
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>(),
+            vec![
+                AnnotationTag::Param {
+                    name: "a".to_string(),
+                    ty: TypeKind::Number,
+                    description: None,
+                },
+                AnnotationTag::Param {
+                    name: "b".to_string(),
+                    ty: TypeKind::Number,
+                    description: None,
+                },
+                AnnotationTag::Param {
+                    name: "x".to_string(),
+                    ty: TypeKind::KVTable {
+                        key: Box::new(TypeKind::Custom("a".to_string())),
+                        val: Box::new(TypeKind::Custom("b".to_string())),
+                    },
+                    description: None,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn function_type() {
+        let content = "---@type fun(x: number, y): number|nil, string";
+        let ann_infos = parse_annotation(content);
+        assert_eq!(
+            ann_infos
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>(),
+            vec![AnnotationTag::Type(TypeKind::Function {
+                generics: Vec::new(),
+                param_names: vec!["x".to_string(), "y".to_string()],
+                params: vec![TypeKind::Number, TypeKind::Any],
+                returns: vec![
+                    TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]),
+                    TypeKind::String,
+                ],
+                return_names: Vec::new(),
+                vararg: None,
+            })]
+        );
+    }
+    #[test]
+    fn nested_table_type() {
+        let tags = |content: &str| {
+            parse_annotation(content)
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>()
+        };
+        assert_eq!(
+            tags("---@type table<string, { [number]: string }>"),
+            vec![AnnotationTag::Type(TypeKind::KVTable {
+                key: Box::new(TypeKind::String),
+                val: Box::new(TypeKind::Dict {
+                    key: Box::new(TypeKind::Number),
+                    val: Box::new(TypeKind::String),
+                }),
+            })]
+        );
+        assert_eq!(
+            tags("---@type { [string]: number[] }"),
+            vec![AnnotationTag::Type(TypeKind::Dict {
+                key: Box::new(TypeKind::String),
+                val: Box::new(TypeKind::Array(Box::new(TypeKind::Number))),
+            })]
+        );
+        assert_eq!(
+            tags("---@type table<string, table<number, boolean>>"),
+            vec![AnnotationTag::Type(TypeKind::KVTable {
+                key: Box::new(TypeKind::String),
+                val: Box::new(TypeKind::KVTable {
+                    key: Box::new(TypeKind::Number),
+                    val: Box::new(TypeKind::Boolean),
+                }),
+            })]
+        );
+    }
+    #[test]
+    fn named_type_annotation() {
+        assert_eq!(
+            parse_annotation("---@type number|nil x"),
+            vec![AnnotationInfo {
+                tag: AnnotationTag::NamedType {
+                    ty: TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]),
+                    name: "x".to_string(),
+                },
+                span: Span::new(Position::new(1, 10), Position::new(1, 22)),
+            }]
+        );
+        // types without name are positional
+        assert_eq!(
+            parse_annotation("---@type number, string")
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<_>>(),
+            vec![
+                AnnotationTag::Type(TypeKind::Number),
+                AnnotationTag::Type(TypeKind::String)
+            ]
+        );
+    }
+    #[test]
+    fn cast_annotation() {
+        assert_eq!(
+            parse_annotation("---@cast x -nil"),
+            vec![AnnotationInfo {
+                tag: AnnotationTag::Cast {
+                    name: "x".to_string(),
+                    op: CastOp::Remove,
+                    ty: TypeKind::Nil,
+                },
+                span: Span::new(Position::new(1, 1), Position::new(1, 16)),
+            }]
+        );
+        let tags = |content: &str| {
+            parse_annotation(content)
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>()
+        };
+        assert_eq!(
+            tags("---@cast x +string"),
+            vec![AnnotationTag::Cast {
+                name: "x".to_string(),
+                op: CastOp::Add,
+                ty: TypeKind::String,
+            }]
+        );
+        assert_eq!(
+            tags("---@cast x number|string"),
+            vec![AnnotationTag::Cast {
+                name: "x".to_string(),
+                op: CastOp::Set,
+                ty: TypeKind::Union(vec![TypeKind::Number, TypeKind::String]),
+            }]
+        );
+    }
+    #[test]
+    fn generic_function_field() {
+        assert_eq!(
+            parse_annotation("---@field map fun<T, U>(self, fn: fun(x: T): U): U[]")
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<_>>(),
+            vec![AnnotationTag::Field {
+                name: "map".to_string(),
+                ty: TypeKind::Function {
+                    generics: vec!["T".to_string(), "U".to_string()],
+                    param_names: vec!["self".to_string(), "fn".to_string()],
+                    params: vec![
+                        TypeKind::Any,
+                        TypeKind::Function {
+                            generics: Vec::new(),
+                            param_names: vec!["x".to_string()],
+                            params: vec![TypeKind::Generic("T".to_string())],
+                            returns: vec![TypeKind::Generic("U".to_string())],
+                            return_names: Vec::new(),
+                            vararg: None,
+                        },
+                    ],
+                    returns: vec![TypeKind::Array(Box::new(TypeKind::Generic(
+                        "U".to_string()
+                    )))],
+                    return_names: Vec::new(),
+                    vararg: None,
+                },
+                description: None,
+            }]
+        );
+    }
+    #[test]
+    fn function_type_display() {
+        // displayed in the form it is written
+        for content in [
+            "fun(a: number): boolean",
+            "fun(a: number, b: string|nil)",
+            "fun<T, U>(self: T, fn: fun(x: T): U): U[]",
+            "fun(): boolean, ...string",
+        ] {
+            let tags: Vec<String> = parse_annotation(&format!("---@type {content}"))
+                .into_iter()
+                .map(|ann| match ann.tag {
+                    AnnotationTag::Type(ty) => ty.to_string(),
+                    tag => panic!("{tag:?}"),
+                })
+                .collect();
+            assert_eq!(tags, vec![content.to_string()]);
+        }
+        // untyped params are any
+        let ann_infos = parse_annotation("---@type fun(a, b: number):boolean");
+        assert_eq!(
+            ann_infos[0].tag,
+            AnnotationTag::Type(TypeKind::Function {
+                generics: Vec::new(),
+                param_names: vec!["a".to_string(), "b".to_string()],
+                params: vec![TypeKind::Any, TypeKind::Number],
+                returns: vec![TypeKind::Boolean],
+                return_names: Vec::new(),
+                vararg: None,
+            })
+        );
+    }
+    #[test]
+    fn function_type_vararg() {
+        let tags = |content: &str| {
+            parse_annotation(content)
+                .into_iter()
+                .map(|ann| ann.tag)
+                .collect::<Vec<AnnotationTag>>()
+        };
+        assert_eq!(
+            tags("---@type fun(): ...number"),
+            vec![AnnotationTag::Type(TypeKind::Function {
+                generics: Vec::new(),
+                param_names: Vec::new(),
+                params: Vec::new(),
+                returns: Vec::new(),
+                return_names: Vec::new(),
+                vararg: Some(Box::new(TypeKind::Number)),
+            })]
+        );
+        assert_eq!(
+            tags("---@type fun(x: string): boolean, string, ..."),
+            vec![AnnotationTag::Type(TypeKind::Function {
+                generics: Vec::new(),
+                param_names: vec!["x".to_string()],
+                params: vec![TypeKind::String],
+                returns: vec![TypeKind::Boolean, TypeKind::String],
+                return_names: Vec::new(),
+                vararg: Some(Box::new(TypeKind::Any)),
+            })]
+        );
+        // vararg in the middle is not a function type
+        assert_eq!(tags("---@type fun(): ...number, string"), Vec::new());
+    }
 }
@@ -1,12 +1,35 @@
-use std::collections::BTreeMap;
-
-use crate::annotation::{AnnotationInfo, concat_tokens, parse_annotation};
+use crate::annotation::{
+    AnnotationInfo, AnnotationTag, parse_inline_annotation, parse_leading_annotation,
+};
 use typua_span::{Position, Span};
-use typua_ty::TypeKind;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypeAst {
     pub block: Block,
+    /// annotations after the last statement, attached to nothing
+    pub trailing_annotates: Vec<AnnotationInfo>,
+}
+
+impl TypeAst {
+    /// `---@meta` file declares types only
+    pub fn is_meta(&self) -> bool {
+        let first_annotates = self.block.stmts.first().map_or(&[][..], Stmt::annotates);
+        first_annotates
+            .iter()
+            .chain(self.trailing_annotates.iter())
+            .any(|ann| matches!(ann.tag, AnnotationTag::Meta))
+    }
+    /// `---@module "name"` at the top of the file, with the name
+    pub fn module(&self) -> Option<(&str, &AnnotationInfo)> {
+        let first_annotates = self.block.stmts.first().map_or(&[][..], Stmt::annotates);
+        first_annotates
+            .iter()
+            .chain(self.trailing_annotates.iter())
+            .find_map(|ann| match &ann.tag {
+                AnnotationTag::Module(name) => Some((name.as_str(), ann)),
+                _ => None,
+            })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -23,18 +46,74 @@ pub enum Stmt {
     FunctionCall(FunctionCall),
     FunctionDeclaration(FunctionDeclaration),
     LocalFunction(LocalFunction),
-    // If(If),
+    If(If),
     // Do(Do),
-    // While(While),
-    // Repeat(Repeat),
-    // Goto(Goto),
+    While(While),
+    Repeat(Repeat),
+    Goto(Goto),
     // NumericFor(NumericFor),
     // GenericFor(GenericFor),
-    // Label(Label),
+    Label(Label),
+    Return(Return),
+    Break(Break),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::LocalAssign(local_assign) => local_assign.span(),
+            Stmt::FunctionCall(call) => call.span.clone(),
+            Stmt::LocalFunction(local_function) => local_function.span.clone(),
+            Stmt::Goto(goto) => goto.span.clone(),
+            Stmt::Label(label) => label.span.clone(),
+            Stmt::Return(ret) => ret.span.clone(),
+            Stmt::If(if_stmt) => if_stmt.span.clone(),
+            Stmt::While(while_stmt) => while_stmt.span.clone(),
+            Stmt::Repeat(repeat) => repeat.span.clone(),
+            Stmt::Break(break_stmt) => break_stmt.span.clone(),
+            Stmt::FunctionDeclaration(function) => function.span.clone(),
+            Stmt::Assign(assign) => assign.span.clone(),
+        }
+    }
+    /// leading annotations, statements which can't be annotated have none
+    pub fn annotates(&self) -> &[AnnotationInfo] {
+        match self {
+            Stmt::LocalAssign(local_assign) => &local_assign.annotates,
+            Stmt::LocalFunction(local_function) => &local_function.annotates,
+            Stmt::FunctionDeclaration(function) => &function.annotates,
+            Stmt::Assign(assign) => &assign.annotates,
+            Stmt::FunctionCall(call) => &call.annotates,
+            Stmt::Return(ret) => &ret.annotates,
+            Stmt::If(if_stmt) => &if_stmt.annotates,
+            Stmt::While(while_stmt) => &while_stmt.annotates,
+            Stmt::Repeat(repeat) => &repeat.annotates,
+            _ => &[],
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Assign {}
+/// x, t.y = 1, "hello"
+/// vars are x, t.y
+pub struct Assign {
+    pub vars: Vec<Expression>,
+    pub exprs: Vec<Expression>,
+    /// leading annotations, `---@type T` above the statement
+    pub annotates: Vec<AnnotationInfo>,
+    /// inline annotations following each value, `v --[[@as T]]`
+    pub value_annotates: Vec<Vec<AnnotationInfo>>,
+    pub span: Span,
+}
+
+impl Assign {
+    /// names of the variables assigned, fields of tables are not included
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.vars.iter().filter_map(|var| match var {
+            Expression::Var { symbol, .. } => Some(symbol.as_str()),
+            _ => None,
+        })
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 /// x, y["a"], z[1] = 1, "hello", nil
@@ -46,29 +125,112 @@ pub struct LocalAssign {
     pub annotates: Vec<AnnotationInfo>,
 }
 
+impl LocalAssign {
+    /// from the first name to the last expression (or name if no expression)
+    pub fn span(&self) -> Span {
+        let start = self
+            .vars
+            .first()
+            .map(|v| v.span.start.clone())
+            .expect("local assignment has at least one name");
+        let end = match self.exprs.last() {
+            Some(expr) => expr.span().end,
+            None => self
+                .vars
+                .last()
+                .map(|v| v.span.end.clone())
+                .expect("local assignment has at least one name"),
+        };
+        Span::new(start, end)
+    }
+}
+
+/// local function f(a, b) ... end
+/// name is f
+/// params are a, b
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalFunction {
+    pub name: Variable,
+    pub params: Vec<Variable>,
+    pub body: Block,
+    pub annotates: Vec<AnnotationInfo>,
+    pub span: Span,
+}
+
+/// f(a, b)
+/// callee is f
+/// args are a, b
+///
+/// obj:m(a, b)
+/// callee is obj, method is m, obj is passed as the first argument
 #[derive(Debug, Clone, PartialEq)]
-pub struct LocalFunction {}
+pub struct FunctionCall {
+    pub callee: Box<Expression>,
+    pub method: Option<Variable>,
+    pub args: Vec<Expression>,
+    /// leading annotations of a call statement, calls in expressions have none
+    pub annotates: Vec<AnnotationInfo>,
+    pub span: Span,
+}
 
+/// function t.a:m(x) ... end
+/// name is t, fields are a, method is m
+/// params are x, self is implicit when method is given
 #[derive(Debug, Clone, PartialEq)]
-pub struct FunctionCall {}
+pub struct FunctionDeclaration {
+    pub name: Variable,
+    pub fields: Vec<Variable>,
+    pub method: Option<Variable>,
+    pub params: Vec<Variable>,
+    pub body: Block,
+    pub annotates: Vec<AnnotationInfo>,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct FunctionDeclaration {}
+/// if c1 then b1 elseif c2 then b2 else b3 end
+pub struct If {
+    /// `if` and `elseif` branches in order
+    pub branches: Vec<IfBranch>,
+    pub else_block: Option<Block>,
+    pub annotates: Vec<AnnotationInfo>,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct If {}
+pub struct IfBranch {
+    pub cond: Expression,
+    pub block: Block,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Do {}
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct While {}
+/// while cond do block end
+pub struct While {
+    pub cond: Expression,
+    pub block: Block,
+    pub annotates: Vec<AnnotationInfo>,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Repeat {}
+/// repeat block until cond
+///   locals of the block are visible in cond
+pub struct Repeat {
+    pub block: Block,
+    pub until: Expression,
+    pub annotates: Vec<AnnotationInfo>,
+    pub span: Span,
+}
 
+/// goto name
 #[derive(Debug, Clone, PartialEq)]
-pub struct Goto {}
+pub struct Goto {
+    pub label: Variable,
+    pub span: Span,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct NumericFor {}
@@ -76,20 +238,55 @@ pub struct NumericFor {}
 #[derive(Debug, Clone, PartialEq)]
 pub struct GenericFor {}
 
+/// break
+///   the last statement of a block, as `return` is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Break {
+    pub span: Span,
+}
+
+/// ::name::
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub name: Variable,
+    pub span: Span,
+}
+
+/// return a, b
+/// always the last statement of a block
+#[derive(Debug, Clone, PartialEq)]
+pub struct Return {
+    pub values: Vec<ReturnValue>,
+    pub annotates: Vec<AnnotationInfo>,
+    pub span: Span,
+}
+
+/// annotates are inline casts following the value, `v --[[@type T]]`
 #[derive(Debug, Clone, PartialEq)]
-pub struct Label {}
+pub struct ReturnValue {
+    pub expr: Expression,
+    pub annotates: Vec<AnnotationInfo>,
+}
 
 /// Expression
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
+    Nil {
+        span: Span,
+    },
     Number {
         span: Span,
+        /// written without fraction or exponent, `1` or `0xff`
+        integer: bool,
     },
     String {
         span: Span,
+        /// content without quotes
+        value: String,
     },
     Boolean {
         span: Span,
+        value: bool,
     },
     BinaryOperator {
         lhs: Box<Expression>,
@@ -99,16 +296,118 @@ pub enum Expression {
     UnaryOperator {
         unop: UnOp,
         expr: Box<Expression>,
+        /// from the operator to the end of expr
+        span: Span,
     },
+    /// function(params) ... end, the body is not checked yet
     Function {
-        params: BTreeMap<String, TypeKind>,
-        returns: Vec<TypeKind>,
+        params: Vec<Variable>,
+        span: Span,
+    },
+    /// `...` of a vararg function or of the main chunk
+    Vararg {
+        span: Span,
     },
     FunctionCall(FunctionCall),
+    /// (expr), truncates multiple values to the first one
+    Parentheses {
+        expr: Box<Expression>,
+        span: Span,
+    },
     Var {
         span: Span,
         symbol: String,
     },
+    /// expr.key
+    Field {
+        expr: Box<Expression>,
+        key: Variable,
+    },
+    /// expr[key]
+    Index {
+        expr: Box<Expression>,
+        key: Box<Expression>,
+        /// from expr to the closing bracket
+        span: Span,
+    },
+    Table {
+        fields: Vec<TableField>,
+        span: Span,
+    },
+}
+
+/// field of table constructor
+///   { [k] = v, name = v, v }
+/// annotates are inline annotations following the value, `v --[[@type T]]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableField {
+    /// annotates also include `---@type T` on the lines above `[k]`
+    ExpressionKey {
+        key: Expression,
+        value: Expression,
+        annotates: Vec<AnnotationInfo>,
+    },
+    NameKey {
+        key: Variable,
+        value: Expression,
+        annotates: Vec<AnnotationInfo>,
+    },
+    NoKey {
+        value: Expression,
+        annotates: Vec<AnnotationInfo>,
+    },
+}
+
+impl TableField {
+    pub fn value(&self) -> &Expression {
+        match self {
+            TableField::ExpressionKey { value, .. }
+            | TableField::NameKey { value, .. }
+            | TableField::NoKey { value, .. } => value,
+        }
+    }
+    /// from the key (or the value if no key) to the value
+    pub fn span(&self) -> Span {
+        match self {
+            TableField::ExpressionKey { key, value, .. } => {
+                Span::new(key.span().start, value.span().end)
+            }
+            TableField::NameKey { key, value, .. } => {
+                Span::new(key.span.start.clone(), value.span().end)
+            }
+            TableField::NoKey { value, .. } => value.span(),
+        }
+    }
+    pub fn annotates(&self) -> &[AnnotationInfo] {
+        match self {
+            TableField::ExpressionKey { annotates, .. }
+            | TableField::NameKey { annotates, .. }
+            | TableField::NoKey { annotates, .. } => annotates,
+        }
+    }
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Nil { span }
+            | Expression::Number { span, .. }
+            | Expression::String { span, .. }
+            | Expression::Boolean { span, .. }
+            | Expression::Vararg { span }
+            | Expression::Var { span, .. } => span.clone(),
+            Expression::BinaryOperator { lhs, rhs, .. } => {
+                Span::new(lhs.span().start, rhs.span().end)
+            }
+            Expression::Field { expr, key } => Span::new(expr.span().start, key.span.end.clone()),
+            Expression::FunctionCall(call) => call.span.clone(),
+            Expression::Table { span, .. }
+            | Expression::UnaryOperator { span, .. }
+            | Expression::Parentheses { span, .. }
+            | Expression::Function { span, .. }
+            | Expression::Index { span, .. } => span.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -126,6 +425,16 @@ pub enum BinOp {
     Equal(Span),
     NotEqual(Span),
     Concat(Span),
+    Mod(Span),
+    Pow(Span),
+    /// `//` of lua 5.3
+    FloorDiv(Span),
+    /// `&`, `|`, `~`, `<<` and `>>` of lua 5.3
+    BitAnd(Span),
+    BitOr(Span),
+    BitXor(Span),
+    ShiftLeft(Span),
+    ShiftRight(Span),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -155,6 +464,7 @@ impl From<full_moon::ast::Ast> for TypeAst {
     fn from(ast: full_moon::ast::Ast) -> Self {
         Self {
             block: Block::from(ast.nodes().clone()),
+            trailing_annotates: parse_leading_annotation(ast.eof().leading_trivia()),
         }
     }
 }
@@ -171,6 +481,16 @@ impl From<full_moon::ast::Block> for Block {
         for stmt in block.stmts() {
             stmts.push(Stmt::from(stmt.clone()));
         }
+        match block.last_stmt() {
+            Some(full_moon::ast::LastStmt::Return(ret)) => {
+                stmts.push(Stmt::Return(Return::from(ret)))
+            }
+            Some(full_moon::ast::LastStmt::Break(token)) => stmts.push(Stmt::Break(Break {
+                span: Span::from(token.clone()),
+            })),
+            // only luau has another last statement, `continue`, which is never parsed here
+            Some(_) | None => (),
+        }
         Self { stmts }
     }
 }
@@ -178,11 +498,28 @@ impl From<full_moon::ast::Block> for Block {
 impl From<full_moon::ast::Stmt> for Stmt {
     fn from(stmt: full_moon::ast::Stmt) -> Self {
         match stmt {
-            full_moon::ast::Stmt::Assignment(assign) => unimplemented!(),
+            full_moon::ast::Stmt::Assignment(assign) => {
+                let (start, end) = full_moon::node::Node::range(&assign)
+                    .expect("parsed assignment has no position");
+                let annotates = match full_moon::node::Node::tokens(&assign).next() {
+                    Some(tkn) => parse_leading_annotation(tkn.leading_trivia()),
+                    None => Vec::new(),
+                };
+                Stmt::Assign(Assign {
+                    vars: assign.variables().iter().map(var_expression).collect(),
+                    exprs: assign
+                        .expressions()
+                        .iter()
+                        .map(|e| Expression::from(e.clone()))
+                        .collect(),
+                    annotates,
+                    value_annotates: assign.expressions().iter().map(inline_annotates).collect(),
+                    span: Span::new(Position::from(start), Position::from(end)),
+                })
+            }
             full_moon::ast::Stmt::LocalAssignment(local_assign) => {
                 let leading_tribia = local_assign.local_token().leading_trivia();
-                let ann_content = concat_tokens(leading_tribia);
-                let annotates = parse_annotation(&ann_content);
+                let annotates = parse_leading_annotation(leading_tribia);
                 let vars: Vec<Variable> = local_assign
                     .names()
                     .iter()
@@ -205,13 +542,140 @@ impl From<full_moon::ast::Stmt> for Stmt {
                     annotates,
                 })
             }
-            // full_moon::ast::Stmt::FunctionDeclaration(func_dec) => unimplemented!(),
-            // full_moon::ast::Stmt::LocalFunction(local_func) => unimplemented!(),
+            full_moon::ast::Stmt::FunctionCall(call) => {
+                let annotates = match full_moon::node::Node::tokens(&call).next() {
+                    Some(tkn) => parse_leading_annotation(tkn.leading_trivia()),
+                    None => Vec::new(),
+                };
+                Stmt::FunctionCall(FunctionCall {
+                    annotates,
+                    ..FunctionCall::from(call)
+                })
+            }
+            full_moon::ast::Stmt::LocalFunction(local_func) => {
+                let leading_tribia = local_func.local_token().leading_trivia();
+                let annotates = parse_leading_annotation(leading_tribia);
+                let (start, end) = full_moon::node::Node::range(&local_func)
+                    .expect("parsed local function has no position");
+                Stmt::LocalFunction(LocalFunction {
+                    name: Variable {
+                        name: local_func.name().token().to_string(),
+                        span: Span::from(local_func.name().clone()),
+                    },
+                    params: parameters(local_func.body()),
+                    body: Block::from(local_func.body().block().clone()),
+                    annotates,
+                    span: Span::new(Position::from(start), Position::from(end)),
+                })
+            }
+            full_moon::ast::Stmt::FunctionDeclaration(func_dec) => {
+                let annotates =
+                    parse_leading_annotation(func_dec.function_token().leading_trivia());
+                let variable = |tkn: &full_moon::tokenizer::TokenReference| Variable {
+                    name: tkn.token().to_string(),
+                    span: Span::from(tkn.clone()),
+                };
+                let mut names = func_dec.name().names().iter().map(variable);
+                let name = names.next().expect("function name is empty");
+                let (start, end) = full_moon::node::Node::range(&func_dec)
+                    .expect("parsed function declaration has no position");
+                Stmt::FunctionDeclaration(FunctionDeclaration {
+                    name,
+                    fields: names.collect(),
+                    method: func_dec.name().method_name().map(variable),
+                    params: parameters(func_dec.body()),
+                    body: Block::from(func_dec.body().block().clone()),
+                    annotates,
+                    span: Span::new(Position::from(start), Position::from(end)),
+                })
+            }
+            full_moon::ast::Stmt::Goto(goto) => Stmt::Goto(Goto {
+                label: Variable {
+                    name: goto.label_name().token().to_string(),
+                    span: Span::from(goto.label_name().clone()),
+                },
+                span: Span::new(
+                    Position::from(goto.goto_token().start_position()),
+                    Position::from(goto.label_name().end_position()),
+                ),
+            }),
+            full_moon::ast::Stmt::Label(label) => Stmt::Label(Label {
+                name: Variable {
+                    name: label.name().token().to_string(),
+                    span: Span::from(label.name().clone()),
+                },
+                span: Span::new(
+                    Position::from(label.left_colons().start_position()),
+                    Position::from(label.right_colons().end_position()),
+                ),
+            }),
+            full_moon::ast::Stmt::If(if_stmt) => {
+                let branches = std::iter::once((if_stmt.condition(), if_stmt.block()))
+                    .chain(
+                        if_stmt
+                            .else_if()
+                            .into_iter()
+                            .flatten()
+                            .map(|else_if| (else_if.condition(), else_if.block())),
+                    )
+                    .map(|(cond, block)| IfBranch {
+                        cond: Expression::from(cond.clone()),
+                        block: Block::from(block.clone()),
+                    })
+                    .collect();
+                let (start, end) =
+                    full_moon::node::Node::range(&if_stmt).expect("parsed if has no position");
+                Stmt::If(If {
+                    branches,
+                    else_block: if_stmt.else_block().cloned().map(Block::from),
+                    annotates: parse_leading_annotation(if_stmt.if_token().leading_trivia()),
+                    span: Span::new(Position::from(start), Position::from(end)),
+                })
+            }
+            full_moon::ast::Stmt::While(while_stmt) => {
+                let (start, end) = full_moon::node::Node::range(&while_stmt)
+                    .expect("parsed while has no position");
+                Stmt::While(While {
+                    cond: Expression::from(while_stmt.condition().clone()),
+                    block: Block::from(while_stmt.block().clone()),
+                    annotates: parse_leading_annotation(while_stmt.while_token().leading_trivia()),
+                    span: Span::new(Position::from(start), Position::from(end)),
+                })
+            }
+            full_moon::ast::Stmt::Repeat(repeat) => {
+                let (start, end) =
+                    full_moon::node::Node::range(&repeat).expect("parsed repeat has no position");
+                Stmt::Repeat(Repeat {
+                    block: Block::from(repeat.block().clone()),
+                    until: Expression::from(repeat.until().clone()),
+                    annotates: parse_leading_annotation(repeat.repeat_token().leading_trivia()),
+                    span: Span::new(Position::from(start), Position::from(end)),
+                })
+            }
             _ => unimplemented!(),
         }
     }
 }
 
+/// params of a function, `...` is the param named `...` which takes the rest of the arguments
+fn parameters(body: &full_moon::ast::FunctionBody) -> Vec<Variable> {
+    body.parameters()
+        .iter()
+        .map(|param| {
+            let tkn = match param {
+                full_moon::ast::Parameter::Name(tkn) | full_moon::ast::Parameter::Ellipsis(tkn) => {
+                    tkn
+                }
+                _ => unimplemented!(),
+            };
+            Variable {
+                name: tkn.token().to_string(),
+                span: Span::from(tkn.clone()),
+            }
+        })
+        .collect()
+}
+
 impl From<full_moon::ast::Expression> for Expression {
     fn from(expr: full_moon::ast::Expression) -> Self {
         match expr {
@@ -220,21 +684,35 @@ impl From<full_moon::ast::Expression> for Expression {
                     start: Position::from(tkn.start_position()),
                     end: Position::from(tkn.end_position()),
                 },
+                integer: is_integer_literal(&tkn.token().to_string()),
             },
             full_moon::ast::Expression::String(tkn) => Expression::String {
                 span: Span {
                     start: Position::from(tkn.start_position()),
                     end: Position::from(tkn.end_position()),
                 },
+                value: string_value(&tkn),
             },
             full_moon::ast::Expression::Symbol(tkn) => match tkn.token_type() {
                 full_moon::tokenizer::TokenType::Symbol { symbol } => match symbol {
-                    full_moon::tokenizer::Symbol::False => Expression::Boolean {
+                    full_moon::tokenizer::Symbol::True | full_moon::tokenizer::Symbol::False => {
+                        Expression::Boolean {
+                            span: Span {
+                                start: Position::from(tkn.start_position()),
+                                end: Position::from(tkn.end_position()),
+                            },
+                            value: *symbol == full_moon::tokenizer::Symbol::True,
+                        }
+                    }
+                    full_moon::tokenizer::Symbol::Nil => Expression::Nil {
                         span: Span {
                             start: Position::from(tkn.start_position()),
                             end: Position::from(tkn.end_position()),
                         },
                     },
+                    full_moon::tokenizer::Symbol::Ellipsis => Expression::Vararg {
+                        span: Span::from(tkn.clone()),
+                    },
                     _ => unimplemented!(),
                 },
                 _ => unimplemented!(),
@@ -247,26 +725,249 @@ impl From<full_moon::ast::Expression> for Expression {
                 }
             }
             full_moon::ast::Expression::UnaryOperator { unop, expression } => {
+                let expr = Expression::from(*expression);
                 Expression::UnaryOperator {
+                    span: Span::new(
+                        Position::from(unop.token().start_position()),
+                        expr.span().end,
+                    ),
                     unop: UnOp::from(unop),
-                    expr: Box::new(Expression::from(*expression)),
+                    expr: Box::new(expr),
+                }
+            }
+            full_moon::ast::Expression::Var(var) => var_expression(&var),
+            full_moon::ast::Expression::FunctionCall(call) => {
+                Expression::FunctionCall(FunctionCall::from(call))
+            }
+            full_moon::ast::Expression::Parentheses {
+                ref contained,
+                ref expression,
+            } => {
+                let (start, end) = contained.tokens();
+                Expression::Parentheses {
+                    expr: Box::new(Expression::from(*expression.clone())),
+                    span: Span::new(
+                        Position::from(start.start_position()),
+                        Position::from(end.end_position()),
+                    ),
                 }
             }
-            full_moon::ast::Expression::Var(var) => match var {
-                full_moon::ast::Var::Expression(_expr) => {
-                    unimplemented!()
+            full_moon::ast::Expression::TableConstructor(table) => table_expression(&table),
+            full_moon::ast::Expression::Function(function) => {
+                let (start, end) = full_moon::node::Node::range(&function)
+                    .expect("parsed function has no position");
+                Expression::Function {
+                    params: parameters(function.body()),
+                    span: Span::new(Position::from(start), Position::from(end)),
                 }
-                full_moon::ast::Var::Name(tkn) => Expression::Var {
-                    span: Span::from(tkn.clone()),
-                    symbol: tkn.token().to_string(),
-                },
-                _ => unimplemented!(),
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+/// inline annotation is trailing trivia of the last token of the value
+///   tokens of a table come braces first, so the last one is found by position
+fn inline_annotates(value: &full_moon::ast::Expression) -> Vec<AnnotationInfo> {
+    match full_moon::node::Node::tokens(value).max_by_key(|tkn| tkn.end_position()) {
+        Some(tkn) => parse_inline_annotation(tkn.trailing_trivia()),
+        None => Vec::new(),
+    }
+}
+
+impl From<&full_moon::ast::Return> for Return {
+    fn from(ret: &full_moon::ast::Return) -> Self {
+        let (start, end) =
+            full_moon::node::Node::range(ret).expect("parsed return has no position");
+        Self {
+            values: ret
+                .returns()
+                .iter()
+                .map(|value| ReturnValue {
+                    annotates: inline_annotates(value),
+                    expr: Expression::from(value.clone()),
+                })
+                .collect(),
+            annotates: parse_leading_annotation(ret.token().leading_trivia()),
+            span: Span::new(Position::from(start), Position::from(end)),
+        }
+    }
+}
+
+impl From<full_moon::ast::Field> for TableField {
+    fn from(field: full_moon::ast::Field) -> Self {
+        match field {
+            full_moon::ast::Field::ExpressionKey {
+                brackets,
+                key,
+                value,
+                ..
+            } => {
+                let mut annotates = parse_leading_annotation(brackets.tokens().0.leading_trivia());
+                annotates.extend(inline_annotates(&value));
+                TableField::ExpressionKey {
+                    annotates,
+                    key: Expression::from(key),
+                    value: Expression::from(value),
+                }
+            }
+            full_moon::ast::Field::NameKey { key, value, .. } => {
+                let mut annotates = parse_leading_annotation(key.leading_trivia());
+                annotates.extend(inline_annotates(&value));
+                TableField::NameKey {
+                    annotates,
+                    key: Variable {
+                        name: key.token().to_string(),
+                        span: Span::from(key.clone()),
+                    },
+                    value: Expression::from(value),
+                }
+            }
+            full_moon::ast::Field::NoKey(value) => TableField::NoKey {
+                annotates: inline_annotates(&value),
+                value: Expression::from(value),
             },
             _ => unimplemented!(),
         }
     }
 }
 
+impl From<full_moon::ast::FunctionCall> for FunctionCall {
+    fn from(call: full_moon::ast::FunctionCall) -> Self {
+        let suffixes: Vec<_> = call.suffixes().collect();
+        let Some((last, indexes)) = suffixes.split_last() else {
+            unreachable!("parsed function call has no suffix")
+        };
+        // a.b.c(...) calls the field c of a.b
+        let callee = indexes
+            .iter()
+            .copied()
+            .fold(prefix_expression(call.prefix()), index_expression);
+        let (method, args) = match last {
+            full_moon::ast::Suffix::Call(full_moon::ast::Call::AnonymousCall(args)) => {
+                (None, function_args(args))
+            }
+            full_moon::ast::Suffix::Call(full_moon::ast::Call::MethodCall(method_call)) => (
+                Some(Variable {
+                    name: method_call.name().token().to_string(),
+                    span: Span::from(method_call.name().clone()),
+                }),
+                function_args(method_call.args()),
+            ),
+            _ => unimplemented!(),
+        };
+        let (start, end) =
+            full_moon::node::Node::range(&call).expect("parsed function call has no position");
+        Self {
+            callee: Box::new(callee),
+            method,
+            args,
+            annotates: Vec::new(),
+            span: Span::new(Position::from(start), Position::from(end)),
+        }
+    }
+}
+
+/// name or a.b.c
+fn var_expression(var: &full_moon::ast::Var) -> Expression {
+    match var {
+        full_moon::ast::Var::Expression(var_expr) => var_expr
+            .suffixes()
+            .fold(prefix_expression(var_expr.prefix()), index_expression),
+        full_moon::ast::Var::Name(tkn) => Expression::Var {
+            span: Span::from(tkn.clone()),
+            symbol: tkn.token().to_string(),
+        },
+        _ => unimplemented!(),
+    }
+}
+
+fn prefix_expression(prefix: &full_moon::ast::Prefix) -> Expression {
+    match prefix {
+        full_moon::ast::Prefix::Name(tkn) => Expression::Var {
+            span: Span::from(tkn.clone()),
+            symbol: tkn.token().to_string(),
+        },
+        // ("x"):upper() or (f)()
+        full_moon::ast::Prefix::Expression(expr) => Expression::from(*expr.clone()),
+        _ => unimplemented!(),
+    }
+}
+
+/// { ... }
+fn table_expression(table: &full_moon::ast::TableConstructor) -> Expression {
+    let (start, end) =
+        full_moon::node::Node::range(table).expect("parsed table constructor has no position");
+    Expression::Table {
+        fields: table
+            .fields()
+            .iter()
+            .map(|f| TableField::from(f.clone()))
+            .collect(),
+        span: Span::new(Position::from(start), Position::from(end)),
+    }
+}
+
+/// expr.name or expr[key]
+fn index_expression(expr: Expression, suffix: &full_moon::ast::Suffix) -> Expression {
+    match suffix {
+        full_moon::ast::Suffix::Index(full_moon::ast::Index::Dot { name, .. }) => {
+            Expression::Field {
+                expr: Box::new(expr),
+                key: Variable {
+                    name: name.token().to_string(),
+                    span: Span::from(name.clone()),
+                },
+            }
+        }
+        full_moon::ast::Suffix::Index(full_moon::ast::Index::Brackets {
+            brackets,
+            expression,
+        }) => {
+            let (_, close) = brackets.tokens();
+            Expression::Index {
+                span: Span::new(expr.span().start, Position::from(close.end_position())),
+                expr: Box::new(expr),
+                key: Box::new(Expression::from(expression.clone())),
+            }
+        }
+        _ => unimplemented!(),
+    }
+}
+
+fn function_args(args: &full_moon::ast::FunctionArgs) -> Vec<Expression> {
+    match args {
+        full_moon::ast::FunctionArgs::Parentheses { arguments, .. } => arguments
+            .iter()
+            .map(|e| Expression::from(e.clone()))
+            .collect(),
+        full_moon::ast::FunctionArgs::String(tkn) => vec![Expression::String {
+            span: Span::from(tkn.clone()),
+            value: string_value(tkn),
+        }],
+        full_moon::ast::FunctionArgs::TableConstructor(table) => vec![table_expression(table)],
+        _ => unimplemented!(),
+    }
+}
+
+/// "abc" => abc
+fn string_value(tkn: &full_moon::tokenizer::TokenReference) -> String {
+    match tkn.token_type() {
+        full_moon::tokenizer::TokenType::StringLiteral { literal, .. } => literal.to_string(),
+        _ => unimplemented!(),
+    }
+}
+
+/// 1, 0xff => true
+/// 1.0, 1e3, 0x1p4 => false
+fn is_integer_literal(text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    match text.strip_prefix("0x") {
+        Some(hex) => !hex.contains(['.', 'p']),
+        None => !text.contains(['.', 'e']),
+    }
+}
+
 impl From<full_moon::ast::BinOp> for BinOp {
     #[rustfmt::skip]
     fn from(binop: full_moon::ast::BinOp) -> Self {
@@ -275,6 +976,23 @@ impl From<full_moon::ast::BinOp> for BinOp {
             full_moon::ast::BinOp::Minus(tkn) => BinOp::Sub(Span::from(tkn.clone())),
             full_moon::ast::BinOp::Star(tkn)  => BinOp::Mul(Span::from(tkn.clone())),
             full_moon::ast::BinOp::Slash(tkn) => BinOp::Div(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::And(tkn)   => BinOp::And(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::Or(tkn)    => BinOp::Or(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::GreaterThan(tkn)      => BinOp::GreaterThan(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::GreaterThanEqual(tkn) => BinOp::GreaterThanEqual(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::LessThan(tkn)         => BinOp::LessThan(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::LessThanEqual(tkn)    => BinOp::LessThanEqual(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::TwoEqual(tkn)   => BinOp::Equal(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::TildeEqual(tkn) => BinOp::NotEqual(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::TwoDots(tkn)    => BinOp::Concat(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::Percent(tkn)    => BinOp::Mod(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::Caret(tkn)      => BinOp::Pow(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::DoubleSlash(tkn)       => BinOp::FloorDiv(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::Ampersand(tkn)         => BinOp::BitAnd(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::Pipe(tkn)              => BinOp::BitOr(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::Tilde(tkn)             => BinOp::BitXor(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::DoubleLessThan(tkn)    => BinOp::ShiftLeft(Span::from(tkn.clone())),
+            full_moon::ast::BinOp::DoubleGreaterThan(tkn) => BinOp::ShiftRight(Span::from(tkn.clone())),
             _ => unimplemented!()
         }
     }
@@ -287,6 +1005,7 @@ impl From<full_moon::ast::UnOp> for UnOp {
             full_moon::ast::UnOp::Minus(_)  => UnOp::Minus,
             full_moon::ast::UnOp::Hash(_)   => UnOp::Hash,
             full_moon::ast::UnOp::Not(_)    => UnOp::Not,
+            full_moon::ast::UnOp::Tilde(_)  => UnOp::Tilde,
             _ => unimplemented!()
         }
     }
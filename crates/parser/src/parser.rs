@@ -1,30 +1,40 @@
 use typua_config::LuaVersion;
+use typua_span::{Position, Span};
 use typua_ty::{ParseError, TypuaError};
 
 use crate::ast::TypeAst;
 
 /// entry point for parsing lua script
 pub fn parse(code: &str, lua_version: LuaVersion) -> (TypeAst, Vec<TypuaError>) {
-    match lua_version {
-        LuaVersion::Lua51 => {
-            let result = full_moon::parse_fallible(code, full_moon::LuaVersion::lua51());
-            (
-                TypeAst::from(result.ast().clone()),
-                result
-                    .errors()
-                    .iter()
-                    .map(|e| TypuaError::Parse(ParseError::SyntaxError(format!("{}", e))))
-                    .collect(),
-            )
-        }
-    }
+    let version = match lua_version {
+        LuaVersion::Lua51 => full_moon::LuaVersion::lua51(),
+        LuaVersion::Lua52 => full_moon::LuaVersion::lua52(),
+        LuaVersion::Lua53 => full_moon::LuaVersion::lua53(),
+        LuaVersion::Lua54 => full_moon::LuaVersion::lua54(),
+        LuaVersion::LuaJit => full_moon::LuaVersion::luajit(),
+    };
+    let result = full_moon::parse_fallible(code, version);
+    (
+        TypeAst::from(result.ast().clone()),
+        result
+            .errors()
+            .iter()
+            .map(|e| {
+                let (start, end) = e.range();
+                TypuaError::Parse(ParseError::SyntaxError {
+                    message: e.error_message().to_string(),
+                    span: Span::new(Position::from(start), Position::from(end)),
+                })
+            })
+            .collect(),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::annotation::{AnnotationInfo, AnnotationTag};
-    use crate::ast::{Expression, LocalAssign, Stmt, Variable};
+    use crate::ast::{Expression, LocalAssign, Stmt, TableField, Variable};
     use pretty_assertions::assert_eq;
     use typua_span::{Position, Span};
     use typua_ty::TypeKind;
@@ -51,7 +61,8 @@ mod tests {
                     span: Span {
                         start: Position::new(1, 11),
                         end: Position::new(1, 13),
-                    }
+                    },
+                    integer: true,
                 }],
                 annotates: Vec::new(),
             })]
@@ -77,7 +88,8 @@ mod tests {
                     span: Span {
                         start: Position::new(2, 11),
                         end: Position::new(2, 13),
-                    }
+                    },
+                    integer: true,
                 }],
                 annotates: vec![AnnotationInfo {
                     tag: AnnotationTag::Type(TypeKind::Number),
@@ -89,4 +101,95 @@ mod tests {
             })]
         );
     }
+    #[test]
+    fn vararg_and_function_expression() {
+        let (ast, errors) = parse(
+            "local g = function(a, ...) end\nlocal t = { ... }\n",
+            LuaVersion::Lua51,
+        );
+        assert!(errors.is_empty());
+        assert_eq!(
+            ast.block.stmts,
+            vec![
+                Stmt::LocalAssign(LocalAssign {
+                    vars: vec![Variable {
+                        name: "g".to_string(),
+                        span: Span::new(Position::new(1, 7), Position::new(1, 8)),
+                    }],
+                    exprs: vec![Expression::Function {
+                        params: vec![
+                            Variable {
+                                name: "a".to_string(),
+                                span: Span::new(Position::new(1, 20), Position::new(1, 21)),
+                            },
+                            Variable {
+                                name: "...".to_string(),
+                                span: Span::new(Position::new(1, 23), Position::new(1, 26)),
+                            },
+                        ],
+                        span: Span::new(Position::new(1, 11), Position::new(1, 31)),
+                    }],
+                    annotates: Vec::new(),
+                }),
+                Stmt::LocalAssign(LocalAssign {
+                    vars: vec![Variable {
+                        name: "t".to_string(),
+                        span: Span::new(Position::new(2, 7), Position::new(2, 8)),
+                    }],
+                    exprs: vec![Expression::Table {
+                        fields: vec![TableField::NoKey {
+                            value: Expression::Vararg {
+                                span: Span::new(Position::new(2, 13), Position::new(2, 16)),
+                            },
+                            annotates: Vec::new(),
+                        }],
+                        span: Span::new(Position::new(2, 11), Position::new(2, 18)),
+                    }],
+                    annotates: Vec::new(),
+                }),
+            ]
+        );
+    }
+    #[test]
+    fn annotation_across_comments_and_blank_lines() {
+        let code = unindent(
+            r#"
+        ---@type number
+        -- plain comment
+
+        local x = 12
+        -- plain comment
+
+        ---@param a string
+        -- helper
+
+        local function f(a)
+        end
+        "#,
+        );
+        let (ast, _) = parse(code.as_str(), LuaVersion::Lua51);
+        let annotates: Vec<Vec<AnnotationInfo>> = ast
+            .block
+            .stmts
+            .iter()
+            .map(|stmt| stmt.annotates().to_vec())
+            .collect();
+        assert_eq!(
+            annotates,
+            vec![
+                vec![AnnotationInfo {
+                    tag: AnnotationTag::Type(TypeKind::Number),
+                    span: Span::new(Position::new(1, 10), Position::new(1, 16)),
+                }],
+                vec![AnnotationInfo {
+                    tag: AnnotationTag::Param {
+                        name: "a".to_string(),
+                        ty: TypeKind::String,
+                        description: None,
+                    },
+                    span: Span::new(Position::new(7, 1), Position::new(7, 19)),
+                }],
+            ]
+        );
+    }
 }
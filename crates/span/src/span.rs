@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Span {
     pub start: Position,
     pub end: Position,
@@ -8,9 +10,25 @@ impl Span {
     pub fn new(start: Position, end: Position) -> Self {
         Self { start, end }
     }
+    pub fn contains(&self, position: &Position) -> bool {
+        self.start <= *position && *position <= self.end
+    }
+    /// span whose columns count each tab in `source` as `tab_width` columns
+    pub fn expand_tabs(&self, source: &str, tab_width: u32) -> Self {
+        let line_text = |position: &Position| {
+            source
+                .lines()
+                .nth(position.line.saturating_sub(1) as usize)
+                .unwrap_or_default()
+        };
+        Self {
+            start: self.start.expand_tabs(line_text(&self.start), tab_width),
+            end: self.end.expand_tabs(line_text(&self.end), tab_width),
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Position {
     line: u32,
     character: u32,
@@ -20,6 +38,34 @@ impl Position {
     pub fn new(line: u32, character: u32) -> Self {
         Self { line, character }
     }
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+    pub fn character(&self) -> u32 {
+        self.character
+    }
+    /// column counting each tab before the position as `tab_width` columns
+    ///   full-moon counts a tab as one column, `line_text` is the line of the position
+    pub fn expand_tabs(&self, line_text: &str, tab_width: u32) -> Self {
+        let tabs = line_text
+            .chars()
+            .take(self.character.saturating_sub(1) as usize)
+            .filter(|c| *c == '\t')
+            .count() as u32;
+        Self::new(
+            self.line,
+            self.character + tabs * tab_width.saturating_sub(1),
+        )
+    }
+    /// position relative to `base` converted to absolute one
+    ///   columns are shifted only on the first line
+    pub fn offset_from(&self, base: &Position) -> Self {
+        if self.line <= 1 {
+            Self::new(base.line, base.character + self.character - 1)
+        } else {
+            Self::new(base.line + self.line - 1, self.character)
+        }
+    }
 }
 
 impl From<full_moon::tokenizer::Token> for Span {
@@ -1,5 +1,7 @@
 use typua_span::Span;
 
+use crate::message::Message;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Diagnostic {
     pub message: String,
@@ -7,8 +9,241 @@ pub struct Diagnostic {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DiagnosticKind {
+    /// syntax error, nothing else is reported for the file
+    ParseError,
+    /// file on which the parser or the checker gave up, nothing else is reported for it
+    InternalError,
     TypeMismatch,
+    /// literal which can never match its annotation
+    AssignTypeMismatch,
     NotDeclaredVariable,
+    /// call passes more arguments than params, or omits a param which doesn't accept nil
+    ArgumentCountMismatch,
+    /// arithmetic on a value which may be nil
+    NilArithmetic,
+    UnreachableCode,
+    MixedTableLiteral,
+    UnbalancedAssignment,
+    UnknownType,
+    DanglingAnnotation,
+    /// annotation which can't be parsed, `---@type number|`
+    MalformedAnnotation,
+    /// `---@type T name` where the statement declares no such name
+    UnknownAnnotationTarget,
+    /// second `---@type` for the same name of a statement
+    ConflictingAnnotation,
+    /// read of a field which the table is not known to have
+    UndefinedField,
+    /// `obj:m()` where the class of obj is known to have no field m
+    UndefinedMethod,
+    /// `obj:m()` where the class of obj is not exact and declares no field m
+    PossiblyUndefinedMethod,
+    /// `==` or `~=` between types which have no value in common
+    SuspiciousComparison,
+    /// condition whose value is always truthy or always falsy
+    ConstantCondition,
+    /// assignment to a global which is never declared, reported with `runtime.strict_globals`
+    ImplicitGlobal,
+    /// `---@module` file which doesn't end in returning a value
+    MissingModuleReturn,
+    /// local named like a global of the standard library, which it hides in its scope
+    ShadowedBuiltin,
+    /// `break` which is in no loop of its function
+    BreakOutsideLoop,
+    /// `coroutine.yield` in a function not annotated with `---@async`
+    YieldOutsideAsync,
+}
+
+/// errors are ordered before warnings, warnings before hints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Hint,
+}
+
+impl Diagnostic {
+    pub fn new(message: Message, span: Span) -> Self {
+        Self {
+            kind: message.kind(),
+            message: message.to_string(),
+            span,
+        }
+    }
+    /// order for stable output, by start position, then errors first, then kind
+    pub fn cmp_position(&self, other: &Diagnostic) -> std::cmp::Ordering {
+        let key = |diagnostic: &Diagnostic| {
+            (
+                diagnostic.span.start.line(),
+                diagnostic.span.start.character(),
+                diagnostic.kind.severity(),
+            )
+        };
+        key(self)
+            .cmp(&key(other))
+            .then_with(|| self.kind.cmp(&other.kind))
+    }
+}
+
+impl DiagnosticKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::ParseError
+            | DiagnosticKind::InternalError
+            | DiagnosticKind::TypeMismatch
+            | DiagnosticKind::AssignTypeMismatch
+            | DiagnosticKind::NotDeclaredVariable
+            | DiagnosticKind::ArgumentCountMismatch
+            | DiagnosticKind::NilArithmetic
+            | DiagnosticKind::BreakOutsideLoop => Severity::Error,
+            DiagnosticKind::UnreachableCode
+            | DiagnosticKind::MixedTableLiteral
+            | DiagnosticKind::YieldOutsideAsync
+            | DiagnosticKind::UnbalancedAssignment
+            | DiagnosticKind::UnknownType
+            | DiagnosticKind::DanglingAnnotation
+            | DiagnosticKind::MalformedAnnotation
+            | DiagnosticKind::UnknownAnnotationTarget
+            | DiagnosticKind::ConflictingAnnotation
+            | DiagnosticKind::UndefinedField
+            | DiagnosticKind::UndefinedMethod
+            | DiagnosticKind::SuspiciousComparison
+            | DiagnosticKind::ImplicitGlobal
+            | DiagnosticKind::MissingModuleReturn => Severity::Warning,
+            DiagnosticKind::ShadowedBuiltin
+            | DiagnosticKind::PossiblyUndefinedMethod
+            | DiagnosticKind::ConstantCondition => Severity::Hint,
+        }
+    }
+    /// name of the kind in lua-language-server's diagnostic codes, as in `diagnostics.disable`
+    ///   kinds without a counterpart there are named in the same style
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiagnosticKind::ParseError => "parse-error",
+            DiagnosticKind::InternalError => "internal-error",
+            DiagnosticKind::TypeMismatch => "type-mismatch",
+            DiagnosticKind::AssignTypeMismatch => "assign-type-mismatch",
+            DiagnosticKind::NotDeclaredVariable => "undefined-global",
+            DiagnosticKind::ArgumentCountMismatch => "argument-count-mismatch",
+            DiagnosticKind::NilArithmetic => "need-check-nil",
+            DiagnosticKind::UnreachableCode => "unreachable-code",
+            DiagnosticKind::MixedTableLiteral => "mixed-table-literal",
+            DiagnosticKind::UnbalancedAssignment => "unbalanced-assignments",
+            DiagnosticKind::UnknownType => "undefined-doc-name",
+            DiagnosticKind::DanglingAnnotation => "dangling-annotation",
+            DiagnosticKind::MalformedAnnotation => "malformed-annotation",
+            DiagnosticKind::UnknownAnnotationTarget => "unknown-annotation-target",
+            DiagnosticKind::ConflictingAnnotation => "conflicting-annotation",
+            DiagnosticKind::UndefinedField => "undefined-field",
+            // lua-language-server reports methods as fields
+            DiagnosticKind::UndefinedMethod => "undefined-field",
+            DiagnosticKind::PossiblyUndefinedMethod => "undefined-field",
+            DiagnosticKind::SuspiciousComparison => "suspicious-comparison",
+            DiagnosticKind::ConstantCondition => "constant-condition",
+            DiagnosticKind::ImplicitGlobal => "implicit-global",
+            DiagnosticKind::MissingModuleReturn => "missing-module-return",
+            DiagnosticKind::ShadowedBuiltin => "shadowed-builtin",
+            DiagnosticKind::BreakOutsideLoop => "break-outside-loop",
+            DiagnosticKind::YieldOutsideAsync => "yield-outside-async",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Hint => write!(f, "hint"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn lua_language_server_codes() {
+        let codes = [
+            (DiagnosticKind::ParseError, "parse-error"),
+            (DiagnosticKind::InternalError, "internal-error"),
+            (DiagnosticKind::TypeMismatch, "type-mismatch"),
+            (DiagnosticKind::AssignTypeMismatch, "assign-type-mismatch"),
+            (DiagnosticKind::NotDeclaredVariable, "undefined-global"),
+            (
+                DiagnosticKind::ArgumentCountMismatch,
+                "argument-count-mismatch",
+            ),
+            (DiagnosticKind::NilArithmetic, "need-check-nil"),
+            (DiagnosticKind::UnreachableCode, "unreachable-code"),
+            (DiagnosticKind::MixedTableLiteral, "mixed-table-literal"),
+            (
+                DiagnosticKind::UnbalancedAssignment,
+                "unbalanced-assignments",
+            ),
+            (DiagnosticKind::UnknownType, "undefined-doc-name"),
+            (DiagnosticKind::DanglingAnnotation, "dangling-annotation"),
+            (DiagnosticKind::MalformedAnnotation, "malformed-annotation"),
+            (
+                DiagnosticKind::UnknownAnnotationTarget,
+                "unknown-annotation-target",
+            ),
+            (
+                DiagnosticKind::ConflictingAnnotation,
+                "conflicting-annotation",
+            ),
+            (DiagnosticKind::UndefinedField, "undefined-field"),
+            (DiagnosticKind::UndefinedMethod, "undefined-field"),
+            (DiagnosticKind::PossiblyUndefinedMethod, "undefined-field"),
+            (
+                DiagnosticKind::SuspiciousComparison,
+                "suspicious-comparison",
+            ),
+            (DiagnosticKind::ConstantCondition, "constant-condition"),
+            (DiagnosticKind::ImplicitGlobal, "implicit-global"),
+            (DiagnosticKind::MissingModuleReturn, "missing-module-return"),
+            (DiagnosticKind::ShadowedBuiltin, "shadowed-builtin"),
+            (DiagnosticKind::BreakOutsideLoop, "break-outside-loop"),
+            (DiagnosticKind::YieldOutsideAsync, "yield-outside-async"),
+        ];
+        // no wildcard, a new kind doesn't compile until it is listed above
+        let listed = |kind: &DiagnosticKind| match kind {
+            DiagnosticKind::ParseError
+            | DiagnosticKind::InternalError
+            | DiagnosticKind::TypeMismatch
+            | DiagnosticKind::AssignTypeMismatch
+            | DiagnosticKind::NotDeclaredVariable
+            | DiagnosticKind::ArgumentCountMismatch
+            | DiagnosticKind::NilArithmetic
+            | DiagnosticKind::UnreachableCode
+            | DiagnosticKind::MixedTableLiteral
+            | DiagnosticKind::UnbalancedAssignment
+            | DiagnosticKind::UnknownType
+            | DiagnosticKind::DanglingAnnotation
+            | DiagnosticKind::MalformedAnnotation
+            | DiagnosticKind::UnknownAnnotationTarget
+            | DiagnosticKind::ConflictingAnnotation
+            | DiagnosticKind::UndefinedField
+            | DiagnosticKind::UndefinedMethod
+            | DiagnosticKind::PossiblyUndefinedMethod
+            | DiagnosticKind::SuspiciousComparison
+            | DiagnosticKind::ConstantCondition
+            | DiagnosticKind::ImplicitGlobal
+            | DiagnosticKind::MissingModuleReturn
+            | DiagnosticKind::ShadowedBuiltin
+            | DiagnosticKind::BreakOutsideLoop
+            | DiagnosticKind::YieldOutsideAsync => codes.iter().any(|(other, _)| other == kind),
+        };
+        for (kind, code) in codes.iter() {
+            assert!(listed(kind));
+            assert_eq!(kind.code(), *code, "code of {kind:?}");
+        }
+        // one kind for each code, but methods are fields in lua-language-server
+        let mut names: Vec<&str> = codes.iter().map(|(_, code)| *code).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), codes.len() - 2);
+    }
 }
@@ -1,4 +1,5 @@
 use thiserror::Error;
+use typua_span::Span;
 
 #[derive(Debug, Error)]
 pub enum TypuaError {
@@ -10,21 +11,71 @@ pub enum TypuaError {
     Bind(#[from] BindError),
     #[error("operation error: {0}")]
     Operation(#[from] OperationError),
-    #[error("failed to start tokio runtime: {source}")]
+    #[error("config error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("failed to read {path}: {source}\n  hint: {}", io_hint(source))]
+    SourceRead {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(
+        "failed to get the current directory: {source}\n  hint: run typua in an existing directory or pass the path to check"
+    )]
+    CurrentDir {
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(
+        "failed to start tokio runtime: {source}\n  hint: the system may be out of threads or memory"
+    )]
     Runtime {
         #[source]
         source: std::io::Error,
     },
 }
 
+impl TypuaError {
+    /// process exit code of the error category, following sysexits.h
+    ///   65 input data, 66 source file, 70 runtime, 71 current directory, 78 config
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            TypuaError::Parse(_)
+            | TypuaError::Annotation(_)
+            | TypuaError::Bind(_)
+            | TypuaError::Operation(_) => 65,
+            TypuaError::SourceRead { .. } => 66,
+            TypuaError::Runtime { .. } => 70,
+            TypuaError::CurrentDir { .. } => 71,
+            TypuaError::Config(_) => 78,
+        }
+    }
+}
+
+/// what to try when a file can't be read
+fn io_hint(source: &std::io::Error) -> &'static str {
+    match source.kind() {
+        std::io::ErrorKind::NotFound => "check that the path exists",
+        std::io::ErrorKind::PermissionDenied => {
+            "check that the file is readable by the current user"
+        }
+        std::io::ErrorKind::InvalidData => "the file must be encoded in UTF-8",
+        _ => "check that the path is accessible",
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ParseError {
-    #[error("Syntax Error")]
-    SyntaxError(String),
+    /// error reported by full_moon, at the range it gives
+    #[error("syntax error at {}:{}: {message}", span.start.line(), span.start.character())]
+    SyntaxError { message: String, span: Span },
     #[error("Invalid token")]
     InvalidToken(String),
     #[error("Unexpected occured")]
     UnexpectedOccured(String),
+    /// parser or binder panicked, on syntax not supported yet
+    #[error("{0}")]
+    Internal(String),
 }
 
 #[derive(Debug, Error)]
@@ -50,3 +101,37 @@ pub enum OperationError {
     #[error("Add operation failed")]
     AddFailed(String),
 }
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read {path}: {source}\n  hint: {}", io_hint(source))]
+    ReadFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid config {path}: {message}")]
+    InvalidConfig { path: String, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn source_read_message() {
+        let err = TypuaError::SourceRead {
+            path: "src/missing.lua".to_string(),
+            source: std::io::Error::from(std::io::ErrorKind::NotFound),
+        };
+        assert_eq!(
+            err.to_string(),
+            "failed to read src/missing.lua: entity not found\n  hint: check that the path exists"
+        );
+        assert_eq!(err.exit_code(), 66);
+        let err = TypuaError::Config(ConfigError::InvalidConfig {
+            path: "typua.toml".to_string(),
+            message: "unknown field".to_string(),
+        });
+        assert_eq!(err.exit_code(), 78);
+    }
+}
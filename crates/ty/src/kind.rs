@@ -1,20 +1,38 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{TypuaError, error::OperationError};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TypeKind {
     Unknown, // top
     Never,   // bottom
     Any,
     Nil,
     Number,
+    /// number without fraction, `1` or `---@type integer`
+    Integer,
     Boolean,
+    /// `true` or `false`
+    BooleanLiteral(bool),
     String,
+    /// `"a"`, the string of the value
+    StringLiteral(String),
     Table,
     Function {
+        /// type parameters, `fun<T>(x: T): T` => T
+        generics: Vec<String>,
+        /// names of the params in order, empty if not known
+        param_names: Vec<String>,
         params: Vec<TypeKind>,
         returns: Vec<TypeKind>,
+        /// names of the returns in order, `---@return T name`, empty if none is named
+        return_names: Vec<Option<String>>,
+        /// `...T` after the fixed returns
+        vararg: Option<Box<TypeKind>>,
     },
     Class,
+    /// class or alias referenced by name
+    Custom(String),
     Generic(String),
     Union(Vec<TypeKind>),
     Array(Box<TypeKind>),
@@ -26,6 +44,8 @@ pub enum TypeKind {
         key: Box<TypeKind>,
         val: Box<TypeKind>,
     },
+    /// table literal with named fields only, `{ x = 1 }` => `{ x: number }`
+    Record(Vec<(String, TypeKind)>),
 }
 
 impl TypeKind {
@@ -33,54 +53,424 @@ impl TypeKind {
     ///   true  => sub_ty is subtype of sup_ty
     ///   false => sub_ty is not subtype of sup_ty
     pub fn subtype(sub_ty: &TypeKind, sup_ty: &TypeKind) -> bool {
+        // every member of sub_ty must be subtype of sup_ty
+        if let TypeKind::Union(sub_tys) = sub_ty
+            && !matches!(sup_ty, TypeKind::Unknown | TypeKind::Any)
+        {
+            return sub_tys.iter().all(|ty| TypeKind::subtype(ty, sup_ty));
+        }
         match sup_ty {
             TypeKind::Unknown => true,
             TypeKind::Never => sub_ty == sup_ty,
-            TypeKind::Any => *sub_ty != TypeKind::Unknown,
+            TypeKind::Any => true,
             TypeKind::Nil => *sub_ty == TypeKind::Nil,
             TypeKind::Number => {
                 matches!(
                     *sub_ty,
-                    TypeKind::Number | TypeKind::Any | TypeKind::Unknown
+                    TypeKind::Number | TypeKind::Integer | TypeKind::Any | TypeKind::Unknown
+                )
+            }
+            TypeKind::Integer => {
+                matches!(
+                    *sub_ty,
+                    TypeKind::Integer | TypeKind::Any | TypeKind::Unknown
                 )
             }
             TypeKind::Boolean => {
                 matches!(
                     *sub_ty,
-                    TypeKind::Boolean | TypeKind::Any | TypeKind::Unknown
+                    TypeKind::Boolean
+                        | TypeKind::BooleanLiteral(_)
+                        | TypeKind::Any
+                        | TypeKind::Unknown
                 )
             }
+            TypeKind::BooleanLiteral(sup_value) => match sub_ty {
+                TypeKind::BooleanLiteral(sub_value) => sub_value == sup_value,
+                _ => matches!(*sub_ty, TypeKind::Any | TypeKind::Unknown),
+            },
             TypeKind::String => {
                 matches!(
                     *sub_ty,
-                    TypeKind::String | TypeKind::Any | TypeKind::Unknown
+                    TypeKind::String
+                        | TypeKind::StringLiteral(_)
+                        | TypeKind::Any
+                        | TypeKind::Unknown
                 )
             }
+            TypeKind::StringLiteral(sup_value) => match sub_ty {
+                TypeKind::StringLiteral(sub_value) => sub_value == sup_value,
+                _ => matches!(*sub_ty, TypeKind::Any | TypeKind::Unknown),
+            },
+            // boolean is true|false
+            TypeKind::Union(sup_tys) => {
+                sup_tys.iter().any(|ty| TypeKind::subtype(sub_ty, ty))
+                    || (*sub_ty == TypeKind::Boolean
+                        && sup_tys.contains(&TypeKind::BooleanLiteral(true))
+                        && sup_tys.contains(&TypeKind::BooleanLiteral(false)))
+            }
+            TypeKind::Table => matches!(
+                *sub_ty,
+                TypeKind::Table
+                    | TypeKind::Array(_)
+                    | TypeKind::Dict { .. }
+                    | TypeKind::KVTable { .. }
+                    | TypeKind::Record(_)
+                    | TypeKind::Custom(_)
+                    | TypeKind::Any
+                    | TypeKind::Unknown
+            ),
+            // table of unknown shape is accepted, so is a record since keys are not checked
+            TypeKind::Array(sup_elem) => match sub_ty {
+                TypeKind::Array(sub_elem) => TypeKind::subtype(sub_elem, sup_elem),
+                _ => matches!(
+                    *sub_ty,
+                    TypeKind::Table | TypeKind::Record(_) | TypeKind::Any | TypeKind::Unknown
+                ),
+            },
+            // params are contravariant, returns are covariant
+            //   the sub function must not require more params than the sup passes
+            //   unannotated returns are unknown and accepted
+            //   returns past the fixed ones come from the vararg, or are nil
+            TypeKind::Function {
+                params: sup_params,
+                returns: sup_returns,
+                vararg: sup_vararg,
+                ..
+            } => match sub_ty {
+                TypeKind::Function {
+                    params: sub_params,
+                    returns: sub_returns,
+                    vararg: sub_vararg,
+                    ..
+                } => {
+                    let sub_nth = |i: usize| {
+                        sub_returns
+                            .get(i)
+                            .or(sub_vararg.as_deref())
+                            .unwrap_or(&TypeKind::Nil)
+                    };
+                    let returns_match = sup_returns
+                        .iter()
+                        .enumerate()
+                        .all(|(i, sup)| TypeKind::subtype(sub_nth(i), sup));
+                    let vararg_match = match sup_vararg {
+                        Some(sup) => sub_returns
+                            .iter()
+                            .skip(sup_returns.len())
+                            .chain(sub_vararg.as_deref())
+                            .all(|sub| TypeKind::subtype(sub, sup)),
+                        None => true,
+                    };
+                    sub_params.len() <= sup_params.len()
+                        && sub_params
+                            .iter()
+                            .zip(sup_params.iter())
+                            .all(|(sub, sup)| TypeKind::subtype(sup, sub))
+                        && ((sub_returns.is_empty() && sub_vararg.is_none())
+                            || (returns_match && vararg_match))
+                }
+                _ => matches!(*sub_ty, TypeKind::Any | TypeKind::Unknown),
+            },
+            // fields of classes are not checked yet, any table is accepted
+            TypeKind::Custom(name) => match sub_ty {
+                TypeKind::Custom(sub_name) => sub_name == name,
+                _ => matches!(
+                    *sub_ty,
+                    TypeKind::Table | TypeKind::Record(_) | TypeKind::Any | TypeKind::Unknown
+                ),
+            },
+            // every field of sup_ty must be in sub_ty, extra fields are allowed
+            TypeKind::Record(sup_fields) => match sub_ty {
+                TypeKind::Record(sub_fields) => sup_fields.iter().all(|(name, sup)| {
+                    sub_fields
+                        .iter()
+                        .any(|(sub_name, sub)| sub_name == name && TypeKind::subtype(sub, sup))
+                }),
+                _ => matches!(*sub_ty, TypeKind::Table | TypeKind::Any | TypeKind::Unknown),
+            },
+            // every key and value of sub_ty must be of the key and value types
+            //   a record has string keys, an array has number keys
+            TypeKind::Dict { key, val } | TypeKind::KVTable { key, val } => match sub_ty {
+                TypeKind::Dict {
+                    key: sub_key,
+                    val: sub_val,
+                }
+                | TypeKind::KVTable {
+                    key: sub_key,
+                    val: sub_val,
+                } => TypeKind::subtype(sub_key, key) && TypeKind::subtype(sub_val, val),
+                TypeKind::Record(fields) => {
+                    TypeKind::subtype(&TypeKind::String, key)
+                        && fields.iter().all(|(_, ty)| TypeKind::subtype(ty, val))
+                }
+                TypeKind::Array(elem) => {
+                    TypeKind::subtype(&TypeKind::Number, key) && TypeKind::subtype(elem, val)
+                }
+                _ => matches!(*sub_ty, TypeKind::Table | TypeKind::Any | TypeKind::Unknown),
+            },
+            // type parameter not instantiated by a call, nothing is known about it
+            TypeKind::Generic(_) => true,
             _ => unimplemented!(),
         }
     }
+    /// the type with every part for which `f` returns Some replaced
+    ///   parts replaced are not visited further
+    pub fn replace(&self, f: &impl Fn(&TypeKind) -> Option<TypeKind>) -> TypeKind {
+        if let Some(ty) = f(self) {
+            return ty;
+        }
+        let boxed = |ty: &TypeKind| Box::new(ty.replace(f));
+        match self {
+            TypeKind::Function {
+                generics,
+                param_names,
+                params,
+                returns,
+                return_names,
+                vararg,
+            } => TypeKind::Function {
+                generics: generics.clone(),
+                param_names: param_names.clone(),
+                params: params.iter().map(|ty| ty.replace(f)).collect(),
+                returns: returns.iter().map(|ty| ty.replace(f)).collect(),
+                return_names: return_names.clone(),
+                vararg: vararg.as_deref().map(boxed),
+            },
+            TypeKind::Union(tys) => TypeKind::Union(tys.iter().map(|ty| ty.replace(f)).collect()),
+            TypeKind::Array(elem) => TypeKind::Array(boxed(elem)),
+            TypeKind::Dict { key, val } => TypeKind::Dict {
+                key: boxed(key),
+                val: boxed(val),
+            },
+            TypeKind::KVTable { key, val } => TypeKind::KVTable {
+                key: boxed(key),
+                val: boxed(val),
+            },
+            TypeKind::Record(fields) => TypeKind::Record(
+                fields
+                    .iter()
+                    .map(|(name, ty)| (name.clone(), ty.replace(f)))
+                    .collect(),
+            ),
+            ty => ty.clone(),
+        }
+    }
+    /// `true` and `false` => boolean, `"a"` => string, integer => number, others are unchanged
+    pub fn widen_literal(&self) -> TypeKind {
+        match self {
+            TypeKind::Integer => TypeKind::Number,
+            TypeKind::BooleanLiteral(_) => TypeKind::Boolean,
+            TypeKind::StringLiteral(_) => TypeKind::String,
+            ty => ty.clone(),
+        }
+    }
+    /// `ty` or nil, nil is always the last member so every optional type has one form
+    ///   number        => number|nil
+    ///   nil|string    => string|nil
+    ///   number|nil    => number|nil
+    pub fn optional(ty: TypeKind) -> TypeKind {
+        match ty.non_nil_part() {
+            TypeKind::Never => TypeKind::Nil,
+            TypeKind::Union(mut tys) => {
+                tys.push(TypeKind::Nil);
+                TypeKind::Union(tys)
+            }
+            ty => TypeKind::Union(vec![ty, TypeKind::Nil]),
+        }
+    }
+    /// nil is one of the values of the type
+    ///   number|nil, nil => true
+    ///   number, any     => false
+    pub fn is_optional(&self) -> bool {
+        match self {
+            TypeKind::Nil => true,
+            TypeKind::Union(tys) => tys.iter().any(|ty| ty.is_optional()),
+            _ => false,
+        }
+    }
+    /// the type without nil, nested unions are flattened
+    ///   number|nil          => number
+    ///   (number|nil)|string => number|string
+    ///   nil                 => never
+    pub fn non_nil_part(&self) -> TypeKind {
+        match self {
+            TypeKind::Nil => TypeKind::Never,
+            TypeKind::Union(tys) => tys
+                .iter()
+                .map(|ty| ty.non_nil_part())
+                .filter(|ty| *ty != TypeKind::Never)
+                .fold(TypeKind::Never, |acc, ty| acc.union(&ty)),
+            ty => ty.clone(),
+        }
+    }
+    /// the values of the type which are truthy, `a or b` is a if it is one of them
+    ///   string|nil    => string
+    ///   boolean|nil   => true
+    ///   false|nil     => never
+    pub fn truthy_part(&self) -> TypeKind {
+        self.non_nil_part()
+            .exclude(&TypeKind::BooleanLiteral(false))
+    }
+    /// the values of the type which are falsy, `a and b` is a if it is one of them
+    ///   string|nil    => nil
+    ///   boolean       => false
+    ///   any           => any
+    pub fn falsy_part(&self) -> TypeKind {
+        match self {
+            TypeKind::Nil | TypeKind::BooleanLiteral(false) | TypeKind::Any | TypeKind::Unknown => {
+                self.clone()
+            }
+            TypeKind::Boolean => TypeKind::BooleanLiteral(false),
+            TypeKind::Union(tys) => tys
+                .iter()
+                .map(|ty| ty.falsy_part())
+                .filter(|ty| *ty != TypeKind::Never)
+                .fold(TypeKind::Never, |acc, ty| acc.union(&ty)),
+            _ => TypeKind::Never,
+        }
+    }
+    /// remove `ty` from the type
+    ///   number|string, string => number
+    ///   string, string        => never
+    ///   boolean, true         => false
+    pub fn exclude(&self, ty: &TypeKind) -> TypeKind {
+        match (self, ty) {
+            _ if self == ty => TypeKind::Never,
+            (TypeKind::Boolean, TypeKind::BooleanLiteral(value)) => {
+                TypeKind::BooleanLiteral(!value)
+            }
+            (TypeKind::Union(tys), _) => {
+                let mut rest: Vec<TypeKind> = tys
+                    .iter()
+                    .map(|t| t.exclude(ty))
+                    .filter(|t| *t != TypeKind::Never)
+                    .collect();
+                match rest.len() {
+                    0 => TypeKind::Never,
+                    1 => rest.remove(0),
+                    _ => TypeKind::Union(rest),
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+    /// union of the type and `ty`, members already included are not repeated
+    ///   number|nil, string => number|nil|string
+    ///   never, string      => string
+    pub fn union(&self, ty: &TypeKind) -> TypeKind {
+        let mut tys = match self {
+            TypeKind::Never => Vec::new(),
+            TypeKind::Union(tys) => tys.clone(),
+            _ => vec![self.clone()],
+        };
+        let added = match ty {
+            TypeKind::Union(added) => added.clone(),
+            _ => vec![ty.clone()],
+        };
+        for ty in added {
+            if !tys.contains(&ty) {
+                tys.push(ty);
+            }
+        }
+        match tys.len() {
+            0 => TypeKind::Never,
+            1 => tys.remove(0),
+            _ => TypeKind::Union(tys),
+        }
+    }
+    /// every value of the type is truthy, only nil and false are falsy in lua
+    ///   number, string|table => true
+    ///   boolean, number|nil  => false
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            TypeKind::Number
+            | TypeKind::Integer
+            | TypeKind::String
+            | TypeKind::StringLiteral(_)
+            | TypeKind::BooleanLiteral(true)
+            | TypeKind::Table
+            | TypeKind::Function { .. }
+            | TypeKind::Array(_)
+            | TypeKind::Dict { .. }
+            | TypeKind::KVTable { .. }
+            | TypeKind::Record(_) => true,
+            TypeKind::Union(tys) => tys.iter().all(TypeKind::is_truthy),
+            _ => false,
+        }
+    }
+    /// every value of the type is nil or false
+    ///   nil, false|nil => true
+    ///   boolean        => false
+    pub fn is_falsy(&self) -> bool {
+        match self {
+            TypeKind::Nil | TypeKind::BooleanLiteral(false) => true,
+            TypeKind::Union(tys) => tys.iter().all(TypeKind::is_falsy),
+            _ => false,
+        }
+    }
+    /// some value can be of both types, types not known enough are assumed to overlap
+    ///   number, number|nil => true
+    ///   number, string     => false
+    ///   "a"|"b", "c"       => false
+    pub fn overlaps(&self, ty: &TypeKind) -> bool {
+        let members = |ty: &TypeKind| match ty {
+            TypeKind::Union(tys) => tys.clone(),
+            ty => vec![ty.clone()],
+        };
+        let rhs = members(ty);
+        members(self).iter().any(|lhs| {
+            rhs.iter().any(|rhs| match (lhs, rhs) {
+                (TypeKind::StringLiteral(lhs), TypeKind::StringLiteral(rhs)) => lhs == rhs,
+                (TypeKind::BooleanLiteral(lhs), TypeKind::BooleanLiteral(rhs)) => lhs == rhs,
+                _ => match (lhs.lua_types(), rhs.lua_types()) {
+                    (Some(lhs), Some(rhs)) => lhs.iter().any(|name| rhs.contains(name)),
+                    _ => true,
+                },
+            })
+        })
+    }
+    /// names `type()` returns for values of the type, None => any of them
+    ///   a class or alias name is not resolved here, so it can be anything
+    fn lua_types(&self) -> Option<Vec<&'static str>> {
+        match self {
+            TypeKind::Nil => Some(vec!["nil"]),
+            TypeKind::Number | TypeKind::Integer => Some(vec!["number"]),
+            TypeKind::Boolean | TypeKind::BooleanLiteral(_) => Some(vec!["boolean"]),
+            TypeKind::String | TypeKind::StringLiteral(_) => Some(vec!["string"]),
+            TypeKind::Table
+            | TypeKind::Array(_)
+            | TypeKind::Dict { .. }
+            | TypeKind::KVTable { .. }
+            | TypeKind::Record(_) => Some(vec!["table"]),
+            TypeKind::Function { .. } => Some(vec!["function"]),
+            TypeKind::Union(tys) => tys
+                .iter()
+                .map(TypeKind::lua_types)
+                .collect::<Option<Vec<_>>>()
+                .map(|names| names.concat()),
+            _ => None,
+        }
+    }
+    /// any and unknown operands are not checked, the sum is any
+    ///   a union is added member by member, classes, tables and functions can't be added
     pub fn can_add(sub_ty: &TypeKind, sup_ty: &TypeKind) -> Result<TypeKind, TypuaError> {
+        if matches!(sub_ty, TypeKind::Any | TypeKind::Unknown) {
+            return Ok(TypeKind::Any);
+        }
         match sup_ty {
-            TypeKind::Unknown => Err(TypuaError::Operation(OperationError::AddFailed(
-                "unknown".to_string(),
-            ))),
+            TypeKind::Any | TypeKind::Unknown => Ok(TypeKind::Any),
             TypeKind::Never => Err(TypuaError::Operation(OperationError::AddFailed(
                 "never".to_string(),
             ))),
-            TypeKind::Any => {
-                if *sub_ty != TypeKind::Unknown {
-                    Ok(TypeKind::Any)
-                } else {
-                    Err(TypuaError::Operation(OperationError::AddFailed(
-                        "any".to_string(),
-                    )))
-                }
-            }
             TypeKind::Nil => Err(TypuaError::Operation(OperationError::AddFailed(
                 "nil".to_string(),
             ))),
-            TypeKind::Number => {
-                if *sub_ty == TypeKind::Number {
+            // integer + integer stays integer
+            TypeKind::Number | TypeKind::Integer => {
+                if *sub_ty == TypeKind::Integer && *sup_ty == TypeKind::Integer {
+                    Ok(TypeKind::Integer)
+                } else if matches!(*sub_ty, TypeKind::Number | TypeKind::Integer) {
                     Ok(TypeKind::Number)
                 } else {
                     Err(TypuaError::Operation(OperationError::AddFailed(
@@ -88,13 +478,43 @@ impl TypeKind {
                     )))
                 }
             }
-            TypeKind::Boolean => Err(TypuaError::Operation(OperationError::AddFailed(
-                "boolean".to_string(),
-            ))),
-            TypeKind::String => Err(TypuaError::Operation(OperationError::AddFailed(
-                "string".to_string(),
+            TypeKind::Boolean | TypeKind::BooleanLiteral(_) => Err(TypuaError::Operation(
+                OperationError::AddFailed("boolean".to_string()),
+            )),
+            TypeKind::String | TypeKind::StringLiteral(_) => Err(TypuaError::Operation(
+                OperationError::AddFailed("string".to_string()),
+            )),
+            TypeKind::Union(tys) => {
+                let sums = tys
+                    .iter()
+                    .map(|ty| TypeKind::can_add(sub_ty, ty))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| {
+                        TypuaError::Operation(OperationError::AddFailed(sup_ty.to_string()))
+                    })?;
+                if sums.iter().all(|sum| *sum == TypeKind::Integer) {
+                    Ok(TypeKind::Integer)
+                } else if sums.contains(&TypeKind::Any) {
+                    Ok(TypeKind::Any)
+                } else {
+                    Ok(TypeKind::Number)
+                }
+            }
+            _ => Err(TypuaError::Operation(OperationError::AddFailed(
+                sup_ty.to_string(),
             ))),
-            _ => unimplemented!(),
+        }
+    }
+}
+
+impl TypeKind {
+    /// the type written inside another one, in parentheses if its parts would be read apart
+    ///   (boolean|number)[] is an array of union, boolean|number[] is not
+    ///   (fun(): number)|nil is optional, fun(): number|nil returns the union
+    pub fn display_parenthesized(&self) -> String {
+        match self {
+            TypeKind::Union(_) | TypeKind::Function { .. } => format!("({self})"),
+            _ => self.to_string(),
         }
     }
 }
@@ -107,26 +527,78 @@ impl std::fmt::Display for TypeKind {
             TypeKind::Never => "never".to_string(),
             TypeKind::Nil => "nil".to_string(),
             TypeKind::Number => "number".to_string(),
+            TypeKind::Integer => "integer".to_string(),
             TypeKind::Boolean => "boolean".to_string(),
+            TypeKind::BooleanLiteral(value) => value.to_string(),
             TypeKind::String => "string".to_string(),
+            TypeKind::StringLiteral(value) => {
+                format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+            }
             TypeKind::Table => "table".to_string(),
-            TypeKind::Function { params, returns } => {
-                let params_string: Vec<String> = params.iter().map(|ty| ty.to_string()).collect();
-                let returns_string: Vec<String> = returns.iter().map(|ty| ty.to_string()).collect();
-                format!(
-                    "fun({})->{}",
-                    params_string.join(","),
-                    returns_string.join(",")
-                )
+            // the same form as in annotations, `fun<T>(x: T, n: number): T, ...string`
+            //   named returns are in parentheses, `fun(): (ok: boolean, err: string|nil)`
+            TypeKind::Function {
+                generics,
+                param_names,
+                params,
+                returns,
+                return_names,
+                vararg,
+            } => {
+                let generics_string = if generics.is_empty() {
+                    String::new()
+                } else {
+                    format!("<{}>", generics.join(", "))
+                };
+                let params_string: Vec<String> = params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| match param_names.get(i) {
+                        Some(name) => format!("{}: {}", name, ty),
+                        None => ty.to_string(),
+                    })
+                    .collect();
+                let returns_string: Vec<String> = returns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| match return_names.get(i) {
+                        Some(Some(name)) => format!("{}: {}", name, ty),
+                        _ => ty.to_string(),
+                    })
+                    .chain(vararg.iter().map(|ty| format!("...{}", ty)))
+                    .collect();
+                let named = return_names.iter().any(Option::is_some);
+                match (returns_string.is_empty(), named) {
+                    (true, _) => format!("fun{}({})", generics_string, params_string.join(", ")),
+                    (false, false) => format!(
+                        "fun{}({}): {}",
+                        generics_string,
+                        params_string.join(", "),
+                        returns_string.join(", ")
+                    ),
+                    (false, true) => format!(
+                        "fun{}({}): ({})",
+                        generics_string,
+                        params_string.join(", "),
+                        returns_string.join(", ")
+                    ),
+                }
             }
             TypeKind::Class => "class".to_string(),
+            TypeKind::Custom(name) => name.clone(),
             TypeKind::Generic(s) => s.clone(),
             TypeKind::Union(types) => {
-                let types_string: Vec<String> = types.iter().map(|ty| ty.to_string()).collect();
+                let types_string: Vec<String> = types
+                    .iter()
+                    .map(|ty| match ty {
+                        TypeKind::Function { .. } => ty.display_parenthesized(),
+                        _ => ty.to_string(),
+                    })
+                    .collect();
                 types_string.join("|")
             }
             TypeKind::Array(ty) => {
-                format!("{}[]", ty)
+                format!("{}[]", ty.display_parenthesized())
             }
             TypeKind::Dict { key, val } => {
                 format!("{{ [{}]: {} }}", key, val)
@@ -134,7 +606,110 @@ impl std::fmt::Display for TypeKind {
             TypeKind::KVTable { key, val } => {
                 format!("table<{}, {}>", key, val)
             }
+            TypeKind::Record(fields) => {
+                let fields_string: Vec<String> = fields
+                    .iter()
+                    .map(|(name, ty)| format!("{}: {}", name, ty))
+                    .collect();
+                format!("{{ {} }}", fields_string.join(", "))
+            }
         };
         write!(f, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn add_other_kinds() {
+        for ty in [
+            TypeKind::Custom("Point".to_string()),
+            TypeKind::Table,
+            TypeKind::Record(vec![("x".to_string(), TypeKind::Number)]),
+            TypeKind::Array(Box::new(TypeKind::Number)),
+            TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]),
+        ] {
+            assert!(TypeKind::can_add(&TypeKind::Integer, &ty).is_err(), "{ty}");
+        }
+        assert_eq!(
+            TypeKind::can_add(
+                &TypeKind::Integer,
+                &TypeKind::Union(vec![TypeKind::Integer, TypeKind::Number])
+            )
+            .ok(),
+            Some(TypeKind::Number)
+        );
+    }
+    #[test]
+    fn optional_form() {
+        let optional = TypeKind::optional(TypeKind::Number);
+        assert_eq!(optional.to_string(), "number|nil");
+        assert!(optional.is_optional());
+        assert!(!TypeKind::Number.is_optional());
+        // built in any order, the result is the same
+        assert_eq!(TypeKind::optional(optional.clone()), optional);
+        assert_eq!(
+            TypeKind::optional(TypeKind::Union(vec![TypeKind::Nil, TypeKind::Number])),
+            optional
+        );
+        assert_eq!(TypeKind::optional(TypeKind::Nil), TypeKind::Nil);
+    }
+    #[test]
+    fn parenthesized_array_element() {
+        let union = TypeKind::Union(vec![TypeKind::Boolean, TypeKind::Number]);
+        assert_eq!(
+            TypeKind::Array(Box::new(union.clone())).to_string(),
+            "(boolean|number)[]"
+        );
+        assert_eq!(
+            TypeKind::Array(Box::new(TypeKind::Number)).to_string(),
+            "number[]"
+        );
+        assert_eq!(union.display_parenthesized(), "(boolean|number)");
+        let function = TypeKind::Function {
+            generics: Vec::new(),
+            param_names: Vec::new(),
+            params: Vec::new(),
+            returns: vec![TypeKind::Number],
+            return_names: Vec::new(),
+            vararg: None,
+        };
+        assert_eq!(
+            TypeKind::optional(function).to_string(),
+            "(fun(): number)|nil"
+        );
+    }
+    #[test]
+    fn non_nil_part_of_nested_union() {
+        let ty = TypeKind::Union(vec![
+            TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]),
+            TypeKind::String,
+            TypeKind::Union(vec![TypeKind::Nil, TypeKind::Number]),
+        ]);
+        assert!(ty.is_optional());
+        assert_eq!(
+            ty.non_nil_part(),
+            TypeKind::Union(vec![TypeKind::Number, TypeKind::String])
+        );
+        assert_eq!(TypeKind::Nil.non_nil_part(), TypeKind::Never);
+        assert_eq!(
+            TypeKind::optional(TypeKind::Boolean).non_nil_part(),
+            TypeKind::Boolean
+        );
+    }
+    #[test]
+    fn truthy_and_falsy_parts() {
+        let ty = TypeKind::Union(vec![TypeKind::String, TypeKind::Boolean, TypeKind::Nil]);
+        assert_eq!(
+            ty.truthy_part(),
+            TypeKind::Union(vec![TypeKind::String, TypeKind::BooleanLiteral(true)])
+        );
+        assert_eq!(
+            ty.falsy_part(),
+            TypeKind::Union(vec![TypeKind::BooleanLiteral(false), TypeKind::Nil])
+        );
+        assert_eq!(TypeKind::Number.falsy_part(), TypeKind::Never);
+        assert_eq!(TypeKind::Nil.truthy_part(), TypeKind::Never);
+    }
+}
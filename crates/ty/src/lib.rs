@@ -1,6 +1,7 @@
 pub mod kind;
 pub mod error;
 pub mod diagnostic;
+pub mod message;
 
 pub use kind::TypeKind;
-pub use error::{TypuaError, ParseError, AnnotationError, BindError};
+pub use error::{TypuaError, ParseError, AnnotationError, BindError, ConfigError};
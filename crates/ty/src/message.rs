@@ -0,0 +1,354 @@
+use crate::TypeKind;
+use crate::diagnostic::DiagnosticKind;
+
+/// `n` and `word`, with an `s` unless there is exactly one, `1 file` or `2 files`
+pub fn plural(n: usize, word: &str) -> String {
+    format!("{n} {word}{}", if n == 1 { "" } else { "s" })
+}
+
+/// what a diagnostic says, with the values it mentions
+///   the wording of every message is written in `Display`, so it can be changed in one place
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// syntax error reported by the parser
+    SyntaxError {
+        message: String,
+    },
+    /// the parser or the checker panicked on the file, `reason` is the panic message
+    InternalError {
+        reason: String,
+    },
+    DanglingAnnotation,
+    UnreachableCode,
+    MixedTableLiteral,
+    ConflictingAnnotation,
+    /// `---@cast x` where x is not declared
+    UnknownCastTarget {
+        name: String,
+    },
+    /// `---@type T x` where the statement declares no x
+    UnknownNamedTarget {
+        name: String,
+    },
+    UnbalancedAssignment {
+        name: String,
+    },
+    ImplicitGlobal {
+        name: String,
+    },
+    MissingModuleReturn {
+        module: String,
+    },
+    NotDeclared {
+        name: String,
+    },
+    ShadowedBuiltin {
+        name: String,
+    },
+    BreakOutsideLoop,
+    YieldOutsideAsync,
+    UnknownType {
+        name: String,
+    },
+    MalformedAnnotation {
+        reason: String,
+    },
+    KeyMismatch {
+        key: TypeKind,
+        expected: TypeKind,
+    },
+    AssignMismatch {
+        value: TypeKind,
+        expected: TypeKind,
+    },
+    /// literal of kind `literal`, e.g. "string", which can never be `expected`
+    LiteralMismatch {
+        literal: String,
+        expected: TypeKind,
+    },
+    ArgumentMismatch {
+        arg: TypeKind,
+        param: TypeKind,
+    },
+    ArgumentCount {
+        expected: usize,
+        found: usize,
+    },
+    CastMismatch {
+        value: TypeKind,
+        target: TypeKind,
+    },
+    ReturnMismatch {
+        value: TypeKind,
+        expected: TypeKind,
+    },
+    FunctionOwnerMismatch {
+        owner: TypeKind,
+    },
+    /// operand of `operation`, e.g. "floor division" or "concatenation", of a type it can't take
+    NumericOperand {
+        operand: TypeKind,
+        operation: String,
+    },
+    AddMismatch {
+        lhs: TypeKind,
+        rhs: TypeKind,
+    },
+    /// `<`, `<=`, `>` or `>=` on operands other than two numbers or two strings
+    CompareMismatch {
+        lhs: TypeKind,
+        rhs: TypeKind,
+    },
+    NilArithmetic {
+        operand: TypeKind,
+    },
+    NeverEqual {
+        lhs: TypeKind,
+        rhs: TypeKind,
+    },
+    ConstantCondition {
+        condition: TypeKind,
+        always: bool,
+    },
+    UndefinedField {
+        ty: TypeKind,
+        field: String,
+    },
+    /// `exact` => the class is known to have no such method
+    UndefinedMethod {
+        class: String,
+        method: String,
+        exact: bool,
+    },
+}
+
+impl Message {
+    pub fn kind(&self) -> DiagnosticKind {
+        match self {
+            Message::SyntaxError { .. } => DiagnosticKind::ParseError,
+            Message::InternalError { .. } => DiagnosticKind::InternalError,
+            Message::DanglingAnnotation => DiagnosticKind::DanglingAnnotation,
+            Message::UnreachableCode => DiagnosticKind::UnreachableCode,
+            Message::MixedTableLiteral => DiagnosticKind::MixedTableLiteral,
+            Message::ConflictingAnnotation => DiagnosticKind::ConflictingAnnotation,
+            Message::UnknownCastTarget { .. } | Message::UnknownNamedTarget { .. } => {
+                DiagnosticKind::UnknownAnnotationTarget
+            }
+            Message::UnbalancedAssignment { .. } => DiagnosticKind::UnbalancedAssignment,
+            Message::ImplicitGlobal { .. } => DiagnosticKind::ImplicitGlobal,
+            Message::MissingModuleReturn { .. } => DiagnosticKind::MissingModuleReturn,
+            Message::NotDeclared { .. } => DiagnosticKind::NotDeclaredVariable,
+            Message::ShadowedBuiltin { .. } => DiagnosticKind::ShadowedBuiltin,
+            Message::BreakOutsideLoop => DiagnosticKind::BreakOutsideLoop,
+            Message::YieldOutsideAsync => DiagnosticKind::YieldOutsideAsync,
+            Message::UnknownType { .. } => DiagnosticKind::UnknownType,
+            Message::MalformedAnnotation { .. } => DiagnosticKind::MalformedAnnotation,
+            Message::LiteralMismatch { .. } => DiagnosticKind::AssignTypeMismatch,
+            Message::KeyMismatch { .. }
+            | Message::AssignMismatch { .. }
+            | Message::ArgumentMismatch { .. }
+            | Message::CastMismatch { .. }
+            | Message::ReturnMismatch { .. }
+            | Message::FunctionOwnerMismatch { .. }
+            | Message::NumericOperand { .. }
+            | Message::AddMismatch { .. }
+            | Message::CompareMismatch { .. } => DiagnosticKind::TypeMismatch,
+            Message::ArgumentCount { .. } => DiagnosticKind::ArgumentCountMismatch,
+            Message::NilArithmetic { .. } => DiagnosticKind::NilArithmetic,
+            Message::NeverEqual { .. } => DiagnosticKind::SuspiciousComparison,
+            Message::ConstantCondition { .. } => DiagnosticKind::ConstantCondition,
+            Message::UndefinedField { .. } => DiagnosticKind::UndefinedField,
+            Message::UndefinedMethod { exact: true, .. } => DiagnosticKind::UndefinedMethod,
+            Message::UndefinedMethod { exact: false, .. } => {
+                DiagnosticKind::PossiblyUndefinedMethod
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Message::SyntaxError { message } => write!(f, "syntax error: {message}"),
+            Message::InternalError { reason } => {
+                write!(f, "internal error: {reason}, the file is not checked")
+            }
+            Message::DanglingAnnotation => write!(f, "annotation is not attached to any statement"),
+            Message::UnreachableCode => write!(f, "unreachable code"),
+            Message::MixedTableLiteral => write!(f, "keyed field in array table literal"),
+            Message::ConflictingAnnotation => {
+                write!(f, "`---@type` is already given, this one is ignored")
+            }
+            Message::UnknownCastTarget { name } => write!(f, "`{name}` is not declared"),
+            Message::UnknownNamedTarget { name } => {
+                write!(f, "`{name}` is not declared by this statement")
+            }
+            Message::UnbalancedAssignment { name } => write!(f, "no value is assigned to `{name}`"),
+            Message::ImplicitGlobal { name } => {
+                write!(f, "assignment to undeclared global `{name}`")
+            }
+            Message::MissingModuleReturn { module } => {
+                write!(
+                    f,
+                    "module `{module}` returns no value, `require` gets `true`"
+                )
+            }
+            Message::NotDeclared { name } => write!(f, "'{name}' is not declared"),
+            Message::BreakOutsideLoop => write!(f, "`break` outside a loop"),
+            Message::YieldOutsideAsync => {
+                write!(f, "`coroutine.yield` in a function not marked `---@async`")
+            }
+            Message::ShadowedBuiltin { name } => {
+                write!(
+                    f,
+                    "local `{name}` shadows the builtin global of the same name"
+                )
+            }
+            Message::UnknownType { name } => write!(f, "unknown type `{name}`"),
+            Message::MalformedAnnotation { reason } => write!(f, "malformed annotation, {reason}"),
+            Message::KeyMismatch { key, expected } => {
+                write!(f, "cannot use `{key}` as `{expected}` key")
+            }
+            Message::AssignMismatch { value, expected } => {
+                write!(f, "cannot assign `{value}` to `{expected}`")
+            }
+            Message::LiteralMismatch { literal, expected } => {
+                write!(f, "cannot annotate {literal} literal as `{expected}`")
+            }
+            Message::ArgumentMismatch { arg, param } => {
+                write!(f, "cannot pass `{arg}` to parameter of type `{param}`")
+            }
+            Message::ArgumentCount { expected, found } => {
+                write!(
+                    f,
+                    "expected {}, found {found}",
+                    plural(*expected, "argument")
+                )
+            }
+            Message::CastMismatch { value, target } => {
+                write!(f, "cannot cast `{value}` to `{target}`")
+            }
+            Message::ReturnMismatch { value, expected } => {
+                write!(f, "cannot return `{value}` as `{expected}`")
+            }
+            Message::FunctionOwnerMismatch { owner } => {
+                write!(f, "cannot declare a function in `{owner}`")
+            }
+            Message::NumericOperand { operand, operation } => {
+                write!(f, "cannot use `{operand}` in {operation}")
+            }
+            Message::AddMismatch { lhs, rhs } => write!(f, "cannot add `{lhs}` and `{rhs}`"),
+            Message::CompareMismatch { lhs, rhs } => {
+                write!(f, "cannot compare `{lhs}` with `{rhs}`")
+            }
+            Message::NilArithmetic { operand } => write!(
+                f,
+                "operand of type `{operand}` may be nil, check it for nil first"
+            ),
+            Message::NeverEqual { lhs, rhs } => write!(f, "`{lhs}` and `{rhs}` are never equal"),
+            Message::ConstantCondition { condition, always } => {
+                write!(f, "condition of type `{condition}` is always {always}")
+            }
+            Message::UndefinedField { ty, field } => write!(f, "`{ty}` has no field `{field}`"),
+            Message::UndefinedMethod {
+                class,
+                method,
+                exact: true,
+            } => write!(f, "`{class}` has no method `{method}`"),
+            Message::UndefinedMethod {
+                class,
+                method,
+                exact: false,
+            } => write!(
+                f,
+                "`{class}` declares no method `{method}`, it may be added elsewhere"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn interpolated_messages() {
+        let cases = [
+            (
+                Message::UnknownType {
+                    name: "Persn".to_string(),
+                },
+                DiagnosticKind::UnknownType,
+                "unknown type `Persn`",
+            ),
+            (
+                Message::ArgumentMismatch {
+                    arg: TypeKind::String,
+                    param: TypeKind::Number,
+                },
+                DiagnosticKind::TypeMismatch,
+                "cannot pass `string` to parameter of type `number`",
+            ),
+            (
+                Message::ArgumentCount {
+                    expected: 2,
+                    found: 1,
+                },
+                DiagnosticKind::ArgumentCountMismatch,
+                "expected 2 arguments, found 1",
+            ),
+            (
+                Message::ArgumentCount {
+                    expected: 1,
+                    found: 2,
+                },
+                DiagnosticKind::ArgumentCountMismatch,
+                "expected 1 argument, found 2",
+            ),
+            (
+                Message::LiteralMismatch {
+                    literal: "string".to_string(),
+                    expected: TypeKind::Number,
+                },
+                DiagnosticKind::AssignTypeMismatch,
+                "cannot annotate string literal as `number`",
+            ),
+            (
+                Message::NilArithmetic {
+                    operand: TypeKind::Union(vec![TypeKind::Number, TypeKind::Nil]),
+                },
+                DiagnosticKind::NilArithmetic,
+                "operand of type `number|nil` may be nil, check it for nil first",
+            ),
+            (
+                Message::ConstantCondition {
+                    condition: TypeKind::Nil,
+                    always: false,
+                },
+                DiagnosticKind::ConstantCondition,
+                "condition of type `nil` is always false",
+            ),
+            (
+                Message::UndefinedMethod {
+                    class: "Player".to_string(),
+                    method: "fly".to_string(),
+                    exact: true,
+                },
+                DiagnosticKind::UndefinedMethod,
+                "`Player` has no method `fly`",
+            ),
+            (
+                Message::UndefinedMethod {
+                    class: "Player".to_string(),
+                    method: "fly".to_string(),
+                    exact: false,
+                },
+                DiagnosticKind::PossiblyUndefinedMethod,
+                "`Player` declares no method `fly`, it may be added elsewhere",
+            ),
+        ];
+        for (message, kind, text) in cases {
+            assert_eq!(message.kind(), kind);
+            assert_eq!(message.to_string(), text);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use typua_config::WorkspaceConfig;
+
+/// directories never checked
+pub const DEFAULT_IGNORE_DIRS: &[&str] = &[".git"];
+
+/// collect lua files under `root`
+///   - directories in `DEFAULT_IGNORE_DIRS` and `workspace.ignore_dir` are skipped
+///   - files matched by `.gitignore` are skipped when `workspace.respect_gitignore` is set
+///
+/// if `root` is a file, it is returned as is.
+pub fn collect_source_files(root: &Path, workspace: &WorkspaceConfig) -> Vec<PathBuf> {
+    if root.is_file() {
+        return vec![root.to_path_buf()];
+    }
+    let ignore_dirs: Vec<String> = DEFAULT_IGNORE_DIRS
+        .iter()
+        .map(|d| d.to_string())
+        .chain(workspace.ignore_dir.iter().cloned())
+        .collect();
+    let mut files: Vec<PathBuf> = WalkBuilder::new(root)
+        .standard_filters(false)
+        .git_ignore(workspace.respect_gitignore)
+        .require_git(false)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            !(is_dir
+                && entry.depth() > 0
+                && ignore_dirs
+                    .iter()
+                    .any(|d| entry.file_name().to_str() == Some(d.as_str())))
+        })
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lua"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    fn setup() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join(".git/hook.lua"), "").unwrap();
+        fs::write(root.join("src/main.lua"), "").unwrap();
+        fs::write(root.join("src/readme.md"), "").unwrap();
+        fs::write(root.join("build/gen.lua"), "").unwrap();
+        fs::write(root.join("generated.lua"), "").unwrap();
+        fs::write(root.join(".gitignore"), "generated.lua\n").unwrap();
+        dir
+    }
+    fn relative(root: &Path, files: Vec<PathBuf>) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| f.strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+    #[test]
+    fn default_ignore_dirs() {
+        let dir = setup();
+        let files = collect_source_files(dir.path(), &WorkspaceConfig::default());
+        assert_eq!(
+            relative(dir.path(), files),
+            vec!["build/gen.lua", "generated.lua", "src/main.lua"]
+        );
+        let workspace = WorkspaceConfig {
+            ignore_dir: vec!["build".to_string()],
+            respect_gitignore: false,
+            ..WorkspaceConfig::default()
+        };
+        let files = collect_source_files(dir.path(), &workspace);
+        assert_eq!(
+            relative(dir.path(), files),
+            vec!["generated.lua", "src/main.lua"]
+        );
+    }
+    #[test]
+    fn respect_gitignore() {
+        let dir = setup();
+        let workspace = WorkspaceConfig {
+            ignore_dir: Vec::new(),
+            respect_gitignore: true,
+            ..WorkspaceConfig::default()
+        };
+        let files = collect_source_files(dir.path(), &workspace);
+        assert_eq!(
+            relative(dir.path(), files),
+            vec!["build/gen.lua", "src/main.lua"]
+        );
+    }
+}